@@ -0,0 +1,114 @@
+use crate::files::{self, Encoding};
+use logsim::presets::{ChipPreset, CombGatePreset, DevicePreset, PresetData, PresetSource};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "usage: logsim-native --pack <board> --out <preset> [--combinational] [--max-inputs <n>] [--name <name>] [--cat <category>]";
+
+/// The arguments of `--pack`, for packing a board into a preset without opening the window
+pub struct PackArgs {
+    pub board: PathBuf,
+    pub out: PathBuf,
+    pub combinational: bool,
+    pub max_inputs: usize,
+    pub name: Option<String>,
+    pub cat: String,
+}
+impl PackArgs {
+    /// Returns None if `--pack` wasn't passed
+    pub fn parse(args: &[String]) -> Option<Result<Self, String>> {
+        match args.iter().any(|arg| arg == "--pack") {
+            true => Some(Self::parse_pack(args)),
+            false => None,
+        }
+    }
+    fn parse_pack(args: &[String]) -> Result<Self, String> {
+        let mut board = None;
+        let mut out = None;
+        let mut combinational = false;
+        let mut max_inputs = CombGatePreset::DEFAULT_MAX_INPUTS;
+        let mut name = None;
+        let mut cat = String::from("Basic");
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .cloned()
+                    .ok_or(format!("missing value for {arg}"))
+            };
+            match arg.as_str() {
+                "--pack" => board = Some(PathBuf::from(value()?)),
+                "--out" => out = Some(PathBuf::from(value()?)),
+                "--name" => name = Some(value()?),
+                "--cat" => cat = value()?,
+                "--combinational" => combinational = true,
+                "--max-inputs" => {
+                    max_inputs = value()?
+                        .parse()
+                        .map_err(|_| format!("invalid value for {arg}"))?
+                }
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        let (Some(board), Some(out)) = (board, out) else {
+            return Err(String::from("--pack and --out need a path"));
+        };
+        Ok(Self {
+            board,
+            out,
+            combinational,
+            max_inputs,
+            name,
+            cat,
+        })
+    }
+}
+
+/// Packs the board and writes the preset, returns the exit code
+pub fn pack(args: PackArgs) -> i32 {
+    let mut board = match files::import_board(&args.board) {
+        Ok(board) => board,
+        Err(err) => {
+            err.log();
+            return 1;
+        }
+    };
+    let data = match args.combinational {
+        true => match CombGatePreset::from_board(&mut board, args.max_inputs) {
+            Ok(comb_gate) => PresetData::CombGate(comb_gate),
+            Err(err) => {
+                eprintln!("failed to pack {:?}: {err}", args.board);
+                return 2;
+            }
+        },
+        false => PresetData::Chip(ChipPreset::from_board(&board)),
+    };
+
+    // the file name is used when no name is given and the board has no title
+    let name = args
+        .name
+        .unwrap_or_else(|| match board.meta.title.is_empty() {
+            true => match args.out.file_stem() {
+                Some(stem) => stem.to_string_lossy().into_owned(),
+                None => String::from("New Chip"),
+            },
+            false => board.meta.title.clone(),
+        });
+    let preset = DevicePreset {
+        name,
+        cat: args.cat,
+        color: [255; 4],
+        data,
+        src: PresetSource::Board(board),
+        glyph: None,
+    };
+
+    let encoding = Encoding::of_path(&args.out).unwrap_or(Encoding::Data);
+    match files::save(&args.out, encoding, &preset) {
+        Ok(()) => 0,
+        Err(err) => {
+            err.context("Failed to write preset").log();
+            1
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use logsim::{
     board::Board,
-    old_data::OldDevicePreset,
+    netlist::Netlist,
+    old_data::{OldBoard, OldBoardPreset, OldDevicePreset},
     presets::{Change, DevicePreset, Library},
     settings::Settings,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::{fs, io, process};
 
@@ -15,11 +16,75 @@ pub fn load_settings() -> Result<Settings, FileErr> {
     load(&config_path("settings.ron"), Encoding::Ron)
 }
 
-pub fn save_board(board: &Board) -> Result<(), FileErr> {
+/// The geometry of the native window, restored on launch
+#[derive(Serialize, Deserialize)]
+pub struct WindowState {
+    pub pos: Option<[f32; 2]>,
+    pub size: [f32; 2],
+    pub fullscreen: bool,
+}
+pub fn save_window(window: &WindowState) -> Result<(), FileErr> {
+    save(&config_path("window.ron"), Encoding::Ron, window)
+}
+pub fn load_window() -> Result<WindowState, FileErr> {
+    load(&config_path("window.ron"), Encoding::Ron)
+}
+
+pub fn save_board(board: &mut Board) -> Result<(), FileErr> {
+    board.capture_sim_state();
     save(&config_path("board.data"), Encoding::Data, board)
 }
 pub fn load_board() -> Result<Board, FileErr> {
-    load(&config_path("board.data"), Encoding::Data)
+    load_board_at(&config_path("board.data"))
+}
+fn load_board_at(path: &Path) -> Result<Board, FileErr> {
+    let board: Result<Board, _> = load(&path, Encoding::Data);
+    let old_board: Result<OldBoard, _> = load(&path, Encoding::Data);
+
+    match (board, old_board) {
+        (Ok(mut board), _) => {
+            board.resume_sim_state();
+            Ok(board)
+        }
+        (_, Ok(old_board)) => Ok(old_board.update()),
+        (Err(err), _) => Err(err),
+    }
+}
+
+/// The directory of the boards that are saved by name
+pub fn boards_dir() -> PathBuf {
+    let path = config_path("boards");
+    match fs::create_dir(&path) {
+        Ok(_) => {}
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(err) => FileErr::io(&path, err)
+            .context("Failed to create boards directory")
+            .log(),
+    }
+    path
+}
+fn board_path(name: &str) -> PathBuf {
+    boards_dir().join(format!("{name}.data"))
+}
+pub fn save_board_as(name: &str, board: &mut Board) -> Result<(), FileErr> {
+    board.capture_sim_state();
+    save(&board_path(name), Encoding::Data, board)
+}
+pub fn load_board_named(name: &str) -> Result<Board, FileErr> {
+    load_board_at(&board_path(name))
+}
+pub fn delete_board(name: &str) -> Result<(), FileErr> {
+    let path = board_path(name);
+    fs::remove_file(&path).map_err(|err| FileErr::io(&path, err))
+}
+/// The names of the boards in `boards_dir`, sorted
+pub fn list_boards() -> Result<Vec<String>, FileErr> {
+    let paths = read_dir(&boards_dir(), |path| Encoding::Data.file_matches(path))?;
+    let mut names: Vec<String> = (paths.iter())
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+        .collect();
+    names.sort();
+    Ok(names)
 }
 
 pub fn save_library(library: &mut Library) -> Result<(), FileErr> {
@@ -51,21 +116,31 @@ pub fn config_path(config_file: &str) -> PathBuf {
     path_buf
 }
 
+#[derive(Clone, Copy)]
 pub enum Encoding {
     Ron,
     Data,
+    Json,
 }
 impl Encoding {
     pub fn file_matches<P: AsRef<Path>>(&self, path: &P) -> bool {
         let end = match self {
             &Self::Ron => ".ron",
             &Self::Data => ".data",
+            &Self::Json => ".json",
         };
         let Some(file_name) = path.as_ref().file_name() else {
         	return false;
         };
         file_name.to_str().unwrap().ends_with(end)
     }
+
+    /// Picks the encoding from the file extension
+    pub fn of_path<P: AsRef<Path>>(path: &P) -> Option<Self> {
+        [Self::Ron, Self::Data, Self::Json]
+            .into_iter()
+            .find(|encoding| encoding.file_matches(path))
+    }
 }
 
 pub fn save<P, T>(path: &P, encoding: Encoding, value: &T) -> Result<(), FileErr>
@@ -78,6 +153,7 @@ where
             .unwrap()
             .into_bytes(),
         Encoding::Data => bincode::serialize(value).unwrap(),
+        Encoding::Json => serde_json::to_vec_pretty(value).unwrap(),
     };
     fs::write(path, bytes).map_err(|err| FileErr::io(path, err))
 }
@@ -94,6 +170,9 @@ where
         Encoding::Data => {
             bincode::deserialize::<T>(&bytes).map_err(|_| FileErr::new(path, "Invalid data"))
         }
+        Encoding::Json => {
+            serde_json::from_slice::<T>(&bytes).map_err(|_| FileErr::new(path, "Invalid JSON"))
+        }
     }
 }
 pub fn read_dir<P: AsRef<Path>, F: Fn(&PathBuf) -> bool>(
@@ -143,16 +222,20 @@ pub fn load_preset<P: AsRef<Path>>(path: &P, presets: &mut Library) -> Result<()
     let add_ctx = |err: FileErr| err.context("Failed to load preset");
 
     let preset: Result<DevicePreset, _> = load(path, Encoding::Data).map_err(add_ctx);
+    let old_board_preset: Result<OldBoardPreset, _> = load(path, Encoding::Data);
     let old_preset: Result<OldDevicePreset, _> = load(path, Encoding::Data);
 
-    match (preset, old_preset) {
-        (Ok(preset), _) => {
+    match (preset, old_board_preset, old_preset) {
+        (Ok(preset), _, _) => {
             presets.add_preset(preset, false);
         }
-        (_, Ok(old_preset)) => {
+        (_, Ok(old_preset), _) => {
+            presets.add_preset(old_preset.update(), true);
+        }
+        (_, _, Ok(old_preset)) => {
             presets.add_preset(old_preset.update(), true);
         }
-        (Err(err), _) => return Err(err),
+        (Err(err), _, _) => return Err(err),
     }
     Ok(())
 }
@@ -168,6 +251,51 @@ pub fn load_presets<P: AsRef<Path>>(path: &P) -> Result<Library, FileErr> {
     Ok(presets)
 }
 
+pub fn export_board<P: AsRef<Path>>(path: &P, board: &mut Board) -> Result<(), FileErr> {
+    let encoding = Encoding::of_path(path).unwrap_or(Encoding::Json);
+    board.capture_sim_state();
+    save(path, encoding, board)
+}
+pub fn import_board<P: AsRef<Path>>(path: &P) -> Result<Board, FileErr> {
+    let encoding = Encoding::of_path(path).unwrap_or(Encoding::Json);
+    let board: Result<Board, _> = load(path, encoding);
+    let old_board: Result<OldBoard, _> = load(path, encoding);
+
+    match (board, old_board) {
+        (Ok(mut board), _) => {
+            board.resume_sim_state();
+            Ok(board)
+        }
+        (_, Ok(old_board)) => Ok(old_board.update()),
+        (Err(err), _) => Err(err.context("Failed to import board")),
+    }
+}
+
+pub fn export_netlist<P: AsRef<Path>>(path: &P, netlist: &Netlist) -> Result<(), FileErr> {
+    save(path, Encoding::Json, netlist)
+}
+pub fn import_netlist<P: AsRef<Path>>(path: &P) -> Result<Netlist, FileErr> {
+    load(path, Encoding::Json).map_err(|err| err.context("Failed to import netlist"))
+}
+
+/// Writes every preset in the library to a single file
+pub fn export_library<P: AsRef<Path>>(path: &P, library: &Library) -> Result<(), FileErr> {
+    let encoding = Encoding::of_path(path).unwrap_or(Encoding::Json);
+    save(path, encoding, &library.presets())
+}
+/// Reads a file written by `export_library`
+pub fn import_library<P: AsRef<Path>>(path: &P) -> Result<Vec<DevicePreset>, FileErr> {
+    let encoding = Encoding::of_path(path).unwrap_or(Encoding::Json);
+    load(path, encoding).map_err(|err| err.context("Failed to import library"))
+}
+
+pub fn export_text<P: AsRef<Path>>(path: &P, text: &str) -> Result<(), FileErr> {
+    fs::write(path, text).map_err(|err| FileErr::io(path, err))
+}
+pub fn import_text<P: AsRef<Path>>(path: &P) -> Result<String, FileErr> {
+    fs::read_to_string(path).map_err(|err| FileErr::io(path, err))
+}
+
 pub fn reveal_dir<P: AsRef<Path>>(path: &P) -> Result<(), FileErr> {
     let path = path.as_ref().to_str().unwrap();
     #[allow(unused_variables)]
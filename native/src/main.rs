@@ -1,35 +1,101 @@
 #![cfg_attr(debug, windows_subsystem = "windows")]
 
+mod cli;
 mod files;
 use files::FileErrResult;
 
-use eframe::egui::Context;
+use eframe::egui::{Context, Pos2, Vec2};
 use eframe::{run_native, NativeOptions};
 use futures::executor::ThreadPool;
-use logsim::{app::App, presets::DevicePreset, IntegrationInfo, OutEvent};
+use logsim::{
+    app::App, board::Board, netlist::Netlist, old_data::OldBoardPreset, presets::DevicePreset,
+    ui::QuitPrompt, IntegrationInfo, OutEvent,
+};
 use rfd::AsyncFileDialog;
 use std::env::consts::{ARCH, OS};
+use std::path::PathBuf;
 use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
-fn save_all(app: &mut App) {
-    files::save_settings(&app.settings).log_err();
-    files::save_board(&app.board).log_err();
-    files::save_library(&mut app.library).log_err();
+/// Saves the board to the saved board that's open, or the default board file
+fn save_board(app: &mut App) -> bool {
+    let saved = match &app.boards_menu.current {
+        Some(name) => files::save_board_as(name, &mut app.board),
+        None => files::save_board(&mut app.board),
+    };
+    saved.log_err().is_some()
+}
+
+/// Returns false if saving anything failed
+fn save_all(app: &mut App) -> bool {
+    let settings = files::save_settings(&app.settings).log_err().is_some();
+    let board = save_board(app);
+    let library = files::save_library(&mut app.library).log_err().is_some();
+    app.settings.modified &= !settings;
+    app.board.modified &= !board;
+    settings && board && library
+}
+
+fn data_file_dialog() -> AsyncFileDialog {
+    AsyncFileDialog::new()
+        .add_filter("JSON", &["json"])
+        .add_filter("Data", &["data"])
+        .add_filter("RON", &["ron"])
+}
+fn netlist_file_dialog() -> AsyncFileDialog {
+    AsyncFileDialog::new().add_filter("JSON", &["json"])
+}
+fn csv_file_dialog() -> AsyncFileDialog {
+    AsyncFileDialog::new().add_filter("CSV", &["csv"])
+}
+fn svg_file_dialog() -> AsyncFileDialog {
+    AsyncFileDialog::new().add_filter("SVG", &["svg"])
+}
+
+/// What to do with the file picked in a file dialog
+enum FileAction {
+    ExportBoard,
+    ImportBoard,
+    ExportNetlist,
+    ImportNetlist,
+    ExportLibrary,
+    ImportBundle,
+    ImportVectors,
+    /// Write the text to the file
+    ExportText(String),
+}
+impl FileAction {
+    /// If the dialog picks a file to save to, instead of one to open
+    fn saves(&self) -> bool {
+        !matches!(
+            self,
+            Self::ImportBoard | Self::ImportNetlist | Self::ImportBundle | Self::ImportVectors
+        )
+    }
 }
 
 struct NativeApp {
     app: App,
     last_save: SystemTime,
     fullscreen: bool,
+    /// The window geometry to save on exit
+    window: Option<files::WindowState>,
+    /// The board title currently shown in the window title
+    title: String,
+    /// Set once closing the window was confirmed, so it isn't canceled again
+    quitting: bool,
+    /// If the changes were discarded in the quit prompt, so they aren't saved on exit
+    discard: bool,
 
     recv_imported_presets: Receiver<DevicePreset>,
     send_imported_presets: Arc<SyncSender<DevicePreset>>,
+    recv_picked_files: Receiver<(FileAction, PathBuf)>,
+    send_picked_files: Arc<SyncSender<(FileAction, PathBuf)>>,
     thread_pool: ThreadPool,
 }
 impl NativeApp {
-    fn new() -> Self {
+    fn new(fullscreen: bool) -> Self {
         let info = IntegrationInfo {
             name: format!("Native {OS} {ARCH}"),
             native: true,
@@ -37,17 +103,29 @@ impl NativeApp {
 
         let library = files::load_library().log_err().unwrap_or_default();
         let settings = files::load_settings().log_err().unwrap_or_default();
-        let board = files::load_board().log_err().unwrap_or_default();
+        let board = files::load_board()
+            .log_err()
+            .unwrap_or_else(|| Board::with_size(settings.default_board_size));
+
+        let mut app = App::new(info, settings, library, board);
+        app.boards_menu.saved = files::list_boards().log_err().unwrap_or_default();
 
         let (send, recv) = sync_channel(100);
+        let (send_picked, recv_picked) = sync_channel(10);
         Self {
-            app: App::new(info, settings, library, board),
+            app,
             last_save: SystemTime::now(),
-            fullscreen: false,
+            fullscreen,
+            window: None,
+            title: String::new(),
+            quitting: false,
+            discard: false,
 
             recv_imported_presets: recv,
             send_imported_presets: Arc::new(send),
-            // TODO gracefully handle err (creating a thread pool is only required for importing presets)
+            recv_picked_files: recv_picked,
+            send_picked_files: Arc::new(send_picked),
+            // TODO gracefully handle err (creating a thread pool is only required for file dialogs)
             thread_pool: ThreadPool::new().expect("Failed to create thread pool"),
         }
     }
@@ -59,7 +137,16 @@ impl NativeApp {
             let entries = AsyncFileDialog::new().pick_files().await;
             for entry in entries.unwrap_or(Vec::new()) {
                 let bytes: Vec<_> = entry.read().await;
-                let Ok(preset) = bincode::deserialize::<DevicePreset>(&bytes) else {
+                let preset = match entry.file_name().ends_with(".json") {
+                    true => serde_json::from_slice::<DevicePreset>(&bytes).ok(),
+                    false => bincode::deserialize::<DevicePreset>(&bytes)
+                        .or_else(|_| {
+                            bincode::deserialize::<OldBoardPreset>(&bytes)
+                                .map(OldBoardPreset::update)
+                        })
+                        .ok(),
+                };
+                let Some(preset) = preset else {
                     println!("failed to parse preset {:?}", entry.file_name());
                     continue;
                 };
@@ -68,6 +155,65 @@ impl NativeApp {
         };
         self.thread_pool.spawn_ok(future);
     }
+
+    /// Opens the dialog without blocking the window, `action` is done once a file is picked
+    fn pick_file(&mut self, ctx: &Context, dialog: AsyncFileDialog, action: FileAction) {
+        let sender = Arc::clone(&self.send_picked_files);
+        let ctx = ctx.clone();
+        let future = async move {
+            let file = match action.saves() {
+                true => dialog.save_file().await,
+                false => dialog.pick_file().await,
+            };
+            if let Some(file) = file {
+                sender.send((action, file.path().to_owned())).unwrap();
+                ctx.request_repaint();
+            }
+        };
+        self.thread_pool.spawn_ok(future);
+    }
+    fn file_picked(&mut self, action: FileAction, path: PathBuf) {
+        match action {
+            FileAction::ExportBoard => {
+                files::export_board(&path, &mut self.app.board).log_err();
+            }
+            FileAction::ImportBoard => {
+                if let Some(board) = files::import_board(&path).log_err() {
+                    self.app.set_board(board);
+                    self.app.board.modified = true;
+                }
+            }
+            FileAction::ExportNetlist => {
+                let (netlist, warnings) = Netlist::from_board(&self.app.board);
+                files::export_netlist(&path, &netlist).log_err();
+                self.app.netlist_warnings = warnings;
+            }
+            FileAction::ImportNetlist => {
+                if let Some(netlist) = files::import_netlist(&path).log_err() {
+                    let (board, warnings) = netlist.to_board(&self.app.library, &self.app.settings);
+                    self.app.set_board(board);
+                    self.app.board.modified = true;
+                    self.app.netlist_warnings = warnings;
+                }
+            }
+            FileAction::ExportLibrary => {
+                files::export_library(&path, &self.app.library).log_err();
+            }
+            FileAction::ImportBundle => {
+                if let Some(presets) = files::import_library(&path).log_err() {
+                    self.app.library.add_presets(&presets);
+                }
+            }
+            FileAction::ImportVectors => {
+                if let Some(text) = files::import_text(&path).log_err() {
+                    self.app.vector_runner.vectors = text;
+                }
+            }
+            FileAction::ExportText(text) => {
+                files::export_text(&path, &text).log_err();
+            }
+        }
+    }
 }
 impl eframe::App for NativeApp {
     fn update(&mut self, ctx: &Context, window: &mut eframe::Frame) {
@@ -75,11 +221,26 @@ impl eframe::App for NativeApp {
         if let Ok(preset) = self.recv_imported_presets.try_recv() {
             self.app.library.add_preset(preset, true);
         }
+        if let Ok((action, path)) = self.recv_picked_files.try_recv() {
+            self.file_picked(action, path);
+        }
 
         let event = self.app.update(ctx);
         match event {
             OutEvent::None => {}
             OutEvent::Quit => window.close(),
+            OutEvent::SaveAndQuit => match save_all(&mut self.app) {
+                true => {
+                    self.quitting = true;
+                    window.close();
+                }
+                false => self.app.quit_prompt.save_failed = true,
+            },
+            OutEvent::DiscardAndQuit => {
+                self.quitting = true;
+                self.discard = true;
+                window.close();
+            }
             OutEvent::ToggleFullscreen => {
                 window.set_fullscreen(!self.fullscreen);
                 self.fullscreen = !self.fullscreen;
@@ -88,12 +249,85 @@ impl eframe::App for NativeApp {
             OutEvent::RevealConfigDir => {
                 files::reveal_config_dir().log_err();
             }
+            OutEvent::ExportBoard => {
+                self.pick_file(ctx, data_file_dialog(), FileAction::ExportBoard)
+            }
+            OutEvent::ImportBoard => {
+                self.pick_file(ctx, data_file_dialog(), FileAction::ImportBoard)
+            }
+            OutEvent::ExportNetlist => {
+                self.pick_file(ctx, netlist_file_dialog(), FileAction::ExportNetlist)
+            }
+            OutEvent::ImportNetlist => {
+                self.pick_file(ctx, netlist_file_dialog(), FileAction::ImportNetlist)
+            }
+            OutEvent::ExportLibrary => {
+                self.pick_file(ctx, data_file_dialog(), FileAction::ExportLibrary)
+            }
+            OutEvent::ImportBundle => {
+                self.pick_file(ctx, data_file_dialog(), FileAction::ImportBundle)
+            }
+            OutEvent::ImportVectors => {
+                self.pick_file(ctx, csv_file_dialog(), FileAction::ImportVectors)
+            }
+            OutEvent::ExportVectors => {
+                let csv = self.app.vector_runner.to_csv();
+                self.pick_file(ctx, csv_file_dialog(), FileAction::ExportText(csv));
+            }
+            OutEvent::ExportCsv(name) => {
+                if let Some(csv) = self.app.preset_csv(&name) {
+                    let dialog = csv_file_dialog().set_file_name(&format!("{name}.csv"));
+                    self.pick_file(ctx, dialog, FileAction::ExportText(csv));
+                }
+            }
+            OutEvent::ExportSelection => {
+                if let Some(svg) = self.app.selection_svg(ctx) {
+                    self.pick_file(ctx, svg_file_dialog(), FileAction::ExportText(svg));
+                }
+            }
 
-            OutEvent::SaveAll => save_all(&mut self.app),
-            OutEvent::SaveSettings => files::save_settings(&self.app.settings).log_err().unwrap(),
+            OutEvent::SaveAll => {
+                let ok = save_all(&mut self.app);
+                self.app.saved(ctx, ok);
+                self.last_save = SystemTime::now();
+            }
+            OutEvent::SaveSettings => {
+                files::save_settings(&self.app.settings).log_err().unwrap();
+                self.app.settings.modified = false;
+            }
             OutEvent::LoadSettings => self.app.settings = files::load_settings().log_err().unwrap(),
-            OutEvent::SaveBoard => files::save_board(&self.app.board).log_err().unwrap(),
-            OutEvent::LoadBoard => self.app.board = files::load_board().log_err().unwrap(),
+            OutEvent::SaveBoard => {
+                self.app.board.modified &= !save_board(&mut self.app);
+            }
+            OutEvent::SaveBoardAs(name) => {
+                let saved = files::save_board_as(&name, &mut self.app.board).log_err();
+                if saved.is_some() {
+                    self.app.board.modified = false;
+                    self.app.boards_menu.current = Some(name);
+                }
+                self.app.boards_menu.saved = files::list_boards().log_err().unwrap_or_default();
+            }
+            OutEvent::OpenBoard(name) => {
+                // keep the changes to the board that's open
+                if self.app.settings.autosave {
+                    save_board(&mut self.app);
+                }
+                if let Some(board) = files::load_board_named(&name).log_err() {
+                    self.app.set_board(board);
+                    self.app.boards_menu.current = Some(name);
+                }
+            }
+            OutEvent::DeleteBoard(name) => {
+                files::delete_board(&name).log_err();
+                if self.app.boards_menu.current.as_ref() == Some(&name) {
+                    self.app.boards_menu.current = None;
+                }
+                self.app.boards_menu.saved = files::list_boards().log_err().unwrap_or_default();
+            }
+            OutEvent::ListBoards => {
+                self.app.boards_menu.saved = files::list_boards().log_err().unwrap_or_default();
+            }
+            OutEvent::LoadBoard => self.app.set_board(files::load_board().log_err().unwrap()),
             OutEvent::SaveLibrary => files::save_library(&mut self.app.library)
                 .log_err()
                 .unwrap(),
@@ -101,25 +335,94 @@ impl eframe::App for NativeApp {
             _ => {}
         }
 
+        // remember the window geometry, but not the size of the fullscreen window
+        let info = &window.info().window_info;
+        let prev = self.window.as_ref();
+        self.window = Some(files::WindowState {
+            pos: match info.fullscreen {
+                true => prev.and_then(|prev| prev.pos),
+                false => info.position.map(|pos| [pos.x, pos.y]),
+            },
+            size: match (info.fullscreen, prev) {
+                (true, Some(prev)) => prev.size,
+                _ => [info.size.x, info.size.y],
+            },
+            fullscreen: info.fullscreen,
+        });
+
+        if self.app.board.meta.title != self.title {
+            self.title = self.app.board.meta.title.clone();
+            match self.title.is_empty() {
+                true => window.set_window_title("LogSim Native"),
+                false => window.set_window_title(&format!("LogSim Native - {}", self.title)),
+            }
+        }
+
         // auto save
         let since_last_save = SystemTime::now().duration_since(self.last_save).unwrap();
-        if since_last_save.as_secs() > 30 {
-            save_all(&mut self.app);
+        if self.app.settings.autosave && since_last_save.as_secs() > 30 {
+            let ok = save_all(&mut self.app);
+            self.app.saved(ctx, ok);
             self.last_save = SystemTime::now();
         }
 
         // repaint
-        ctx.request_repaint_after(Duration::from_millis(1000 / 60));
+        if self.app.needs_repaint() {
+            ctx.request_repaint_after(Duration::from_millis(1000 / 60));
+        }
+    }
+
+    /// Called when the window is closed, or after `window.close()`.
+    /// Returning false keeps the window open, to ask about unsaved changes.
+    fn on_close_event(&mut self) -> bool {
+        if self.quitting {
+            return true;
+        }
+        let autosave = self.app.settings.autosave;
+        self.quitting = match autosave {
+            true => save_all(&mut self.app),
+            false => !self.app.has_unsaved_changes(),
+        };
+        if !self.quitting {
+            self.app.quit_prompt = QuitPrompt {
+                open: true,
+                save_failed: autosave,
+            };
+        }
+        self.quitting
     }
 
     fn on_exit(&mut self, _ctx: Option<&eframe::glow::Context>) {
-        save_all(&mut self.app);
+        if self.app.settings.autosave && !self.discard {
+            save_all(&mut self.app);
+        }
+        if let Some(window) = &self.window {
+            files::save_window(window).log_err();
+        }
     }
 }
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match cli::PackArgs::parse(&args) {
+        Some(Ok(pack_args)) => std::process::exit(cli::pack(pack_args)),
+        Some(Err(err)) => {
+            eprintln!("{err}\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+        None => {}
+    }
+
+    let mut options = NativeOptions::default();
+    let mut fullscreen = false;
+    if let Some(window) = files::load_window().log_err() {
+        options.initial_window_pos = window.pos.map(|[x, y]| Pos2::new(x, y));
+        options.initial_window_size = Some(Vec2::new(window.size[0], window.size[1]));
+        options.fullscreen = window.fullscreen;
+        fullscreen = window.fullscreen;
+    }
     run_native(
         "LogSim Native",
-        NativeOptions::default(),
-        Box::new(|_cc| Box::new(NativeApp::new())),
+        options,
+        Box::new(move |_cc| Box::new(NativeApp::new(fullscreen))),
     );
 }
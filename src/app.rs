@@ -1,6 +1,9 @@
-use crate::board::{Board, BoardItem, Device};
+use crate::board::{Board, BoardItem, Clock, Device, DeviceData, IoSel, SimSpeed};
+use crate::graphics::{LinkRouting, View};
 use crate::input::Input;
-use crate::settings::Settings;
+use crate::presets::{ChipPreset, CombGatePreset, DevicePreset, PackErr, PresetData, PresetSource};
+use crate::profiler::{Profiler, Timer};
+use crate::settings::{Palette, Settings, Theme, WriteDelay};
 use crate::*;
 use egui::*;
 
@@ -46,20 +49,68 @@ pub enum AppAction {
     LoadSettings,
     ReloadLibrary,
     ImportLibrary,
+    ExportBoard,
+    ImportBoard,
+    ExportNetlist,
+    ImportNetlist,
+    ExportLibrary,
+    ImportBundle,
 
     ToggleLibraryMenu,
     TogglePackMenu,
     ToggleSimMenu,
+    ToggleBoardInfo,
+    ToggleVectorRunner,
 
     OpenSettings,
     CloseSettings,
 
+    /// Saves the view in a bookmark slot (1 to 9)
+    SetBookmark(u8),
+    JumpToBookmark(u8),
+
+    SaveAll,
+    SaveBoardAs(String),
+    OpenBoard(String),
+    DeleteBoard(String),
+    ListBoards,
+    Quit,
+    SaveAndQuit,
+    DiscardAndQuit,
+
     PackBoard,
+    FindEquivalent,
+    /// Checks if the board settles with every input combination, before packing it as combinational
+    CheckSettles,
     StepSim,
+    ResetSim,
+    RepairSim,
+    MeasurePeriod,
+    /// Counts the board inputs up (or down) as one number
+    StepInputs(i64),
+    SpreadDevices,
+    /// Frames everything on the board
+    ZoomToFit,
+    ArrangeDevices,
+    RefreshDevices,
+    ShowTruthTable(u64),
+    FlattenChip(u64),
+    ConstantToInput(u64),
+    RunVectors,
+    ImportVectors,
+    ExportVectors,
+    ExportSelection,
+    /// Exports the truth table of a combinational preset as CSV
+    ExportCsv(String),
 
     HoldPreset(String),
+    ToggleFavorite(String),
     LoadPreset(String),
+    /// Loads the board of a preset even if the current board has unsaved changes
+    ForceLoadPreset(String),
     DeletePreset(String),
+    /// The preset, and its new input and output names
+    SetPinNames(String, Vec<String>, Vec<String>),
     Clear,
 }
 impl Default for AppAction {
@@ -75,17 +126,30 @@ impl AppAction {
     }
 }
 
+/// The pin that's selected for linking with the keyboard
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PinCursor {
+    Start(LinkStart<u64>),
+    Target(LinkTarget<u64>),
+}
+
 pub struct CreateLinks {
     pub starts: Vec<LinkStart<u64>>,
     pub color: usize,
+    /// Overrides `color` with an explicit off and on color
+    pub rgb: Option<[Color32; 2]>,
     pub anchors: Vec<Pos2>,
+    /// How the anchors are routed when the link is finished
+    pub routing: LinkRouting,
 }
 impl CreateLinks {
     fn new() -> Self {
         Self {
             starts: Vec::new(),
             color: 0,
+            rgb: None,
             anchors: Vec::new(),
+            routing: LinkRouting::Straight,
         }
     }
 
@@ -95,12 +159,13 @@ impl CreateLinks {
         }
         if self.starts.is_empty() {
             self.color = 0;
+            self.rgb = None;
             self.anchors.clear();
         }
         self.starts.insert(0, start);
     }
-    fn take(&mut self) -> Option<(LinkStart<u64>, usize)> {
-        self.starts.pop().map(|start| (start, self.color))
+    fn take(&mut self) -> Option<LinkStart<u64>> {
+        self.starts.pop()
     }
 }
 
@@ -116,22 +181,72 @@ pub struct App {
     pub library_menu: ui::LibraryMenu,
     pub pack_menu: ui::PackMenu,
     pub sim_menu: ui::SimMenu,
+    pub board_info_menu: ui::BoardInfoMenu,
+    pub refresh_prompt: ui::RefreshPrompt,
+    pub quit_prompt: ui::QuitPrompt,
+    pub load_prompt: ui::LoadPrompt,
+    pub boards_menu: ui::BoardsMenu,
+    pub profiler: Profiler,
+    pub truth_table: Option<ui::TruthTableWindow>,
+    pub vector_runner: ui::VectorRunner,
+    /// What was left out of the last imported or exported netlist
+    pub netlist_warnings: Vec<String>,
+    /// Why the last bus link (see `App::bus_link`) couldn't be made
+    pub bus_link_err: Option<String>,
+    /// The missing presets that were dismissed, so the warning only comes back if they change
+    pub dismissed_missing: Vec<String>,
+    /// When everything was last saved (egui time, in seconds), and if it succeeded
+    pub last_save: Option<(f64, bool)>,
 
     /// The small window for searching and placing library
     pub preset_placer: ui::ChipPlacer,
     pub name_popup: Option<ui::NamePopup>,
 
     pub create_links: CreateLinks,
+    /// Tab cycles through the pins, Enter picks up a link from a start pin,
+    /// or connects it to a target pin
+    pub pin_cursor: Option<PinCursor>,
     /// A list of the presets we've picked from the preset placer
     pub held_presets: Vec<String>,
     /// If we've selected multiple devices for bulk actions
     pub selected_devices: Vec<u64>,
+    /// The devices copied with Ctrl+C, relative to the top-left of the copy.
+    /// Links between them target the index of the device in here, instead of an id.
+    pub clipboard: Vec<Device>,
+    /// The device the context menu was opened on
+    pub context_device: Option<u64>,
+    /// The link the context menu was opened on
+    pub context_link: Option<(LinkStart<u64>, usize)>,
+    /// The input the context menu was opened on
+    pub context_input: Option<u64>,
+    /// The net that's shown while everything else is dimmed, see `Board::net_of`
+    pub focus_net: Option<LinkStart<u64>>,
+    /// Where the dragged device would be without snapping to alignment guides
+    pub drag_device_pos: Option<Pos2>,
+    pub alignment_guides: Vec<(Pos2, Pos2)>,
+    /// The device that's hovered, and since when (in seconds)
+    pub hover_card: Option<(BoardItem, f64)>,
+    /// The path of the pointer while slicing links (Alt+Drag)
+    pub slice_path: Vec<Pos2>,
+    /// Where the selection box started and where it's dragged to (Shift+Drag on the board)
+    pub select_box: Option<(Pos2, Pos2)>,
     /// If true, we should automatically start/finish placing a link when we hover the pin
     pub auto_link: bool,
+    /// The bookmark the camera is moving to
+    pub view_target: Option<View>,
+    /// If something changed or is animating, so we should redraw next frame
+    pub dirty: bool,
+    /// The screen space rect of the board panel, as of the last frame
+    pub board_panel: Rect,
 }
 
 impl App {
     pub fn new(info: IntegrationInfo, settings: Settings, library: Library, board: Board) -> Self {
+        let mut pack_menu = ui::PackMenu::default();
+        pack_menu.use_last_pack(&settings);
+        let mut sim_menu = ui::SimMenu::default();
+        sim_menu.load_board(&board);
+        let auto_link = settings.auto_link;
         Self {
             settings,
             library,
@@ -142,77 +257,615 @@ impl App {
 
             settings_open: false,
             library_menu: ui::LibraryMenu::default(),
-            pack_menu: ui::PackMenu::default(),
-            sim_menu: ui::SimMenu::default(),
+            pack_menu,
+            sim_menu,
+            board_info_menu: ui::BoardInfoMenu::default(),
+            refresh_prompt: ui::RefreshPrompt::default(),
+            quit_prompt: ui::QuitPrompt::default(),
+            load_prompt: ui::LoadPrompt::default(),
+            boards_menu: ui::BoardsMenu::default(),
+            profiler: Profiler::new(),
+            truth_table: None,
+            vector_runner: ui::VectorRunner::default(),
+            netlist_warnings: Vec::new(),
+            bus_link_err: None,
+            dismissed_missing: Vec::new(),
+            last_save: None,
 
             preset_placer: ui::ChipPlacer::default(),
             name_popup: None,
 
             create_links: CreateLinks::new(),
+            pin_cursor: None,
             held_presets: Vec::new(),
             selected_devices: Vec::new(),
-            auto_link: false,
+            clipboard: Vec::new(),
+            context_device: None,
+            context_link: None,
+            context_input: None,
+            focus_net: None,
+            drag_device_pos: None,
+            hover_card: None,
+            alignment_guides: Vec::new(),
+            slice_path: Vec::new(),
+            select_box: None,
+            auto_link,
+            view_target: None,
+            dirty: true,
+            board_panel: Rect::NOTHING,
+        }
+    }
+
+    /// If the integration should request a repaint, even without any input
+    pub fn needs_repaint(&self) -> bool {
+        self.settings.continuous_redraw || self.dirty
+    }
+
+    /// Called by the integration after saving everything, for the indicator in the top panel
+    pub fn saved(&mut self, ctx: &Context, ok: bool) {
+        self.last_save = Some((ctx.input().time, ok));
+    }
+
+    /// If the board, library or settings changed since they were last saved
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.board.modified || self.library.has_changes() || self.settings.modified
+    }
+
+    /// Tracks how long the item is hovered, returns true once its hover card should show
+    fn hover_card_dwelled(&mut self, ctx: &Context, item: BoardItem) -> bool {
+        const HOVER_CARD_DELAY: f64 = 0.6;
+
+        let time = ctx.input().time;
+        let since = match self.hover_card {
+            Some((hover_item, since)) if hover_item == item => since,
+            _ => time,
+        };
+        self.hover_card = Some((item, since));
+
+        let dwell = time - since;
+        if dwell < HOVER_CARD_DELAY {
+            let wait = (HOVER_CARD_DELAY - dwell) as f32;
+            ctx.request_repaint_after(std::time::Duration::from_secs_f32(wait));
         }
+        dwell >= HOVER_CARD_DELAY
+    }
+
+    /// Replaces the board with a loaded one, and takes its sim speed.
+    /// The selection, focused net and links being created pointed into the old board, so they're cleared.
+    pub fn set_board(&mut self, board: Board) {
+        self.sim_menu.load_board(&board);
+        self.board = board;
+        self.selected_devices.clear();
+        self.focus_net = None;
+        self.create_links.starts.clear();
+        self.create_links.anchors.clear();
     }
 
     pub fn place_preset(&mut self, name: &str, pos: Pos2) {
         if let Some(preset) = self.library.get_preset(name) {
+            let pos = self.board.free_device_pos(pos, &self.settings);
             let device = Device::from_preset(preset, pos);
             self.board.add_device(rand_id(), device);
             self.preset_placer.push_recent(name);
         }
     }
+    /// Clears the board and/or places the newly packed preset, depending on the pack menu
+    pub fn finish_pack(&mut self, name: &str) {
+        let menu = &self.pack_menu;
+        self.settings.last_pack = Some((menu.cat.clone(), menu.color));
+        self.settings.modified = true;
+
+        if self.pack_menu.clear_board {
+            self.set_board(Board::with_size(self.settings.default_board_size));
+            self.board.modified = true;
+        }
+        match self.pack_menu.after_pack {
+            ui::AfterPack::Nothing => {}
+            ui::AfterPack::Place => {
+                let size = match self.library.get_preset(name) {
+                    Some(preset) => graphics::preset_size(preset, &self.settings),
+                    None => return,
+                };
+                self.place_preset(name, self.board.rect.center() - size * 0.5);
+            }
+            ui::AfterPack::Hold => self.held_presets.push(name.to_owned()),
+        }
+    }
+    /// Replaces the board with the one the preset was packed from, so packing it again updates the preset
+    pub fn load_preset_board(&mut self, name: &str) {
+        let Some(preset) = self.library.get_preset(name) else { return };
+        let PresetSource::Board(board) = &preset.src else { return };
+        let menu = &mut self.pack_menu;
+        menu.name = preset.name.clone();
+        menu.cat = preset.cat.clone();
+        menu.color = Color32::from_rgba_premultiplied(
+            preset.color[0],
+            preset.color[1],
+            preset.color[2],
+            preset.color[3],
+        );
+        menu.err = None;
+        menu.equivalent = None;
+        menu.combinational = matches!(preset.data, PresetData::CombGate(_));
+
+        self.set_board(board.clone());
+        // it's not the board that was saved last
+        self.board.modified = true;
+    }
+    /// The truth table of the preset as CSV, if it's a combinational gate
+    pub fn preset_csv(&self, name: &str) -> Option<String> {
+        match &self.library.get_preset(name)?.data {
+            PresetData::CombGate(comb_gate) => Some(comb_gate.to_csv()),
+            _ => None,
+        }
+    }
+    /// An SVG image of the selected devices and the links between them
+    pub fn selection_svg(&self, ctx: &Context) -> Option<String> {
+        const MARGIN: f32 = 10.0;
+
+        let ids = &self.selected_devices;
+        let rect = self.board.devices_rect(ids, &self.settings)?.expand(MARGIN);
+        let transform = graphics::Transform {
+            scale: 1.0,
+            offset: [-rect.min.x, -rect.min.y],
+        };
+        // a NaN pointer position, so nothing is drawn hovered
+        let mut g = graphics::Graphics::new(ctx, transform, pos2(f32::NAN, f32::NAN));
+        let style = match self.settings.print_export {
+            true => graphics::BoardStyle::PRINT,
+            false => graphics::BoardStyle::from_settings(&self.settings),
+        };
+        graphics::show_devices(
+            &mut g,
+            &self.settings,
+            &style,
+            &self.board,
+            &self.library,
+            ids,
+            rect,
+        );
+        Some(svg::shapes_to_svg(&g.finish(), rect.size()))
+    }
     pub fn finish_link(&mut self, target: LinkTarget<u64>) -> bool {
-        if let Some((start, color)) = self.create_links.take() {
-            let anchors = self.create_links.anchors.clone();
-            self.board
-                .add_link(start, crate::Link::new(target, color, anchors));
+        if let Some(start) = self.create_links.take() {
+            let from = graphics::link_start_pos(&self.settings, &self.board, start);
+            let to = graphics::link_target_pos(&self.settings, &self.board, target);
+            let anchors = match (from, to) {
+                (Some(from), Some(to)) => {
+                    let routing = self.create_links.routing;
+                    routing.route(from, &self.create_links.anchors, to)
+                }
+                _ => self.create_links.anchors.clone(),
+            };
+            let mut link = crate::Link::new(target, self.create_links.color, anchors);
+            link.rgb = self.create_links.rgb;
+            self.board.add_link(start, link);
+            if self.settings.colorful_wires && self.create_links.rgb.is_none() {
+                self.create_links.color = (self.create_links.color + 1) % graphics::NUM_LINK_COLORS;
+            }
             return true;
         }
         false
     }
 
+    /// Picks up all the outputs of the hovered device in order, or links the outputs
+    /// being linked to all the inputs of the hovered device or output group at once
+    pub fn bus_link(&mut self, item: BoardItem) {
+        if let BoardItem::DeviceOutput(device, _) = item {
+            let num_outputs = self.board.devices.get(&device).unwrap().num_outputs();
+            for output in 0..num_outputs {
+                self.create_links
+                    .push(LinkStart::DeviceOutput(device, output));
+            }
+            return;
+        }
+        if self.create_links.starts.is_empty() {
+            return;
+        }
+        let targets = self.board.bus_targets(item);
+        // `CreateLinks::take` takes the first picked start first
+        let starts: Vec<_> = self.create_links.starts.iter().rev().copied().collect();
+        let (color, rgb) = (self.create_links.color, self.create_links.rgb);
+        match self.board.bus_link(&starts, &targets, color, rgb) {
+            Ok(()) => {
+                self.create_links.starts.clear();
+                self.bus_link_err = None;
+            }
+            Err(err) => self.bus_link_err = Some(err),
+        }
+    }
+
+    /// Moves the pin cursor to the next (or previous) pin,
+    /// a target pin if we're creating links, and a start pin otherwise
+    pub fn move_pin_cursor(&mut self, back: bool) {
+        let pins: Vec<PinCursor> = match self.create_links.starts.is_empty() {
+            true => self
+                .board
+                .link_starts()
+                .into_iter()
+                .map(PinCursor::Start)
+                .collect(),
+            false => self
+                .board
+                .link_targets()
+                .into_iter()
+                .map(PinCursor::Target)
+                .collect(),
+        };
+        if pins.is_empty() {
+            self.pin_cursor = None;
+            return;
+        }
+        let idx = self
+            .pin_cursor
+            .and_then(|cursor| pins.iter().position(|pin| *pin == cursor));
+        let idx = match (idx, back) {
+            (None, false) => 0,
+            (None, true) => pins.len() - 1,
+            (Some(idx), false) => (idx + 1) % pins.len(),
+            (Some(idx), true) => (idx + pins.len() - 1) % pins.len(),
+        };
+        self.pin_cursor = Some(pins[idx]);
+    }
+
+    /// Picks up a link from the pin under the pin cursor (or the hovered pin),
+    /// or connects the links being created to it
+    pub fn use_pin_cursor(&mut self) {
+        let cursor = self.pin_cursor.or(match self.input.hovered() {
+            AppItem::Board(BoardItem::InputPin(id)) => Some(PinCursor::Start(LinkStart::Input(id))),
+            AppItem::Board(BoardItem::DeviceOutput(device, output)) => {
+                Some(PinCursor::Start(LinkStart::DeviceOutput(device, output)))
+            }
+            AppItem::Board(BoardItem::OutputPin(id)) => {
+                Some(PinCursor::Target(LinkTarget::Output(id)))
+            }
+            AppItem::Board(BoardItem::DeviceInput(device, input)) => {
+                Some(PinCursor::Target(LinkTarget::DeviceInput(device, input)))
+            }
+            _ => None,
+        });
+        // the pin could have been removed since it was selected
+        match cursor {
+            Some(PinCursor::Start(start)) if self.board.link_start_state(start).is_some() => {
+                self.create_links.push(start);
+            }
+            Some(PinCursor::Target(target)) if self.board.link_target_state(target).is_some() => {
+                self.finish_link(target);
+            }
+            _ => {}
+        }
+        self.pin_cursor = None;
+    }
+
     pub fn exec_action(&mut self, action: AppAction, out: &mut OutEvent) {
         match action {
             AppAction::None => {}
             AppAction::RevealConfigDir => *out = OutEvent::RevealConfigDir,
             AppAction::LoadSettings => *out = OutEvent::LoadSettings,
-            AppAction::ReloadLibrary => *out = OutEvent::LoadLibrary,
+            AppAction::ReloadLibrary => {
+                *out = OutEvent::LoadLibrary;
+                self.refresh_prompt = ui::RefreshPrompt {
+                    open: !self.board.devices.is_empty(),
+                    changed: Vec::new(),
+                };
+            }
             AppAction::ImportLibrary => *out = OutEvent::ImportPresets,
+            AppAction::ExportBoard => *out = OutEvent::ExportBoard,
+            AppAction::ImportBoard => *out = OutEvent::ImportBoard,
+            AppAction::ExportNetlist => *out = OutEvent::ExportNetlist,
+            AppAction::ImportNetlist => *out = OutEvent::ImportNetlist,
+            AppAction::ExportLibrary => *out = OutEvent::ExportLibrary,
+            AppAction::ImportBundle => *out = OutEvent::ImportBundle,
+            AppAction::ImportVectors => *out = OutEvent::ImportVectors,
+            AppAction::ExportVectors => *out = OutEvent::ExportVectors,
+            AppAction::ExportSelection => *out = OutEvent::ExportSelection,
+            AppAction::ExportCsv(name) => *out = OutEvent::ExportCsv(name),
 
             AppAction::TogglePackMenu => self.pack_menu.open ^= true,
             AppAction::ToggleLibraryMenu => self.library_menu.open ^= true,
             AppAction::ToggleSimMenu => self.sim_menu.open ^= true,
+            AppAction::ToggleBoardInfo => {
+                self.board_info_menu.open ^= true;
+                self.board_info_menu.tags_field = self.board.meta.tags.join(", ");
+            }
+            AppAction::ToggleVectorRunner => self.vector_runner.open ^= true,
 
             AppAction::OpenSettings => self.settings_open = true,
-            AppAction::CloseSettings => self.settings_open = false,
+            AppAction::CloseSettings => {
+                self.settings_open = false;
+                // the settings change how devices are drawn
+                self.library_menu.thumbnails.clear();
+            }
+
+            AppAction::FlattenChip(id) => {
+                let flattened = self.board.flatten_chip(id, &self.library, &self.settings);
+                if let Ok(ids) = flattened {
+                    self.selected_devices = ids;
+                }
+            }
+            AppAction::ConstantToInput(id) => {
+                self.board.constant_to_input(id, &self.settings);
+                self.selected_devices.retain(|other| *other != id);
+            }
+            AppAction::SetBookmark(slot) => {
+                self.board.set_bookmark(slot, self.sim_menu.view.clone())
+            }
+            AppAction::ZoomToFit => {
+                self.zoom_to_fit(self.board.content_rect(&self.settings));
+            }
+            AppAction::JumpToBookmark(slot) => {
+                if let Some(bookmark) = self.board.get_bookmark(slot) {
+                    self.view_target = Some(bookmark.view.clone());
+                }
+            }
+
+            AppAction::SaveAll => *out = OutEvent::SaveAll,
+            AppAction::SaveBoardAs(name) => *out = OutEvent::SaveBoardAs(name),
+            AppAction::OpenBoard(name) => *out = OutEvent::OpenBoard(name),
+            AppAction::DeleteBoard(name) => *out = OutEvent::DeleteBoard(name),
+            AppAction::ListBoards => *out = OutEvent::ListBoards,
+            AppAction::Quit => *out = OutEvent::Quit,
+            AppAction::SaveAndQuit => *out = OutEvent::SaveAndQuit,
+            AppAction::DiscardAndQuit => *out = OutEvent::DiscardAndQuit,
+
+            AppAction::PackBoard => {
+                let menu = &mut self.pack_menu;
+                if menu.name.trim().is_empty() {
+                    menu.err = Some(String::from("The chip needs a name"));
+                    return;
+                }
+                let data = match menu.combinational {
+                    true => {
+                        let max_inputs = self.settings.max_comb_inputs;
+                        CombGatePreset::from_board(&mut self.board.clone(), max_inputs)
+                            .map(PresetData::CombGate)
+                    }
+                    false => Ok(PresetData::Chip(ChipPreset::from_board(&self.board))),
+                };
+                let data = match data {
+                    Ok(data) => data,
+                    Err(err) => {
+                        self.pack_failed(err);
+                        return;
+                    }
+                };
+                let preset = DevicePreset {
+                    name: menu.name.clone(),
+                    cat: menu.cat.clone(),
+                    color: menu.color.to_array(),
+                    data,
+                    src: PresetSource::Board(self.board.clone()),
+                    glyph: None,
+                };
+                menu.err = None;
+                menu.equivalent = None;
+                let name = preset.name.clone();
+                self.library.add_preset(preset, true);
+                self.finish_pack(&name);
+            }
+            AppAction::FindEquivalent => {
+                let menu = &mut self.pack_menu;
+                let max_inputs = self.settings.max_comb_inputs;
+                match CombGatePreset::from_board(&mut self.board.clone(), max_inputs) {
+                    Ok(comb_gate) => {
+                        let equivalent = self.library.find_equivalent(&comb_gate.table);
+                        let names = equivalent.iter().map(|preset| preset.name.clone());
+                        menu.equivalent = Some(names.collect());
+                        menu.err = None;
+                    }
+                    Err(err) => {
+                        menu.equivalent = None;
+                        self.pack_failed(err);
+                    }
+                }
+            }
+            AppAction::CheckSettles => {
+                const MAX_UPDATES: usize = 1000;
 
-            AppAction::PackBoard => todo!(),
+                let max_inputs = self.settings.max_comb_inputs;
+                match self.board.check_settles(max_inputs, MAX_UPDATES) {
+                    Ok(()) => {
+                        self.pack_menu.settles = Some(true);
+                        self.pack_menu.err = None;
+                    }
+                    Err(unsettled) => {
+                        self.pack_menu.settles = Some(false);
+                        self.pack_failed(PackErr::Unsettled(unsettled));
+                    }
+                }
+            }
             AppAction::StepSim => self.board.update(),
+            AppAction::ResetSim => self.board.reset_sim(),
+            AppAction::RepairSim => self.board.recompute_all(),
+            AppAction::StepInputs(step) => self.board.step_input_combination(step),
+            AppAction::MeasurePeriod => {
+                let ticks = self.sim_menu.period_ticks;
+                self.sim_menu.period = Some(self.board.measure_period(ticks));
+            }
+            AppAction::SpreadDevices => {
+                let ids: Vec<u64> = match self.selected_devices.is_empty() {
+                    true => self.board.devices.keys().copied().collect(),
+                    false => self.selected_devices.clone(),
+                };
+                self.board.spread_devices(&ids, &self.settings);
+            }
+            AppAction::ArrangeDevices => self.board.arrange_devices(&self.settings),
+            AppAction::ShowTruthTable(id) => {
+                let device = self.board.devices.get(&id).unwrap();
+                let (inputs, outputs) = match self.library.get_preset(&device.preset) {
+                    Some(preset) => (
+                        preset.data.input_names().to_vec(),
+                        preset.data.output_names().to_vec(),
+                    ),
+                    None => (Vec::new(), Vec::new()),
+                };
+                self.truth_table = Some(ui::TruthTableWindow {
+                    title: format!("{} truth table", device.preset),
+                    inputs,
+                    outputs,
+                    table: device.data.truth_table(),
+                });
+            }
+            AppAction::RunVectors => {
+                let name = |id: &u64| match self.board.inputs.get(id) {
+                    Some(input) => input.io.name.clone(),
+                    None => self.board.outputs.get(id).unwrap().io.name.clone(),
+                };
+                let runner = &mut self.vector_runner;
+                runner.inputs = self.board.inputs_sorted().iter().map(name).collect();
+                runner.outputs = self.board.outputs_sorted().iter().map(name).collect();
+                runner.results.clear();
+                runner.err = None;
+
+                match runner.parse_vectors() {
+                    Ok(vectors) => {
+                        let outputs = self.board.run_vectors(&vectors, runner.max_updates);
+                        runner.results = vectors.into_iter().zip(outputs).collect();
+                    }
+                    Err(err) => runner.err = Some(err),
+                }
+            }
+            AppAction::RefreshDevices => {
+                let mut changed: Vec<String> = self
+                    .board
+                    .refresh_devices(&self.library)
+                    .into_iter()
+                    .map(|id| self.board.devices.get(&id).unwrap().preset.clone())
+                    .collect();
+                changed.sort();
+                changed.dedup();
+                self.refresh_prompt = ui::RefreshPrompt {
+                    open: !changed.is_empty(),
+                    changed,
+                };
+            }
             AppAction::HoldPreset(name) => self.held_presets.push(name),
-            AppAction::LoadPreset(_name) => todo!(),
+            AppAction::ToggleFavorite(name) => {
+                let favorites = &mut self.settings.favorite_presets;
+                match favorites.iter().position(|favorite| *favorite == name) {
+                    Some(idx) => {
+                        favorites.remove(idx);
+                    }
+                    None => favorites.push(name),
+                }
+                *out = OutEvent::SaveSettings;
+            }
+            AppAction::LoadPreset(name) => {
+                let has_work = !self.board.devices.is_empty() || !self.board.inputs.is_empty();
+                match self.board.modified && has_work {
+                    true => {
+                        self.load_prompt = ui::LoadPrompt {
+                            open: true,
+                            preset: name,
+                        }
+                    }
+                    false => self.load_preset_board(&name),
+                }
+            }
+            AppAction::ForceLoadPreset(name) => {
+                self.load_prompt = ui::LoadPrompt::default();
+                self.load_preset_board(&name);
+            }
             AppAction::DeletePreset(name) => self.library.remove_preset(&name),
-            AppAction::Clear => self.board = Board::new(),
+            AppAction::SetPinNames(name, inputs, outputs) => {
+                self.library.set_pin_names(&name, inputs, outputs);
+            }
+            AppAction::Clear => {
+                self.set_board(Board::with_size(self.settings.default_board_size));
+                self.board.modified = true;
+            }
         }
     }
 
     // -----------------------------------------------------------
     // GUI
 
-    pub fn board_input(&mut self, focus_clear: bool) {
+    /// How far to pan the view this frame, when dragging something on the board
+    /// (or creating links) near the edge of the board panel
+    fn edge_scroll(&self, ctx: &Context, panel: Rect) -> Vec2 {
+        let margin = self.settings.edge_scroll_margin;
+        let dragging = match self.input.drag_delta() {
+            None | Some((_, AppItem::Board(BoardItem::Board))) => false,
+            Some((_, AppItem::Board(_))) => !self.input.modifiers.alt,
+            Some(_) => false,
+        };
+        let linking = !self.create_links.starts.is_empty();
+        let pos = self.input.pointer_pos;
+        if margin <= 0.0 || !(dragging || linking) || !panel.expand(margin).contains(pos) {
+            return Vec2::ZERO;
+        }
+        // 0 at the margin, 1 at the edge (and past it)
+        let closeness = |dist: f32| ((margin - dist) / margin).clamp(0.0, 1.0);
+        let dir = vec2(
+            closeness(panel.max.x - pos.x) - closeness(pos.x - panel.min.x),
+            closeness(panel.max.y - pos.y) - closeness(pos.y - panel.min.y),
+        );
+        -dir * self.settings.edge_scroll_speed * ctx.input().stable_dt
+    }
+
+    /// Where a link anchor placed at the pointer goes, and the guides it lines up with
+    fn snap_anchor(&self) -> (Pos2, Vec<(Pos2, Pos2)>) {
+        const SNAP_THRESHOLD: f32 = 6.0;
+        let inv_t = self.sim_menu.view.create_inv_transform();
+        self.board.snap_anchor(
+            inv_t * self.input.pointer_pos,
+            &self.create_links.anchors,
+            &self.settings,
+            inv_t * SNAP_THRESHOLD,
+        )
+    }
+
+    pub fn board_input(&mut self) {
         let AppItem::Board(item) = self.input.hovered() else {
     		return;
     	};
         let world_pos = self.sim_menu.view.create_inv_transform() * self.input.pointer_pos;
         let try_link = self.auto_link && self.input.hovered_changed;
+        // a finger links by tapping, so it can still drag the view from a pin
+        let link_press = match self.input.touch {
+            true => self.input.clicked_prim,
+            false => self.input.pressed_prim,
+        };
+        if self.input.shortcut(Key::B) {
+            self.bus_link(item);
+        }
+        // N shows only the net of the hovered pin or link, clicking the board shows everything again
+        if self.input.shortcut(Key::N) {
+            self.focus_net = self.board.net_of(item);
+        }
+        if self.input.pressed_sec {
+            self.context_device = match item {
+                BoardItem::Device(id) => Some(id),
+                _ => None,
+            };
+            self.context_link = match item {
+                BoardItem::InputLink(id, idx) => Some((LinkStart::Input(id), idx)),
+                BoardItem::LinkAnchor(start, idx, _) => Some((start, idx)),
+                BoardItem::DeviceOutputLink(id, output, idx) => {
+                    Some((LinkStart::DeviceOutput(id, output), idx))
+                }
+                _ => None,
+            };
+            self.context_input = match item {
+                BoardItem::InputBulb(id) | BoardItem::InputPin(id) => Some(id),
+                _ => None,
+            };
+        }
         match item {
             BoardItem::Board => {
-                if self.input.pressed_prim {
-                    self.create_links.anchors.push(world_pos);
+                if self.input.clicked_prim && self.create_links.starts.is_empty() {
+                    self.focus_net = None;
+                }
+                if link_press {
+                    let pos = match self.create_links.starts.is_empty() {
+                        true => world_pos,
+                        false => self.snap_anchor().0,
+                    };
+                    self.create_links.anchors.push(pos);
                 }
             }
             BoardItem::Device(id) => {
-                if self.input.pressed(Key::Backspace) {
+                if self.input.shortcut(Key::Backspace) {
                     if self.selected_devices.contains(&id) {
                         for id in &self.selected_devices {
                             self.board.remove_device(*id);
@@ -227,6 +880,14 @@ impl App {
                         self.selected_devices.push(id);
                     }
                 }
+                // double clicking selects every device of the same preset
+                if self.input.double_clicked_prim {
+                    let preset = &self.board.devices.get(&id).unwrap().preset;
+                    let same = (self.board.devices.iter())
+                        .filter(|(_, device)| &device.preset == preset)
+                        .map(|(id, _)| *id);
+                    self.selected_devices = same.collect();
+                }
             }
             BoardItem::InputBulb(id) => {
                 if self.input.clicked_prim {
@@ -234,75 +895,102 @@ impl App {
                     self.board.set_input(id, !state);
                 }
                 self.name_popup = Some(ui::NamePopup::input(id));
-                if self.input.pressed(Key::Backspace) && focus_clear {
+                if self.input.shortcut(Key::Backspace) {
                     self.board.remove_input(id);
                 }
-                if self.input.pressed(Key::ArrowDown) {
+                if self.input.shortcut(Key::ArrowDown) {
                     self.board.stack_input(id, &self.settings);
                 }
-                if self.input.pressed(Key::ArrowUp) {
+                // Ctrl+K ties the signal off with a High or Low device
+                if self.input.command_used(Key::K) {
+                    self.board
+                        .input_to_constant(id, &self.library, &self.settings);
+                    self.name_popup = None;
+                }
+                // Shift+Up takes the hovered member out, instead of the last one
+                if self.input.shortcut(Key::ArrowUp) && self.input.modifiers.shift {
+                    self.board.detach_input(id);
+                } else if self.input.shortcut(Key::ArrowUp) {
                     self.board.unstack_input(id);
                 }
             }
             BoardItem::InputPin(id) => {
-                if self.input.pressed_prim || try_link {
+                if link_press || try_link {
                     self.create_links.push(LinkStart::Input(id));
                 }
             }
             BoardItem::InputLink(input_id, link_idx) => {
-                if self.input.pressed(Key::Backspace) {
+                if self.input.shortcut(Key::Backspace) {
                     let links = &mut self.board.inputs.get_mut(&input_id).unwrap().links;
                     let target = links[link_idx].target;
                     links.remove(link_idx);
                     self.board.write_queue.push(target, false);
+                    self.board.modified = true;
                 }
             }
             BoardItem::InputGroup(_) => {}
             BoardItem::OutputBulb(id) => {
-                if self.input.pressed(Key::Backspace) {
+                if self.input.shortcut(Key::Backspace) {
                     self.board.remove_output(id);
                 }
                 self.name_popup = Some(ui::NamePopup::output(id));
-                if self.input.pressed(Key::ArrowDown) && focus_clear {
+                if self.input.shortcut(Key::ArrowDown) {
                     self.board.stack_output(id, &self.settings);
                 }
-                if self.input.pressed(Key::ArrowUp) {
+                if self.input.shortcut(Key::ArrowUp) && self.input.modifiers.shift {
+                    self.board.detach_output(id);
+                } else if self.input.shortcut(Key::ArrowUp) {
                     self.board.unstack_output(id);
                 }
             }
             BoardItem::OutputGroup(_) => {}
             BoardItem::OutputPin(id) => {
-                if self.input.pressed_prim || try_link {
+                if link_press || try_link {
                     self.finish_link(LinkTarget::Output(id));
                 }
             }
             BoardItem::DeviceInput(device, device_input) => {
                 let mut created_link = false;
-                if self.input.pressed_prim || try_link {
+                if link_press || try_link {
                     created_link = self.finish_link(LinkTarget::DeviceInput(device, device_input));
                 }
-                if self.input.pressed_prim && !created_link {
+                // a linked input is set by its link, so toggling it would only flicker
+                let can_toggle = || {
+                    let target = LinkTarget::DeviceInput(device, device_input);
+                    self.settings.toggle_linked_inputs || !self.board.is_linked(target)
+                };
+                if link_press && !created_link && can_toggle() {
                     let state = self.board.get_device_input(device, device_input).unwrap();
                     self.board.set_device_input(device, device_input, !state);
                 }
             }
             BoardItem::DeviceOutput(device, output) => {
-                if self.input.pressed_prim || try_link {
+                if link_press || try_link {
                     self.create_links
                         .push(LinkStart::DeviceOutput(device, output));
                 }
-                if self.input.pressed(Key::Backspace) {
+                if self.input.shortcut(Key::Backspace) {
                     let device = self.board.devices.get_mut(&device).unwrap();
                     device.links[output].clear();
+                    self.board.modified = true;
                 }
             }
             BoardItem::DeviceOutputLink(device_id, output_idx, link_idx) => {
-                if self.input.pressed(Key::Backspace) {
+                if self.input.shortcut(Key::Backspace) {
                     let links =
                         &mut self.board.devices.get_mut(&device_id).unwrap().links[output_idx];
                     let target = links[link_idx].target;
                     links.remove(link_idx);
                     self.board.write_queue.push(target, false);
+                    self.board.modified = true;
+                }
+            }
+            BoardItem::LinkAnchor(start, link_idx, anchor_idx) => {
+                if self.input.shortcut(Key::Backspace) {
+                    if let Some(link) = self.board.get_link_mut(start, link_idx) {
+                        link.anchors.remove(anchor_idx);
+                        self.board.modified = true;
+                    }
                 }
             }
             BoardItem::InputCol => {
@@ -338,6 +1026,71 @@ impl App {
         self.selected_devices = ids;
     }
 
+    /// Shows why packing failed, and selects the devices of a loop so they're easy to find
+    fn pack_failed(&mut self, err: PackErr) {
+        if let PackErr::Unsettled(unsettled) = &err {
+            self.selected_devices = unsettled.devices.clone();
+        }
+        self.pack_menu.err = Some(err.to_string());
+    }
+
+    /// Moves the view to show the world space `rect` in the board panel
+    pub fn zoom_to_fit(&mut self, rect: Rect) {
+        const MARGIN: f32 = 40.0;
+
+        let mut target = self.sim_menu.view.clone();
+        target.fit(rect, self.board_panel.shrink(MARGIN));
+        self.view_target = Some(target);
+    }
+
+    pub fn copy_selected_devices(&mut self) {
+        let ids: Vec<u64> = (self.selected_devices.iter().copied())
+            .filter(|id| self.board.devices.contains_key(id))
+            .collect();
+        let mut selection_min = pos2(f32::INFINITY, f32::INFINITY);
+        for device in ids.iter().filter_map(|id| self.board.devices.get(id)) {
+            selection_min.x = f32::min(selection_min.x, device.pos.x);
+            selection_min.y = f32::min(selection_min.y, device.pos.y);
+        }
+        let index_of = |id: u64| ids.iter().position(|other| *other == id);
+
+        self.clipboard.clear();
+        for device in ids.iter().filter_map(|id| self.board.devices.get(id)) {
+            let mut device = device.clone();
+            device.pos = (device.pos - selection_min).to_pos2();
+            // links to something that isn't copied are dropped
+            for links in &mut device.links {
+                links.retain_mut(|link| {
+                    let LinkTarget::DeviceInput(id, input) = link.target else {
+                        return false;
+                    };
+                    let Some(idx) = index_of(id) else {
+                        return false;
+                    };
+                    link.target = LinkTarget::DeviceInput(idx as u64, input);
+                    true
+                });
+            }
+            self.clipboard.push(device);
+        }
+    }
+
+    pub fn paste_devices(&mut self, pointer_pos: Pos2) {
+        let offset = (self.sim_menu.view.create_inv_transform() * pointer_pos).to_vec2();
+        let ids: Vec<u64> = self.clipboard.iter().map(|_| rand_id()).collect();
+        for (device, id) in self.clipboard.iter().zip(&ids) {
+            let mut device = device.clone();
+            device.pos += offset;
+            for link in device.links.iter_mut().flatten() {
+                if let LinkTarget::DeviceInput(idx, input) = link.target {
+                    link.target = LinkTarget::DeviceInput(ids[idx as usize], input);
+                }
+            }
+            self.board.add_device(*id, device);
+        }
+        self.selected_devices = ids;
+    }
+
     pub fn update(&mut self, ctx: &Context) -> OutEvent {
         let mut style = (*ctx.style()).clone();
         self.settings.theme.set(&mut style);
@@ -359,54 +1112,301 @@ impl App {
             }
             if ui.button("Reset").clicked() {
                 self.settings = Settings::default();
+                self.settings.modified = true;
             }
         });
         CentralPanel::default().show(ctx, |ui| {
-            ui.label("Settings here");
+            let settings = &mut self.settings;
+            let mut changed = false;
+            ScrollArea::vertical().show(ui, |ui| {
+                ui.strong("App");
+                ui.horizontal(|ui| {
+                    ui.label("theme");
+                    changed |= ui
+                        .radio_value(&mut settings.theme, Theme::Dark, "dark")
+                        .changed();
+                    changed |= ui
+                        .radio_value(&mut settings.theme, Theme::Light, "light")
+                        .changed();
+                });
+                changed |= ui
+                    .checkbox(&mut settings.colorful_wires, "colorful wires")
+                    .on_hover_text("every new link starts with the next color")
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut settings.auto_link, "auto link on start")
+                    .on_hover_text("start with links placed by hovering pins, like toggling it with Ctrl+L")
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut settings.continuous_redraw, "redraw continuously")
+                    .changed();
+                changed |= ui
+                    .checkbox(
+                        &mut settings.category_stripes,
+                        "show category stripes on devices",
+                    )
+                    .changed();
+                changed |= ui
+                    .checkbox(
+                        &mut settings.device_glyphs,
+                        "show gate symbols instead of names when zoomed out",
+                    )
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut settings.io_bulb_values, "show 1/0 in IO bulbs")
+                    .changed();
+                ui.horizontal(|ui| {
+                    ui.label("palette");
+                    changed |= ui
+                        .radio_value(&mut settings.palette, Palette::Default, "default")
+                        .changed();
+                    changed |= ui
+                        .radio_value(&mut settings.palette, Palette::Colorblind, "colorblind")
+                        .on_hover_text("orange on dark blue, which differ in brightness too")
+                        .changed();
+                });
+                changed |= ui
+                    .checkbox(&mut settings.wide_on_links, "draw on links wider")
+                    .changed();
+                ui.horizontal(|ui| {
+                    ui.label("max inputs of combinational gates");
+                    changed |= ui
+                        .add(DragValue::new(&mut settings.max_comb_inputs).clamp_range(1..=TruthTable::MAX_INPUTS))
+                        .on_hover_text("packing checks every combination of inputs, each input doubles how long it takes")
+                        .changed();
+                });
+                changed |= ui
+                    .checkbox(
+                        &mut settings.toggle_linked_inputs,
+                        "clicking linked device inputs toggles them",
+                    )
+                    .on_hover_text("the link sets the input again the next time its state changes")
+                    .changed();
+                ui.horizontal(|ui| {
+                    ui.label("edge scrolling");
+                    changed |= ui
+                        .add(
+                            DragValue::new(&mut settings.edge_scroll_margin)
+                                .clamp_range(0.0..=200.0)
+                                .prefix("margin: "),
+                        )
+                        .on_hover_text("dragging or linking this close to the edge pans the view, 0 turns it off")
+                        .changed();
+                    changed |= ui
+                        .add(
+                            DragValue::new(&mut settings.edge_scroll_speed)
+                                .clamp_range(50.0..=5000.0)
+                                .prefix("speed: "),
+                        )
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("link anchor grid");
+                    changed |= ui
+                        .add(DragValue::new(&mut settings.anchor_grid).clamp_range(0.0..=100.0))
+                        .on_hover_text("anchors that don't line up with other anchors or pins snap to it, 0 turns it off")
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    const DEFAULT_FIXED: u8 = 1;
+
+                    let delay = &mut settings.write_delay;
+                    ui.label("write delay");
+                    let zero = matches!(delay, WriteDelay::Zero);
+                    if ui.radio(zero, "zero").on_hover_text("instant, the timing is always the same").clicked() {
+                        *delay = WriteDelay::Zero;
+                        changed = true;
+                    }
+                    let fixed = matches!(delay, WriteDelay::Fixed(_));
+                    if ui.radio(fixed, "fixed").clicked() && !fixed {
+                        *delay = WriteDelay::Fixed(DEFAULT_FIXED);
+                        changed = true;
+                    }
+                    let random = matches!(delay, WriteDelay::Random { .. });
+                    if ui.radio(random, "random").on_hover_text("a wider range makes races and glitches more likely").clicked() && !random {
+                        *delay = WriteDelay::default();
+                        changed = true;
+                    }
+                    match delay {
+                        WriteDelay::Zero => {}
+                        WriteDelay::Fixed(delay) => {
+                            changed |= ui.add(DragValue::new(delay).clamp_range(0..=20)).changed();
+                        }
+                        WriteDelay::Random { min, max } => {
+                            changed |= ui
+                                .add(DragValue::new(min).clamp_range(0..=20).prefix("min: "))
+                                .changed();
+                            changed |= ui
+                                .add(DragValue::new(max).clamp_range(0..=20).prefix("max: "))
+                                .changed();
+                            *max = (*max).max(*min);
+                        }
+                    }
+                });
+                changed |= ui
+                    .checkbox(&mut settings.print_export, "export in print style")
+                    .on_hover_text("black on white, regardless of the theme and colors")
+                    .changed();
+                ui.horizontal(|ui| {
+                    ui.label("click tolerance");
+                    changed |= ui
+                        .add(DragValue::new(&mut settings.click_tolerance).clamp_range(0.0..=20.0))
+                        .on_hover_text("moving less than this between pressing and releasing is still a click, not a drag")
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    let size = &mut settings.default_board_size;
+                    ui.label("new board size");
+                    changed |= ui
+                        .add(DragValue::new(&mut size.x).clamp_range(200.0..=5000.0))
+                        .changed();
+                    changed |= ui
+                        .add(DragValue::new(&mut size.y).clamp_range(200.0..=5000.0))
+                        .changed();
+                    if ui.button("use current").clicked() {
+                        *size = self.board.rect.size();
+                        changed = true;
+                    }
+                });
+                if self.int.native {
+                    changed |= ui
+                        .checkbox(&mut settings.autosave, "auto save")
+                        .on_hover_text("save every 30 seconds and when quitting")
+                        .changed();
+                }
+
+                ui.separator();
+                ui.strong("Board");
+                ui.horizontal(|ui| {
+                    ui.label("board color");
+                    changed |= ui.color_edit_button_srgba(&mut settings.board_color).changed();
+                    ui.label("IO column color");
+                    changed |= ui
+                        .color_edit_button_srgba(&mut settings.board_io_col_color)
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("pin colors (off, on)");
+                    for color in &mut settings.pin_colors {
+                        changed |= ui.color_edit_button_srgba(color).changed();
+                    }
+                    ui.label("link colors (off, on)");
+                    for color in &mut settings.link_colors {
+                        changed |= ui.color_edit_button_srgba(color).changed();
+                    }
+                })
+                .response
+                .on_hover_text("used by the default palette");
+                let sliders = [
+                    (&mut settings.board_io_pin_size, 2.0..=20.0, "IO pin size"),
+                    (&mut settings.board_io_col_w, 20.0..=80.0, "IO column width"),
+                    (&mut settings.link_width, 1.0..=12.0, "link width"),
+                    (&mut settings.device_name_size, 6.0..=40.0, "device name size"),
+                    (&mut settings.device_pin_size, 2.0..=16.0, "device pin size"),
+                    (&mut settings.device_min_pin_spacing, 6.0..=40.0, "device pin spacing"),
+                ];
+                for (value, range, text) in sliders {
+                    changed |= ui.add(Slider::new(value, range).text(text)).changed();
+                }
+
+                ui.separator();
+                ui.strong("Debug");
+                changed |= ui
+                    .checkbox(&mut settings.debug, "debug")
+                    .on_hover_text("show device IDs, the debug menu and debug buttons")
+                    .changed();
+            });
+            settings.modified |= changed;
         });
+        self.dirty = false;
         out_event
     }
     pub fn show_sim_page(&mut self, ctx: &Context) -> OutEvent {
         let mut out_event = OutEvent::default();
         let mut action = AppAction::None;
 
-        self.board_input(ctx.memory().focus().is_none());
-        self.input.update(ctx);
+        let focus_clear = ctx.memory().focus().is_none();
+        self.board_input();
+        self.input.update(ctx, self.settings.click_tolerance);
+
+        // --- Sweep links that removing devices or outputs left behind ---
+        self.board.remove_dangling_links();
+        let board = &self.board;
+        (self.create_links.starts).retain(|start| board.link_start_state(*start).is_some());
 
         // --- Update sim ---
+        self.board
+            .set_write_delays(self.settings.write_delay.range());
+        let timer = Timer::start();
         if !self.sim_menu.paused {
             for _ in 0..self.sim_menu.speed {
                 self.board.update();
             }
         }
+        self.profiler.current.sim_time = timer.elapsed();
 
         // --- Show UI ---
         TopBottomPanel::top("top_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
-                let new_action = ui::show_top_panel(ui);
+                let title = &self.board.meta.title;
+                // favorites can be missing from the library after it's reloaded
+                let favorites: Vec<String> = (self.settings.favorite_presets.iter())
+                    .filter(|name| self.library.get_preset(name).is_some())
+                    .cloned()
+                    .collect();
+                let save_status = match self.last_save {
+                    Some((_, false)) => String::from("save failed"),
+                    Some((time, true)) => {
+                        // keep the seconds counting up
+                        ctx.request_repaint_after(std::time::Duration::from_secs(1));
+                        format!("saved {}s ago", (ctx.input().time - time) as u64)
+                    }
+                    None => String::from("not saved yet"),
+                };
+                let new_action = ui::show_top_panel(
+                    ui,
+                    self.int.native,
+                    title,
+                    &favorites,
+                    &save_status,
+                    &mut self.boards_menu,
+                );
                 action.set(new_action);
             });
         });
 
         if self.library_menu.open {
-            SidePanel::left("library_menu").show(ctx, |ui| {
-                let mut menu = self.library_menu.clone();
-                action.set(ui::show_library_menu(
+            // the menu needs all of the settings for drawing thumbnails
+            let mut floating = self.settings.float_library_menu;
+            ui::show_menu(
+                ctx,
+                "library_menu",
+                panel::Side::Left,
+                &mut floating,
+                |ui| {
+                    action.set(ui::show_library_menu(
+                        ui,
+                        &self.settings,
+                        &mut self.library_menu,
+                        self.int.native,
+                        &self.library,
+                    ));
+                },
+            );
+            self.settings.float_library_menu = floating;
+        }
+        if self.pack_menu.open {
+            let mut floating = self.settings.float_pack_menu;
+            ui::show_menu(ctx, "pack_menu", panel::Side::Left, &mut floating, |ui| {
+                action.set(ui::show_pack_menu(
                     ui,
-                    self.settings.debug,
-                    &mut menu,
-                    self.int.native,
+                    &mut self.pack_menu,
+                    &mut self.settings,
                     &self.library,
+                    &self.board,
                 ));
-                self.library_menu = menu;
-            });
-        }
-        if self.pack_menu.open {
-            SidePanel::left("pack_menu").show(ctx, |ui| {
-                let mut menu = self.pack_menu.clone();
-                action.set(ui::show_pack_menu(ui, &mut menu, &self.library));
-                self.pack_menu = menu;
             });
+            self.settings.float_pack_menu = floating;
         }
         if self.settings.debug {
             TopBottomPanel::top("debug_menu").show(ctx, |ui| {
@@ -414,36 +1414,202 @@ impl App {
             });
         }
         if self.sim_menu.open {
-            SidePanel::right("sim_menu").show(ctx, |ui| {
+            let floating = &mut self.settings.float_sim_menu;
+            ui::show_menu(ctx, "sim_menu", panel::Side::Right, floating, |ui| {
                 let mut menu = self.sim_menu.clone();
-                action.set(ui::show_sim_menu(ui, &mut menu));
+                action.set(ui::show_sim_menu(ui, &mut menu, &mut self.board));
                 self.sim_menu = menu;
             });
         }
+        if !self.create_links.starts.is_empty() {
+            TopBottomPanel::bottom("link_color").show(ctx, |ui| {
+                let palette = self.settings.palette;
+                let meta = &self.board.meta;
+                ui.horizontal(|ui| ui::show_link_color(ui, &mut self.create_links, palette, meta));
+            });
+        }
+        if let Some(window) = &self.truth_table {
+            if !ui::show_truth_table(ctx, window) {
+                self.truth_table = None;
+            }
+        }
+        if self.vector_runner.open {
+            let mut open = true;
+            Window::new("Input vectors")
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    action.set(ui::show_vector_runner(
+                        ui,
+                        &mut self.vector_runner,
+                        self.int.native,
+                    ));
+                });
+            self.vector_runner.open = open;
+        }
+        if !self.netlist_warnings.is_empty() {
+            let mut open = true;
+            Window::new("Netlist")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label("Some of the board couldn't be converted:");
+                    for warning in &self.netlist_warnings {
+                        ui.label(warning);
+                    }
+                });
+            if !open {
+                self.netlist_warnings.clear();
+            }
+        }
+        if let Some(err) = &self.bus_link_err {
+            let mut open = true;
+            Window::new("Bus link")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| ui.label(err));
+            if !open {
+                self.bus_link_err = None;
+            }
+        }
+        let missing = self.board.missing_presets(&self.library);
+        if !missing.is_empty() && missing != self.dismissed_missing {
+            let mut open = true;
+            Window::new("Missing presets")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label("The board uses presets that aren't in the library:");
+                    for name in &missing {
+                        ui.label(name);
+                    }
+                    ui.small("they're shown as placeholders until they're imported");
+                    if ui.button("import presets").clicked() {
+                        action.set(AppAction::ImportLibrary);
+                    }
+                });
+            if !open {
+                self.dismissed_missing = missing;
+            }
+        }
+        if self.board_info_menu.open {
+            let mut open = true;
+            Window::new("Board info")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    let meta = &mut self.board.meta;
+                    let palette = self.settings.palette;
+                    self.board.modified |=
+                        ui::show_board_info(ui, &mut self.board_info_menu, meta, palette);
+                });
+            self.board_info_menu.open = open;
+        }
+        if self.refresh_prompt.open {
+            Window::new("Update devices")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    action.set(ui::show_refresh_prompt(ui, &mut self.refresh_prompt));
+                });
+        }
+        if self.load_prompt.open {
+            Window::new("Load board")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    action.set(ui::show_load_prompt(ui, &mut self.load_prompt));
+                });
+        }
+        if self.quit_prompt.open {
+            Window::new("Unsaved changes")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    action.set(ui::show_quit_prompt(ui, &mut self.quit_prompt));
+                });
+        }
+
+        if self.sim_menu.show_profiler {
+            Window::new("Profiler")
+                .resizable(false)
+                .show(ctx, |ui| self.profiler.show(ui));
+        }
 
         let mut board_item = None;
+        let timer = Timer::start();
         let mut g = graphics::Graphics::new(
             ctx,
             self.sim_menu.view.create_transform(),
             self.input.pointer_pos,
         );
+        // the side panels are already shown, so this is the area of the board panel.
+        // the margin keeps wide links and pins at the edge from popping in
+        let screen_rect = ctx.available_rect().expand(20.0);
+        let inv_t = self.sim_menu.view.create_inv_transform();
+        g.view_rect = Some(Rect::from_min_max(
+            inv_t * screen_rect.min,
+            inv_t * screen_rect.max,
+        ));
 
+        let style = match self.sim_menu.print_preview {
+            true => graphics::BoardStyle::PRINT,
+            false => graphics::BoardStyle::from_settings(&self.settings),
+        };
         if let Some(item) = graphics::show_board(
             &mut g,
             &self.settings,
+            &style,
             &self.board,
             &self.library,
-            self.settings.debug,
+            self.sim_menu.outputs_as_number,
+            self.focus_net,
         ) {
             board_item = Some(item);
         }
+        if self.sim_menu.show_writes {
+            graphics::show_write_queue(&mut g, &self.settings, &self.board);
+        }
+        if self.sim_menu.show_unconnected {
+            let targets = self.board.unconnected_targets();
+            graphics::show_unconnected(&mut g, &self.settings, &self.board, &targets);
+        }
         graphics::outline_devices(&mut g, &self.settings, &self.selected_devices, &self.board);
+        let stacked = self.board.stacked_devices();
+        graphics::show_stacked_devices(&mut g, &self.settings, &stacked, &self.board);
+        graphics::show_alignment_guides(&mut g, &self.alignment_guides);
+        graphics::show_slice_path(&mut g, &self.slice_path);
+        if let Some((start, end)) = self.select_box {
+            graphics::show_select_box(&mut g, Rect::from_two_pos(start, end));
+        }
+        let cursor_pos = self.pin_cursor.and_then(|cursor| match cursor {
+            PinCursor::Start(start) => graphics::link_start_pos(&self.settings, &self.board, start),
+            PinCursor::Target(target) => {
+                graphics::link_target_pos(&self.settings, &self.board, target)
+            }
+        });
+        if let Some(pos) = cursor_pos {
+            graphics::show_pin_cursor(&mut g, &self.settings, pos);
+        }
+        let placing_anchor = !self.create_links.starts.is_empty()
+            && matches!(self.input.hovered(), AppItem::Board(BoardItem::Board));
+        let anchor_snap = placing_anchor.then(|| self.snap_anchor());
+        if let Some((pos, guides)) = &anchor_snap {
+            graphics::show_alignment_guides(&mut g, guides);
+            graphics::show_anchor_snap(&mut g, &self.settings, *pos);
+        }
+        let link_end = match (self.pin_cursor, cursor_pos, anchor_snap) {
+            (Some(PinCursor::Target(_)), Some(pos), _) => pos,
+            (_, _, Some((pos, _))) => pos,
+            _ => self.sim_menu.view.create_inv_transform() * self.input.pointer_pos,
+        };
         graphics::show_create_links(
             &mut g,
             &self.settings,
             &self.board,
             &self.create_links,
-            self.sim_menu.view.create_inv_transform() * self.input.pointer_pos,
+            link_end,
         );
         graphics::show_held_presets(
             &mut g,
@@ -453,13 +1619,22 @@ impl App {
             &self.held_presets,
         );
 
+        self.profiler.current.culled = g.culled;
         let shapes = g.finish();
+        self.profiler.current.graphics_time = timer.elapsed();
+        self.profiler.current.shapes = shapes.len();
 
         let board_rs = CentralPanel::default()
             .show(ctx, |ui| {
                 let (_, painter) = ui.allocate_painter(ui.available_size(), Sense::drag());
                 painter.extend(shapes);
 
+                if self.sim_menu.show_legend {
+                    let rect = painter.clip_rect().shrink(8.0);
+                    let mut ui = ui.child_ui(rect, Layout::bottom_up(Align::Min));
+                    ui::show_color_legend(&mut ui, self.settings.palette, &self.board.meta);
+                }
+
                 if painter.clip_rect().contains(self.input.pointer_pos) {
                     self.input.set_hovered(AppItem::Board(BoardItem::Board));
                 } else {
@@ -477,10 +1652,37 @@ impl App {
                 }
             })
             .response;
+        self.board_panel = board_rs.rect;
         if let Some(item) = board_item {
             self.input.set_hovered(AppItem::Board(item));
         }
 
+        // --- Show the hover card of a device or link ---
+        // not while placing links, where it would only be in the way
+        let routing = !self.create_links.starts.is_empty();
+        match (self.input.hovered(), self.input.drag_delta()) {
+            (AppItem::Board(item @ BoardItem::Device(id)), None) if !routing => {
+                if self.hover_card_dwelled(ctx, item) {
+                    if let Some(device) = self.board.devices.get(&id) {
+                        show_tooltip_at_pointer(ctx, Id::new("device_card"), |ui| {
+                            ui::show_device_card(ui, device);
+                        });
+                    }
+                }
+            }
+            (AppItem::Board(item @ BoardItem::DeviceOutputLink(id, output, link_idx)), None)
+                if !routing =>
+            {
+                if self.hover_card_dwelled(ctx, item) {
+                    show_tooltip_at_pointer(ctx, Id::new("link_card"), |ui| {
+                        let link = (id, output, link_idx);
+                        ui::show_link_card(ui, &self.board, &self.library, link);
+                    });
+                }
+            }
+            _ => self.hover_card = None,
+        }
+
         // --- Handle key binds ---
         if self.input.command_used(Key::L) {
             self.auto_link = !self.auto_link;
@@ -491,13 +1693,111 @@ impl App {
         if self.selected_devices.len() > 0 && self.input.command_used(Key::D) {
             self.clone_selected_devices(self.input.pointer_pos);
         }
-        if self.input.pressed(Key::Escape) {
+        if !self.selected_devices.is_empty() && self.input.command_used(Key::C) {
+            self.copy_selected_devices();
+        }
+        if !self.clipboard.is_empty() && self.input.command_used(Key::V) {
+            self.paste_devices(self.input.pointer_pos);
+        }
+        if self.sim_menu.input_counter && self.input.shortcut(Key::PageUp) {
+            action.set(AppAction::StepInputs(1));
+        }
+        if self.sim_menu.input_counter && self.input.shortcut(Key::PageDown) {
+            action.set(AppAction::StepInputs(-1));
+        }
+        if self.input.shortcut(Key::Escape) {
             self.create_links = CreateLinks::new();
+            self.pin_cursor = None;
+            self.focus_net = None;
+        }
+        // Tab and Enter also move and press focused widgets, so only use them when nothing has focus
+        if focus_clear && self.input.shortcut(Key::Tab) {
+            self.move_pin_cursor(self.input.modifiers.shift);
+        }
+        if focus_clear && self.input.shortcut(Key::Enter) {
+            self.use_pin_cursor();
+        }
+        // Shift+F frames the selection, or the whole board
+        if self.input.shortcut(Key::F) && self.input.modifiers.shift {
+            let rect = match self.selected_devices.is_empty() {
+                true => Some(self.board.rect),
+                false => (self.board).devices_rect(&self.selected_devices, &self.settings),
+            };
+            if let Some(rect) = rect {
+                self.zoom_to_fit(rect);
+            }
+        }
+        if self.input.command_used(Key::F) {
+            action.set(AppAction::ZoomToFit);
+        }
+        if !self.create_links.starts.is_empty() && self.input.shortcut(Key::R) {
+            self.create_links.routing = self.create_links.routing.next();
+        }
+        const SLOT_KEYS: [Key; 9] = [
+            Key::Num1,
+            Key::Num2,
+            Key::Num3,
+            Key::Num4,
+            Key::Num5,
+            Key::Num6,
+            Key::Num7,
+            Key::Num8,
+            Key::Num9,
+        ];
+        for (idx, key) in SLOT_KEYS.into_iter().enumerate() {
+            let slot = idx as u8 + 1;
+            if self.input.command_used(key) && self.input.modifiers.shift {
+                action.set(AppAction::SetBookmark(slot));
+            } else if self.input.command_used(key) {
+                action.set(AppAction::JumpToBookmark(slot));
+            }
         }
 
+        // --- Pan the view when dragging or linking near the edge ---
+        let edge_pan = self.edge_scroll(ctx, board_rs.rect);
+        self.sim_menu.view.drag(edge_pan);
+
         // --- Handle dragging ---
         let inv_t = self.sim_menu.view.create_inv_transform();
-        if let Some((delta, item)) = self.input.drag_delta() {
+        if self.input.pressed_prim || self.input.drag_delta().is_none() {
+            self.drag_device_pos = None;
+            self.alignment_guides.clear();
+        }
+        let slicing = self.input.modifiers.alt && self.input.drag_delta().is_some();
+        if slicing {
+            let pos = inv_t * self.input.pointer_pos;
+            if self.slice_path.last() != Some(&pos) {
+                self.slice_path.push(pos);
+            }
+        } else if !self.slice_path.is_empty() {
+            self.board.slice_links(&self.slice_path, &self.settings);
+            self.slice_path.clear();
+        }
+        let selecting = self.input.modifiers.shift
+            && matches!(
+                self.input.drag_delta(),
+                Some((_, AppItem::Board(BoardItem::Board)))
+            );
+        if selecting {
+            let start = match self.select_box {
+                Some((start, _)) => start,
+                None => inv_t * self.input.press_pos,
+            };
+            self.select_box = Some((start, inv_t * self.input.pointer_pos));
+        } else if let Some((start, end)) = self.select_box.take() {
+            let rect = Rect::from_two_pos(start, end);
+            for (id, device) in &self.board.devices {
+                let device_rect =
+                    Rect::from_min_size(device.pos, graphics::device_size(device, &self.settings));
+                if device_rect.intersects(rect) && !self.selected_devices.contains(id) {
+                    self.selected_devices.push(*id);
+                }
+            }
+        }
+        let dragged = self.input.drag_delta().filter(|_| !slicing && !selecting);
+        if let Some((delta, item)) = dragged {
+            // keep what's dragged under the pointer while the view pans
+            let delta = delta - edge_pan;
             match item {
                 AppItem::Board(BoardItem::Board) => {
                     self.sim_menu.view.drag(delta);
@@ -514,29 +1814,76 @@ impl App {
                             self.board.drag_device(*id, inv_t * delta);
                         }
                     } else {
-                        self.board.drag_device(id, inv_t * delta);
+                        const ALIGN_THRESHOLD: f32 = 6.0;
+
+                        let pos = self.board.devices.get(&id).unwrap().pos;
+                        let raw_pos = self.drag_device_pos.unwrap_or(pos) + inv_t * delta;
+                        let (pos, guides) = self.board.align_device(
+                            id,
+                            raw_pos,
+                            &self.settings,
+                            inv_t * ALIGN_THRESHOLD,
+                        );
+                        self.board.devices.get_mut(&id).unwrap().pos = pos;
+                        self.board.modified = true;
+                        self.drag_device_pos = Some(raw_pos);
+                        self.alignment_guides = guides;
+                    }
+                }
+                AppItem::Board(BoardItem::LinkAnchor(start, link_idx, anchor_idx)) => {
+                    let link = self.board.get_link_mut(start, link_idx);
+                    if let Some(anchor) = link.and_then(|link| link.anchors.get_mut(anchor_idx)) {
+                        *anchor += inv_t * delta;
+                        self.board.modified = true;
                     }
                 }
                 AppItem::Board(BoardItem::InputCol) => {
-                    self.board.rect.min.x += inv_t * delta.x;
+                    self.board
+                        .drag_io_col(IoSel::Input, inv_t * delta.x, &self.settings);
                 }
                 AppItem::Board(BoardItem::OutputCol) => {
-                    self.board.rect.max.x += inv_t * delta.x;
+                    self.board
+                        .drag_io_col(IoSel::Output, inv_t * delta.x, &self.settings);
                 }
                 _ => {}
             }
         }
 
-        // --- Handle scrolling ---
-        self.sim_menu.view.drag(self.input.scroll_delta);
+        // --- Handle scrolling (and panning with two fingers) ---
+        let touch_pan =
+            (self.input.multi_touch).map_or(Vec2::ZERO, |touch| touch.translation_delta);
+        self.sim_menu.view.drag(self.input.scroll_delta + touch_pan);
 
-        // --- Handle zooming ---
+        // --- Handle zooming (pinching zooms around the fingers) ---
         let zoom_delta = ctx.input().zoom_delta();
         if zoom_delta != 1.0 {
-            let pos = self.input.pointer_pos - board_rs.rect.min;
+            let center = match self.input.multi_touch {
+                Some(_) => self.input.touch_center,
+                None => self.input.pointer_pos,
+            };
+            let pos = center - board_rs.rect.min;
             self.sim_menu.view.zoom(zoom_delta, pos.to_pos2());
         }
 
+        // --- Move the camera to a bookmark ---
+        let moved_view = self.input.scroll_delta != Vec2::ZERO
+            || touch_pan != Vec2::ZERO
+            || edge_pan != Vec2::ZERO
+            || zoom_delta != 1.0
+            || matches!(
+                self.input.drag_delta(),
+                Some((_, AppItem::Board(BoardItem::Board)))
+            );
+        if moved_view {
+            self.view_target = None;
+        }
+        if let Some(target) = &self.view_target {
+            const EASE: f32 = 0.25;
+            if self.sim_menu.view.approach(target, EASE) {
+                self.view_target = None;
+            }
+        }
+
         // --- Handle placing library ---
         let can_place_preset = matches!(self.input.hovered(), AppItem::Board(_));
         if self.held_presets.len() > 0 && self.input.pressed_prim && can_place_preset {
@@ -550,12 +1897,7 @@ impl App {
                 self.place_preset(&name, pos);
 
                 let preset = self.library.get_preset(&name).unwrap();
-                let size = graphics::calc_device_size(
-                    preset.data.num_inputs(),
-                    preset.data.num_outputs(),
-                    self.settings.device_min_pin_spacing,
-                );
-                pos.y += size.y;
+                pos.y += graphics::preset_size(preset, &self.settings).y;
             }
         }
 
@@ -568,6 +1910,110 @@ impl App {
 
             ui.set_width(100.0);
             let mut place_preset = None;
+            let mut swap_preset = None;
+
+            if let Some(link) = self
+                .context_link
+                .and_then(|(start, idx)| self.board.get_link_mut(start, idx))
+            {
+                let changed = ui
+                    .horizontal(|ui| {
+                        ui.label("delay");
+                        ui.add(DragValue::new(&mut link.delay).clamp_range(0..=50))
+                            .on_hover_text("extra updates for writes through this link")
+                            .changed()
+                    })
+                    .inner;
+                self.board.modified |= changed;
+                if ui.button("show only this net").clicked() {
+                    self.focus_net = self.context_link.map(|(start, _)| start);
+                    ui.close_menu();
+                }
+                ui.separator();
+            }
+
+            let context_input =
+                (self.context_input).and_then(|id| Some((id, self.board.inputs.get(&id)?.clock)));
+            if let Some((id, clock)) = context_input {
+                let mut period = clock.map(|clock| clock.period).unwrap_or(0);
+                let changed = ui
+                    .horizontal(|ui| {
+                        ui.label("clock");
+                        ui.add(DragValue::new(&mut period).clamp_range(0..=1000))
+                            .on_hover_text(
+                                "toggles the input every this many updates, 0 turns it off",
+                            )
+                            .changed()
+                    })
+                    .inner;
+                if changed {
+                    self.board.set_clock(id, period);
+                }
+                if clock.is_none() && ui.button("make clock").clicked() {
+                    self.board.set_clock(id, Clock::DEFAULT_PERIOD);
+                }
+                ui.separator();
+            }
+
+            if let Some(device) = self
+                .context_device
+                .and_then(|id| self.board.devices.get(&id))
+            {
+                ui.menu_button("swap preset", |ui| {
+                    ui.set_width(100.0);
+                    // presets with the same pin counts are listed first
+                    let (compatible, other): (Vec<_>, Vec<_>) = self
+                        .library
+                        .presets()
+                        .iter()
+                        .filter(|preset| preset.name != device.preset)
+                        .partition(|preset| {
+                            preset.data.num_inputs() == device.num_inputs()
+                                && preset.data.num_outputs() == device.num_outputs()
+                        });
+                    let has_compatible = !compatible.is_empty();
+                    for (idx, presets) in [compatible, other].iter().enumerate() {
+                        if idx == 1 && has_compatible && !presets.is_empty() {
+                            ui.separator();
+                        }
+                        for preset in presets {
+                            if ui.button(&preset.name).clicked() {
+                                swap_preset = Some(preset.name.clone());
+                                ui.close_menu();
+                            }
+                        }
+                    }
+                });
+                if ui.button("show truth table").clicked() {
+                    action.set(AppAction::ShowTruthTable(self.context_device.unwrap()));
+                    ui.close_menu();
+                }
+                if let DeviceData::Chip(_) = device.data {
+                    let id = self.context_device.unwrap();
+                    let gate_presets = self.board.chip_gate_presets(id, &self.library);
+                    let rs = ui
+                        .add_enabled(gate_presets.is_ok(), Button::new("flatten"))
+                        .on_hover_text("replace the chip with the gates inside it");
+                    let rs = match gate_presets {
+                        Ok(_) => rs,
+                        Err(err) => rs.on_disabled_hover_text(err),
+                    };
+                    if rs.clicked() {
+                        action.set(AppAction::FlattenChip(id));
+                        ui.close_menu();
+                    }
+                }
+                if device.constant_state().is_some() {
+                    let rs = ui
+                        .button("to input")
+                        .on_hover_text("replace it with an input in the same state");
+                    if rs.clicked() {
+                        action.set(AppAction::ConstantToInput(self.context_device.unwrap()));
+                        ui.close_menu();
+                    }
+                }
+                ui.separator();
+            }
 
             for (cat, library) in self.library.cats_sorted() {
                 ui.menu_button(cat, |ui| {
@@ -581,11 +2027,29 @@ impl App {
                 });
             }
 
+            ui.separator();
+            if ui.button("spread out").clicked() {
+                action.set(AppAction::SpreadDevices);
+                ui.close_menu();
+            }
+            if ui.button("arrange").clicked() {
+                action.set(AppAction::ArrangeDevices);
+                ui.close_menu();
+            }
+            let can_export = self.int.native && !self.selected_devices.is_empty();
+            if can_export && ui.button("export selection as SVG").clicked() {
+                action.set(AppAction::ExportSelection);
+                ui.close_menu();
+            }
             if self.settings.debug {
                 if ui.button("debug").clicked() {
                     println!("{:#?}", self.board);
                 }
             }
+            if let (Some(id), Some(name)) = (self.context_device, swap_preset) {
+                let preset = self.library.get_preset(&name).unwrap();
+                self.board.swap_device_preset(id, preset);
+            }
             if let Some(name) = place_preset {
                 self.place_preset(
                     &name,
@@ -594,6 +2058,25 @@ impl App {
             }
         });
         self.exec_action(action, &mut out_event);
+
+        self.profiler.current.frame_time = ctx.input().unstable_dt * 1000.0;
+        self.profiler.current.devices = self.board.devices.len();
+        self.profiler.current.links = self.board.num_links();
+        self.profiler.finish_frame();
+
+        let sim_active = !self.sim_menu.paused && !self.board.is_settled();
+        let pointer_moved = self.input.pointer_pos != self.input.prev_pointer_pos;
+        self.dirty = sim_active
+            || pointer_moved
+            || self.view_target.is_some()
+            || self.name_popup.is_some()
+            || self.input.drag_delta().is_some()
+            || edge_pan != Vec2::ZERO;
+        // saved with the board, see `SimMenu::load_board`
+        self.board.sim_speed = Some(SimSpeed {
+            paused: self.sim_menu.paused,
+            speed: self.sim_menu.speed,
+        });
         out_event
     }
 }
@@ -1,9 +1,12 @@
-use crate::presets::{ChipPreset, DevicePreset, PresetData};
+use crate::graphics::{
+    device_size, link_start_pos, link_target_pos, preset_size, segments_intersect, View,
+};
+use crate::presets::{ChipPreset, DevicePreset, Library, PresetData, HIGH_PRESET, LOW_PRESET};
 use crate::settings::Settings;
 use crate::*;
-use egui::{pos2, Pos2, Rect, Vec2};
-use hashbrown::HashMap;
-use tinyrand::{RandRange, Seeded, StdRand};
+use egui::{pos2, vec2, Pos2, Rect, Vec2};
+use hashbrown::{HashMap, HashSet};
+use tinyrand::{Rand, RandRange, Seeded, StdRand};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BoardItem {
@@ -14,6 +17,8 @@ pub enum BoardItem {
     DeviceInput(u64, usize),
     DeviceOutput(u64, usize),
     DeviceOutputLink(u64, usize, usize),
+    /// The start of the link, the index of the link and the index of the anchor
+    LinkAnchor(LinkStart<u64>, usize, usize),
     InputPin(u64),
     InputBulb(u64),
     InputLink(u64, usize),
@@ -23,7 +28,7 @@ pub enum BoardItem {
     OutputGroup(u64),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Write<T> {
     pub target: LinkTarget<T>,
     pub state: bool,
@@ -32,8 +37,57 @@ pub struct Write<T> {
 
 pub struct WriteQueue<T> {
     pub writes: Vec<Write<T>>,
-    pub buffer: Vec<(LinkTarget<T>, bool)>,
-    pub rand: StdRand,
+    /// The writes pushed this update, with their extra delay
+    pub buffer: Vec<(LinkTarget<T>, bool, u8)>,
+    pub rand: QueueRand,
+    /// The smallest and largest random delay added to writes, see `Settings::write_delay`
+    pub delays: [u8; 2],
+}
+
+/// The random delays of a `WriteQueue`. Remembers its seed and how many numbers it gave,
+/// so it can be saved and restored exactly
+pub struct QueueRand {
+    seed: u64,
+    draws: u32,
+    rand: StdRand,
+}
+impl QueueRand {
+    /// Reseeds itself after this many numbers, so restoring never replays more than this
+    const RESEED: u32 = 1 << 12;
+
+    pub fn seed(seed: u64) -> Self {
+        Self {
+            seed,
+            draws: 0,
+            rand: StdRand::seed(seed),
+        }
+    }
+    /// Replays `draws` numbers from `seed`
+    pub fn restore(seed: u64, draws: u32) -> Self {
+        let mut rand = Self::seed(seed);
+        for _ in 0..draws.min(Self::RESEED) {
+            rand.next_u64();
+        }
+        rand
+    }
+}
+impl Rand for QueueRand {
+    #[inline(always)]
+    fn next_u64(&mut self) -> u64 {
+        if self.draws == Self::RESEED {
+            *self = Self::seed(self.rand.next_u64());
+        }
+        self.draws += 1;
+        self.rand.next_u64()
+    }
+}
+
+/// The parts of a `WriteQueue` that normal saves leave out
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QueueState<T> {
+    pub buffer: Vec<(LinkTarget<T>, bool, u8)>,
+    pub seed: u64,
+    pub draws: u32,
 }
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -55,16 +109,28 @@ impl<T: std::fmt::Debug> std::fmt::Debug for WriteQueue<T> {
 }
 impl<T: Clone> Clone for WriteQueue<T> {
     fn clone(&self) -> Self {
-        Self::new(self.writes.clone())
+        Self {
+            delays: self.delays,
+            ..Self::new(self.writes.clone())
+        }
+    }
+}
+/// Like saving, only compares the writes (see `QueueState` for the rest)
+impl<T: PartialEq> PartialEq for WriteQueue<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.writes == other.writes
     }
 }
 
 impl<T> WriteQueue<T> {
+    pub const DEFAULT_DELAYS: [u8; 2] = [0, 2];
+
     pub fn new(writes: Vec<Write<T>>) -> Self {
         Self {
             writes,
             buffer: Vec::new(),
-            rand: StdRand::seed(rand_id()),
+            rand: QueueRand::seed(rand_id()),
+            delays: Self::DEFAULT_DELAYS,
         }
     }
     pub fn empty() -> Self {
@@ -80,21 +146,48 @@ impl<T> WriteQueue<T> {
         self.writes.clear();
         self.buffer.clear();
     }
+    /// Restarts the random delays from `seed`, so they come out the same every time
+    pub fn reseed(&mut self, seed: u64) {
+        self.rand = QueueRand::seed(seed);
+    }
+}
+impl<T: Clone> WriteQueue<T> {
+    pub fn state(&self) -> QueueState<T> {
+        QueueState {
+            buffer: self.buffer.clone(),
+            seed: self.rand.seed,
+            draws: self.rand.draws,
+        }
+    }
+    pub fn restore(&mut self, state: QueueState<T>) {
+        self.buffer = state.buffer;
+        self.rand = QueueRand::restore(state.seed, state.draws);
+    }
 }
 impl<T: PartialEq + Clone + Copy> WriteQueue<T> {
     // note: HOT CODE!
     #[inline(always)]
     pub fn push(&mut self, target: LinkTarget<T>, state: bool) {
-        self.buffer.push((target, state));
+        self.buffer.push((target, state, 0));
+    }
+    /// Pushes a write that takes `delay` more updates than usual
+    #[inline(always)]
+    pub fn push_delayed(&mut self, target: LinkTarget<T>, state: bool, delay: u8) {
+        self.buffer.push((target, state, delay));
     }
 
     #[inline(always)] // only one call site
-    fn push_raw(&mut self, target: LinkTarget<T>, state: bool) {
-        let new_delay = self.rand.next_range(0u64..3) as u8;
+    fn push_raw(&mut self, target: LinkTarget<T>, state: bool, delay: u8) {
+        let [min, max] = self.delays;
+        let random_delay = match max > min {
+            true => min + self.rand.next_range(0..(max - min) as u64 + 1) as u8,
+            false => min,
+        };
+        let new_delay = random_delay.saturating_add(delay);
         for write in &mut self.writes {
             if write.target == target {
                 write.state = state;
-                write.delay += new_delay;
+                write.delay = write.delay.saturating_add(new_delay);
                 return;
             }
         }
@@ -108,8 +201,8 @@ impl<T: PartialEq + Clone + Copy> WriteQueue<T> {
     #[inline(always)]
     pub fn flush(&mut self) {
         for idx in 0..self.buffer.len() {
-            let (target, state) = self.buffer[idx];
-            self.push_raw(target, state);
+            let (target, state, delay) = self.buffer[idx];
+            self.push_raw(target, state, delay);
         }
         self.buffer.clear();
     }
@@ -137,7 +230,7 @@ impl<T: PartialEq + Clone + Copy> WriteQueue<T> {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DeviceData {
     CombGate(CombGate),
     Chip(Chip),
@@ -147,7 +240,34 @@ impl DeviceData {
         match preset {
             PresetData::CombGate(e) => Self::CombGate(CombGate::new(e.table.clone())),
             PresetData::Chip(e) => Self::Chip(Chip::from_preset(e)),
-            _ => panic!(),
+            PresetData::Builtin(e) => e.device_data(),
+        }
+    }
+
+    pub fn truth_table(&self) -> Result<TruthTable, &'static str> {
+        match self {
+            Self::CombGate(e) => Ok(e.table.clone()),
+            Self::Chip(e) => e.truth_table(),
+        }
+    }
+
+    /// Puts the device back into the state it was placed in
+    pub fn reset(&mut self) {
+        match self {
+            Self::CombGate(e) => e.reset(),
+            Self::Chip(e) => e.reset(),
+        }
+    }
+
+    /// Recomputes the outputs from the inputs, see `Board::recompute_all`.
+    /// A chip queues its own writes, so only gates return changed outputs
+    pub fn recompute(&mut self) -> ChangedOutputs {
+        match self {
+            Self::CombGate(e) => e.recompute(),
+            Self::Chip(e) => {
+                e.recompute();
+                ChangedOutputs::none()
+            }
         }
     }
 
@@ -162,22 +282,22 @@ impl DeviceData {
     }
 
     #[inline(always)]
-    pub fn input(&self) -> BitField {
+    pub fn input(&self) -> &BitField {
         match self {
-            Self::CombGate(e) => e.input,
-            Self::Chip(e) => e.input,
+            Self::CombGate(e) => &e.input,
+            Self::Chip(e) => &e.input,
         }
     }
     #[inline(always)]
-    pub fn output(&self) -> BitField {
+    pub fn output(&self) -> &BitField {
         match self {
-            Self::CombGate(e) => e.output,
-            Self::Chip(e) => e.output,
+            Self::CombGate(e) => &e.output,
+            Self::Chip(e) => &e.output,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Device {
     pub pos: Pos2,
     pub data: DeviceData,
@@ -198,6 +318,15 @@ impl Device {
     pub fn num_inputs(&self) -> usize {
         self.data.input().len
     }
+    /// The output of a gate without inputs, like the High and Low presets
+    pub fn constant_state(&self) -> Option<bool> {
+        match &self.data {
+            DeviceData::CombGate(gate) if gate.input.len == 0 && gate.output.len == 1 => {
+                Some(gate.output.get(0))
+            }
+            _ => None,
+        }
+    }
     #[inline(always)]
     pub fn num_outputs(&self) -> usize {
         self.data.output().len
@@ -210,7 +339,7 @@ pub enum IoSel {
     Output,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Io {
     pub name: String,
     pub y_pos: f32,
@@ -228,32 +357,151 @@ impl Io {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Input {
     pub io: Io,
     pub links: Vec<Link>,
+    /// Overrides `io.state` for propagation, `Some(false)` mutes it, `Some(true)` solos it
+    #[serde(default)]
+    pub force: Option<bool>,
+    /// Toggles the input by itself while the sim runs
+    #[serde(default)]
+    pub clock: Option<Clock>,
 }
 impl Input {
     pub fn new(io: Io) -> Self {
         Self {
             io,
             links: Vec::new(),
+            force: None,
+            clock: None,
         }
     }
+
+    /// The state that is propagated to the links
+    #[inline(always)]
+    pub fn state(&self) -> bool {
+        self.force.unwrap_or(self.io.state)
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Toggles an input every `period` updates, see `Board::update_clocks`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Clock {
+    pub period: u32,
+    /// Updates since the last toggle, saved so a loaded board continues where it left off
+    pub counter: u32,
+}
+impl Clock {
+    pub const DEFAULT_PERIOD: u32 = 10;
+
+    pub fn new(period: u32) -> Self {
+        Self { period, counter: 0 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Output {
     pub io: Io,
+    /// The state this output should have, for self-checking test circuits
+    #[serde(default)]
+    pub expected: Option<bool>,
 }
 impl Output {
     pub fn new(io: Io) -> Self {
-        Self { io }
+        Self { io, expected: None }
     }
+
+    pub fn failed(&self) -> bool {
+        matches!(self.expected, Some(expected) if expected != self.io.state)
+    }
+}
+
+/// Info about a board for sharing, isn't simulated or packed
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct BoardMeta {
+    pub title: String,
+    pub author: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+    /// What each color of the link palette means, by index
+    #[serde(default)]
+    pub color_labels: Vec<String>,
+    /// Save the whole simulation state with the board (see `SimState`), so it resumes exactly
+    #[serde(default)]
+    pub exact_sim: bool,
+    /// Always open paused, so the board only advances on Step
+    #[serde(default)]
+    pub manual_only: bool,
+}
+impl BoardMeta {
+    pub fn color_label(&self, color: usize) -> &str {
+        self.color_labels.get(color).map_or("", String::as_str)
+    }
+    pub fn set_color_label(&mut self, color: usize, label: String) {
+        if self.color_labels.len() <= color {
+            self.color_labels.resize(color + 1, String::new());
+        }
+        self.color_labels[color] = label;
+    }
+}
+
+/// A saved camera position, jumped to with Ctrl+<slot>
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    /// 1 to 9
+    pub slot: u8,
+    pub view: View,
+}
+
+/// A board that kept updating past the limit, see `Board::settle_inputs`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unsettled {
+    /// The devices that were still being written to, which are likely part of a loop
+    pub devices: Vec<u64>,
+}
+impl From<Unsettled> for &'static str {
+    fn from(_: Unsettled) -> Self {
+        "Has a loop or is too big"
+    }
+}
+
+/// What `Board::measure_period` found
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Period {
+    /// Nothing was left to simulate after this many ticks
+    Settled(usize),
+    /// The outputs repeat every this many ticks
+    Oscillates(usize),
+    /// The outputs repeat every this many ticks on average, but not exactly
+    Jitters(f32),
+    /// The outputs didn't settle or repeat in the ticks that were run
+    Unknown,
+}
+
+/// If the simulation was paused and how fast it ran when the board was saved
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimSpeed {
+    pub paused: bool,
+    pub speed: u32,
+}
+
+/// The simulation state that normal saves leave out: writes that weren't flushed yet,
+/// and where the random delays are. Saved when `BoardMeta::exact_sim` is on
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimState {
+    pub queue: QueueState<u64>,
+    /// The queues of the chips on the board, by device ID
+    pub chips: HashMap<u64, QueueState<usize>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Board {
+    #[serde(default)]
+    pub meta: BoardMeta,
     /// This is not in screen space, this is in world space
     pub rect: Rect,
     pub write_queue: WriteQueue<u64>,
@@ -264,6 +512,50 @@ pub struct Board {
 
     pub input_groups: HashMap<u64, Group>,
     pub output_groups: HashMap<u64, Group>,
+
+    /// Only set while saving and loading, see `capture_sim_state`
+    #[serde(default)]
+    pub sim_state: Option<SimState>,
+    #[serde(default)]
+    pub sim_speed: Option<SimSpeed>,
+    /// The seed of the random write delays, so the sim runs the same every time it's reset.
+    /// `None` picks a new seed every time, see `reseed`
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    /// If the board was edited since it was last saved
+    #[serde(skip)]
+    pub modified: bool,
+}
+/// `modified` isn't saved, so it's left out, a loaded board is equal to the board that was saved
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            meta,
+            rect,
+            write_queue,
+            inputs,
+            outputs,
+            devices,
+            input_groups,
+            output_groups,
+            sim_state,
+            sim_speed,
+            seed,
+            modified: _,
+        } = self;
+        *meta == other.meta
+            && *rect == other.rect
+            && *write_queue == other.write_queue
+            && *inputs == other.inputs
+            && *outputs == other.outputs
+            && *devices == other.devices
+            && *input_groups == other.input_groups
+            && *output_groups == other.output_groups
+            && *sim_state == other.sim_state
+            && *sim_speed == other.sim_speed
+            && *seed == other.seed
+    }
 }
 impl Default for Board {
     fn default() -> Self {
@@ -271,12 +563,15 @@ impl Default for Board {
     }
 }
 impl Board {
+    pub const DEFAULT_SIZE: Vec2 = vec2(600.0, 400.0);
+
     pub fn new() -> Self {
+        Self::with_size(Self::DEFAULT_SIZE)
+    }
+    pub fn with_size(size: Vec2) -> Self {
         Self {
-            rect: Rect {
-                min: pos2(0.0, 0.0),
-                max: pos2(600.0, 400.0),
-            },
+            meta: BoardMeta::default(),
+            rect: Rect::from_min_size(pos2(0.0, 0.0), size),
             write_queue: WriteQueue::empty(),
 
             inputs: HashMap::new(),
@@ -285,14 +580,345 @@ impl Board {
 
             input_groups: HashMap::new(),
             output_groups: HashMap::new(),
+
+            sim_state: None,
+            sim_speed: None,
+            seed: None,
+
+            modified: false,
         }
     }
 
+    /// Call before saving, stores the `SimState` if `BoardMeta::exact_sim` is on
+    pub fn capture_sim_state(&mut self) {
+        self.sim_state = self.meta.exact_sim.then(|| SimState {
+            queue: self.write_queue.state(),
+            chips: self
+                .devices
+                .iter()
+                .filter_map(|(id, device)| match &device.data {
+                    DeviceData::Chip(chip) => Some((*id, chip.write_queue.state())),
+                    DeviceData::CombGate(_) => None,
+                })
+                .collect(),
+        });
+    }
+    /// Call after loading, puts back the `SimState` if it was saved
+    pub fn resume_sim_state(&mut self) {
+        let Some(state) = self.sim_state.take() else { return };
+        self.write_queue.restore(state.queue);
+        for (id, queue) in state.chips {
+            if let Some(DeviceData::Chip(chip)) = self.devices.get_mut(&id).map(|d| &mut d.data) {
+                chip.write_queue.restore(queue);
+            }
+        }
+    }
+
+    pub fn get_bookmark(&self, slot: u8) -> Option<&Bookmark> {
+        self.meta
+            .bookmarks
+            .iter()
+            .find(|bookmark| bookmark.slot == slot)
+    }
+    /// Saves the view in a bookmark slot, keeping the name if the slot was used
+    pub fn set_bookmark(&mut self, slot: u8, view: View) {
+        let bookmarks = &mut self.meta.bookmarks;
+        match bookmarks.iter_mut().find(|bookmark| bookmark.slot == slot) {
+            Some(bookmark) => bookmark.view = view,
+            None => {
+                bookmarks.push(Bookmark {
+                    name: format!("Bookmark {slot}"),
+                    slot,
+                    view,
+                });
+                bookmarks.sort_by_key(|bookmark| bookmark.slot);
+            }
+        }
+        self.modified = true;
+    }
+    /// The lowest slot without a bookmark
+    pub fn free_bookmark_slot(&self) -> Option<u8> {
+        (1..=9).find(|slot| self.get_bookmark(*slot).is_none())
+    }
+
     pub fn item_count(&self) -> usize {
         self.inputs.len() + self.outputs.len() + self.devices.len()
     }
+    pub fn get_link_mut(&mut self, start: LinkStart<u64>, idx: usize) -> Option<&mut Link> {
+        match start {
+            LinkStart::Input(id) => self.inputs.get_mut(&id)?.links.get_mut(idx),
+            LinkStart::DeviceOutput(id, output) => self
+                .devices
+                .get_mut(&id)?
+                .links
+                .get_mut(output)?
+                .get_mut(idx),
+        }
+    }
+    /// If any link has an extra delay, which is lost when packing into a chip
+    pub fn has_link_delays(&self) -> bool {
+        let input_links = self.inputs.values().flat_map(|input| &input.links);
+        let device_links = (self.devices.values()).flat_map(|device| device.links.iter().flatten());
+        input_links.chain(device_links).any(|link| link.delay > 0)
+    }
+
+    pub fn num_links(&self) -> usize {
+        let input_links: usize = self.inputs.values().map(|input| input.links.len()).sum();
+        let device_links: usize = self
+            .devices
+            .values()
+            .flat_map(|device| &device.links)
+            .map(|links| links.len())
+            .sum();
+        input_links + device_links
+    }
+
+    /// Clears all pending writes, resets every device, then queues writes for every link
+    /// so the board recomputes from its inputs
+    pub fn reset_sim(&mut self) {
+        self.write_queue = WriteQueue::empty();
+        for output in self.outputs.values_mut() {
+            output.io.state = false;
+        }
+        for clock in self
+            .inputs
+            .values_mut()
+            .filter_map(|input| input.clock.as_mut())
+        {
+            clock.counter = 0;
+        }
+        for device in self.devices.values_mut() {
+            device.data.reset();
+            let output = device.data.output();
+            for (idx, links) in device.links.iter().enumerate() {
+                for link in links {
+                    (self.write_queue).push_delayed(link.target, output.get(idx), link.delay);
+                }
+            }
+        }
+        for input in self.inputs.values() {
+            for link in &input.links {
+                (self.write_queue).push_delayed(link.target, input.state(), link.delay);
+            }
+        }
+        if let Some(seed) = self.seed {
+            self.reseed(seed);
+        }
+    }
+
+    /// Fixes the seed of the random write delays, and restarts the delays of the board and its chips from it
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        self.write_queue.reseed(seed);
+        for (id, device) in &mut self.devices {
+            if let DeviceData::Chip(chip) = &mut device.data {
+                // every chip gets its own numbers, but the same ones every time
+                chip.write_queue.reseed(seed ^ id);
+            }
+        }
+    }
+
+    /// Sets the random delay range of the board's queue and the queues of its chips
+    pub fn set_write_delays(&mut self, delays: [u8; 2]) {
+        self.write_queue.delays = delays;
+        for device in self.devices.values_mut() {
+            if let DeviceData::Chip(chip) = &mut device.data {
+                chip.write_queue.delays = delays;
+            }
+        }
+    }
+
+    /// Fixes outputs that got out of date with their inputs (and links that got out of date with their start)
+    /// after editing the board, without resetting it like `reset_sim`
+    pub fn recompute_all(&mut self) {
+        for device in self.devices.values_mut() {
+            let mut changed_outputs = device.data.recompute();
+            while let Some((output, state)) = changed_outputs.next() {
+                for link in &device.links[output] {
+                    self.write_queue
+                        .push_delayed(link.target, state, link.delay);
+                }
+            }
+        }
+        let input_links = self
+            .inputs
+            .values()
+            .flat_map(|input| (input.links.iter()).map(|link| (link.target, input.state())));
+        let device_links = self.devices.values().flat_map(|device| {
+            let output = device.data.output();
+            (device.links.iter().enumerate()).flat_map(move |(idx, links)| {
+                links.iter().map(move |link| (link.target, output.get(idx)))
+            })
+        });
+        let stale: Vec<_> = input_links
+            .chain(device_links)
+            .filter(|(target, state)| self.link_target_state(*target) != Some(*state))
+            .collect();
+        for (target, state) in stale {
+            self.write_queue.push(target, state);
+        }
+    }
+
+    /// Sets the inputs, then updates until there are no more pending writes
+    pub fn settle_inputs(
+        &mut self,
+        inputs: &[(u64, bool)],
+        max_updates: usize,
+    ) -> Result<(), Unsettled> {
+        for (id, state) in inputs {
+            self.set_input(*id, *state);
+        }
+        let mut total_updates = 0;
+        while !self.is_settled() {
+            if total_updates > max_updates {
+                return Err(self.unsettled());
+            }
+            self.update();
+            total_updates += 1;
+        }
+        Ok(())
+    }
+
+    /// Keeps updating a board that doesn't settle, and collects the devices that are written to
+    fn unsettled(&mut self) -> Unsettled {
+        const TRACK_UPDATES: usize = 32;
+
+        let mut devices = HashSet::new();
+        for _ in 0..TRACK_UPDATES {
+            let writes = (self.write_queue.writes.iter()).map(|write| write.target);
+            let buffer = (self.write_queue.buffer.iter()).map(|(target, _, _)| *target);
+            for target in writes.chain(buffer) {
+                if let LinkTarget::DeviceInput(id, _) = target {
+                    devices.insert(id);
+                }
+            }
+            // a chip can also loop inside, without writing to anything on the board
+            for (id, device) in &self.devices {
+                if matches!(&device.data, DeviceData::Chip(chip) if chip.write_queue.len() > 0) {
+                    devices.insert(*id);
+                }
+            }
+            self.update();
+        }
+        let mut devices: Vec<u64> = devices.into_iter().collect();
+        devices.sort();
+        Unsettled { devices }
+    }
+
+    /// Settles a copy of the board with every combination of the inputs,
+    /// or only with the current inputs if there are more than `max_inputs`
+    pub fn check_settles(&self, max_inputs: usize, max_updates: usize) -> Result<(), Unsettled> {
+        let mut board = self.clone();
+        // cloning the queue leaves out the writes that weren't flushed yet
+        board.write_queue.buffer = self.write_queue.buffer.clone();
+        let inputs = board.inputs_sorted();
+        if inputs.len() > max_inputs {
+            return board.settle_inputs(&[], max_updates);
+        }
+        for combination in 0..(1u64 << inputs.len()) {
+            let states: Vec<_> = (inputs.iter().enumerate())
+                .map(|(idx, id)| (*id, ((combination >> idx) & 1) == 1))
+                .collect();
+            board.settle_inputs(&states, max_updates)?;
+        }
+        Ok(())
+    }
+
+    /// Applies each input vector (in `inputs_sorted` order) to a copy of the board,
+    /// and reads the outputs (in `outputs_sorted` order) once it has settled
+    pub fn run_vectors(
+        &self,
+        vectors: &[Vec<bool>],
+        max_updates: usize,
+    ) -> Vec<Result<Vec<bool>, &'static str>> {
+        let mut board = self.clone();
+        let inputs = board.inputs_sorted();
+        let outputs = board.outputs_sorted();
+        vectors
+            .iter()
+            .map(|vector| {
+                let states: Vec<_> = inputs.iter().copied().zip(vector.iter().copied()).collect();
+                board.settle_inputs(&states, max_updates)?;
+                Ok(outputs
+                    .iter()
+                    .map(|id| board.outputs.get(id).unwrap().io.state)
+                    .collect())
+            })
+            .collect()
+    }
+
+    /// Simulates a copy of the board for up to `max_ticks`, recording the outputs every tick,
+    /// and finds the smallest period they repeat with
+    pub fn measure_period(&self, max_ticks: usize) -> Period {
+        let mut board = self.clone();
+        // cloning the queue leaves out the writes that weren't flushed yet
+        board.write_queue.buffer = self.write_queue.buffer.clone();
+        let mut outputs = Vec::with_capacity(max_ticks);
+        for tick in 0..max_ticks {
+            if board.is_settled() {
+                return Period::Settled(tick);
+            }
+            board.update();
+            outputs.push(board.output_field());
+        }
+
+        // the first half may still be starting up, so only the second half has to repeat
+        let tail = outputs.len() / 2;
+        let start = outputs.len() - tail;
+        if outputs[start..]
+            .iter()
+            .all(|state| *state == outputs[start])
+        {
+            return Period::Unknown;
+        }
+        let repeats =
+            |period: usize| (start..outputs.len()).all(|i| outputs[i] == outputs[i - period]);
+        if let Some(period) = (1..=tail / 2).find(|period| repeats(*period)) {
+            return Period::Oscillates(period);
+        }
+
+        // the random write delays make most oscillators jitter,
+        // so use the mean time between the outputs changing to the same state
+        let changes: Vec<usize> = (start + 1..outputs.len())
+            .filter(|i| outputs[*i] != outputs[i - 1])
+            .collect();
+        let Some(first) = changes.first() else { return Period::Unknown };
+        let cycles: Vec<&usize> = changes
+            .iter()
+            .filter(|i| outputs[**i] == outputs[*first])
+            .collect();
+        match cycles.len() {
+            0..=2 => Period::Unknown,
+            len => Period::Jitters((*cycles[len - 1] - first) as f32 / (len - 1) as f32),
+        }
+    }
+
+    /// The presets that devices on the board use, but aren't in the library, sorted by name
+    pub fn missing_presets(&self, library: &Library) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .devices
+            .values()
+            .filter(|device| library.get_preset(&device.preset).is_none())
+            .map(|device| device.preset.clone())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// If there are no pending writes on the board or in any chip on the board
+    pub fn is_settled(&self) -> bool {
+        if self.write_queue.len() > 0 || !self.write_queue.buffer.is_empty() {
+            return false;
+        }
+        self.devices.values().all(|device| match &device.data {
+            DeviceData::Chip(chip) => chip.write_queue.len() == 0,
+            DeviceData::CombGate(_) => true,
+        })
+    }
 
     pub fn update(&mut self) {
+        self.update_clocks();
         while let Some(write) = self.write_queue.next() {
             match write.target {
                 LinkTarget::DeviceInput(device, input) => {
@@ -301,7 +927,8 @@ impl Board {
                     let mut changed_outputs = device.data.set_input(input, write.state);
                     while let Some((output, state)) = changed_outputs.next() {
                         for link in &device.links[output] {
-                            self.write_queue.push(link.target, state);
+                            self.write_queue
+                                .push_delayed(link.target, state, link.delay);
                         }
                     }
                 }
@@ -319,7 +946,8 @@ impl Board {
             let mut changed_outputs = chip.update();
             while let Some((output, state)) = changed_outputs.next() {
                 for link in &device.links[output] {
-                    self.write_queue.push(link.target, state);
+                    self.write_queue
+                        .push_delayed(link.target, state, link.delay);
                 }
             }
         }
@@ -329,11 +957,320 @@ impl Board {
 }
 impl Board {
     pub fn add_device(&mut self, id: u64, device: Device) {
+        // a device that comes with links (like a pasted one) writes its outputs to them right away
+        let output = device.data.output();
+        for (idx, links) in device.links.iter().enumerate() {
+            for link in links {
+                self.write_queue.push(link.target, output.get(idx));
+            }
+        }
         self.devices.insert(id, device);
+        self.modified = true;
+    }
+
+    /// A position at or near `pos` that no device is at yet, so a new device doesn't hide another one
+    pub fn free_device_pos(&self, pos: Pos2, settings: &Settings) -> Pos2 {
+        const SAME_DIST: f32 = 2.0;
+        const MAX_TRIES: usize = 20;
+
+        let step = Vec2::splat(settings.device_min_pin_spacing * 0.5);
+        let mut pos = pos;
+        for _ in 0..MAX_TRIES {
+            let taken = self
+                .devices
+                .values()
+                .any(|device| device.pos.distance(pos) < SAME_DIST);
+            if !taken {
+                break;
+            }
+            pos += step;
+        }
+        pos
+    }
+    /// The devices at the same position as another device (to the nearest point)
+    pub fn stacked_devices(&self) -> Vec<u64> {
+        let mut at: HashMap<(i32, i32), Vec<u64>> = HashMap::new();
+        for (id, device) in &self.devices {
+            let key = (device.pos.x.round() as i32, device.pos.y.round() as i32);
+            at.entry(key).or_default().push(*id);
+        }
+        at.into_values()
+            .filter(|ids| ids.len() > 1)
+            .flatten()
+            .collect()
     }
 
     pub fn drag_device(&mut self, id: u64, drag: Vec2) {
         self.devices.get_mut(&id).unwrap().pos += drag;
+        self.modified = true;
+    }
+
+    /// The bounding box of the devices, including their pins
+    pub fn devices_rect(&self, ids: &[u64], settings: &Settings) -> Option<Rect> {
+        let mut rects = ids.iter().filter_map(|id| {
+            let device = self.devices.get(id)?;
+            Some(Rect::from_min_size(
+                device.pos,
+                device_size(device, settings),
+            ))
+        });
+        let first = rects.next()?;
+        let rect = rects.fold(first, |rect, other| rect.union(other));
+        Some(rect.expand2(vec2(settings.device_pin_size, 0.0)))
+    }
+
+    /// The bounds of the board and every device on it, even devices outside of the board
+    pub fn content_rect(&self, settings: &Settings) -> Rect {
+        let ids: Vec<u64> = self.devices.keys().copied().collect();
+        match self.devices_rect(&ids, settings) {
+            Some(rect) => rect.union(self.rect),
+            None => self.rect,
+        }
+    }
+
+    /// Snaps the edges or center of a device at `pos` to line up with other devices within `threshold`.
+    /// Returns the snapped position, and the guide lines it lined up with.
+    pub fn align_device(
+        &self,
+        id: u64,
+        pos: Pos2,
+        settings: &Settings,
+        threshold: f32,
+    ) -> (Pos2, Vec<(Pos2, Pos2)>) {
+        let size = device_size(self.devices.get(&id).unwrap(), settings);
+        let rect = Rect::from_min_size(pos, size);
+        let lines = |rect: Rect| {
+            let xs = [rect.min.x, rect.center().x, rect.max.x];
+            let ys = [rect.min.y, rect.center().y, rect.max.y];
+            (xs, ys)
+        };
+        let (my_xs, my_ys) = lines(rect);
+
+        // (offset, line, other rect) of the closest line on each axis
+        let mut snap_x: Option<(f32, f32, Rect)> = None;
+        let mut snap_y: Option<(f32, f32, Rect)> = None;
+        for (other_id, other) in &self.devices {
+            if *other_id == id {
+                continue;
+            }
+            let other_rect = Rect::from_min_size(other.pos, device_size(other, settings));
+            let (xs, ys) = lines(other_rect);
+            for (mine, others, snap) in [(my_xs, xs, &mut snap_x), (my_ys, ys, &mut snap_y)] {
+                for a in mine {
+                    for b in others {
+                        let offset = b - a;
+                        let closer = match snap {
+                            Some((snap, _, _)) => offset.abs() < snap.abs(),
+                            None => true,
+                        };
+                        if offset.abs() <= threshold && closer {
+                            *snap = Some((offset, b, other_rect));
+                        }
+                    }
+                }
+            }
+        }
+
+        let offset = vec2(
+            snap_x.map(|(x, _, _)| x).unwrap_or(0.0),
+            snap_y.map(|(y, _, _)| y).unwrap_or(0.0),
+        );
+        let rect = rect.translate(offset);
+        let mut guides = Vec::new();
+        if let Some((_, x, other)) = snap_x {
+            let (min_y, max_y) = (rect.min.y.min(other.min.y), rect.max.y.max(other.max.y));
+            guides.push((pos2(x, min_y), pos2(x, max_y)));
+        }
+        if let Some((_, y, other)) = snap_y {
+            let (min_x, max_x) = (rect.min.x.min(other.min.x), rect.max.x.max(other.max.x));
+            guides.push((pos2(min_x, y), pos2(max_x, y)));
+        }
+        (rect.min, guides)
+    }
+
+    /// Snaps a link anchor at `pos` to line up with the pins and link anchors on the board
+    /// (and the `extra` anchors that aren't in a link yet) within `threshold`,
+    /// or else to `Settings::anchor_grid` if it's on.
+    /// Returns the snapped position, and the guide lines it lined up with.
+    pub fn snap_anchor(
+        &self,
+        pos: Pos2,
+        extra: &[Pos2],
+        settings: &Settings,
+        threshold: f32,
+    ) -> (Pos2, Vec<(Pos2, Pos2)>) {
+        let mut points = extra.to_vec();
+        for start in self.link_starts() {
+            points.extend(link_start_pos(settings, self, start));
+        }
+        for target in self.link_targets() {
+            points.extend(link_target_pos(settings, self, target));
+        }
+        let input_links = self.inputs.values().flat_map(|input| &input.links);
+        let device_links = self
+            .devices
+            .values()
+            .flat_map(|device| device.links.iter().flatten());
+        for link in input_links.chain(device_links) {
+            points.extend(&link.anchors);
+        }
+
+        // (offset, point) of the closest point on each axis
+        let mut snaps: [Option<(f32, Pos2)>; 2] = [None; 2];
+        for point in points {
+            for (axis, snap) in snaps.iter_mut().enumerate() {
+                let offset = point[axis] - pos[axis];
+                let closer = match snap {
+                    Some((snap, _)) => offset.abs() < snap.abs(),
+                    None => true,
+                };
+                if offset.abs() <= threshold && closer {
+                    *snap = Some((offset, point));
+                }
+            }
+        }
+
+        let grid = settings.anchor_grid;
+        let mut snapped = pos;
+        for (axis, snap) in snaps.iter().enumerate() {
+            match snap {
+                Some((offset, _)) => snapped[axis] += offset,
+                None if grid > 0.0 => snapped[axis] = (pos[axis] / grid).round() * grid,
+                None => {}
+            }
+        }
+        let guides = snaps
+            .iter()
+            .flatten()
+            .map(|(_, point)| (*point, snapped))
+            .collect();
+        (snapped, guides)
+    }
+
+    /// Nudges the given devices apart until none of them overlap
+    pub fn spread_devices(&mut self, ids: &[u64], settings: &Settings) {
+        const MAX_PASSES: usize = 50;
+        const GAP: f32 = 5.0;
+
+        // the selection can still hold the ids of removed devices
+        let mut ids: Vec<u64> = (ids.iter().copied())
+            .filter(|id| self.devices.contains_key(id))
+            .collect();
+        ids.sort();
+        self.modified = true;
+        for _ in 0..MAX_PASSES {
+            let mut moved = false;
+            for i in 0..ids.len() {
+                for j in (i + 1)..ids.len() {
+                    let a = &self.devices[&ids[i]];
+                    let b = &self.devices[&ids[j]];
+                    let a_rect = Rect::from_min_size(a.pos, device_size(a, settings));
+                    let b_rect = Rect::from_min_size(b.pos, device_size(b, settings));
+                    if !a_rect.intersects(b_rect) {
+                        continue;
+                    }
+                    let overlap_x = a_rect.max.x.min(b_rect.max.x) - a_rect.min.x.max(b_rect.min.x);
+                    let overlap_y = a_rect.max.y.min(b_rect.max.y) - a_rect.min.y.max(b_rect.min.y);
+
+                    // move along the axis with the least overlap,
+                    // devices at the same position are pushed down/right
+                    let dir = b_rect.center() - a_rect.center();
+                    let push = if overlap_x < overlap_y {
+                        let sign = if dir.x < 0.0 { -1.0 } else { 1.0 };
+                        vec2(sign * (overlap_x + GAP) * 0.5, 0.0)
+                    } else {
+                        let sign = if dir.y < 0.0 { -1.0 } else { 1.0 };
+                        vec2(0.0, sign * (overlap_y + GAP) * 0.5)
+                    };
+                    self.devices.get_mut(&ids[i]).unwrap().pos -= push;
+                    self.devices.get_mut(&ids[j]).unwrap().pos += push;
+                    moved = true;
+                }
+            }
+            if !moved {
+                break;
+            }
+        }
+    }
+
+    /// Lays out all devices left to right, in columns by how many devices come before them.
+    /// Each column is ordered by the height of what links to its devices.
+    pub fn arrange_devices(&mut self, settings: &Settings) {
+        const GAP: f32 = 30.0;
+
+        let mut ids: Vec<u64> = self.devices.keys().copied().collect();
+        ids.sort();
+        let index: HashMap<u64, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+
+        // the heights of the inputs and the devices that link to each device
+        let mut from_inputs = vec![Vec::new(); ids.len()];
+        let mut from_devices = vec![Vec::new(); ids.len()];
+        for input in self.inputs.values() {
+            for link in &input.links {
+                if let LinkTarget::DeviceInput(device, _) = link.target {
+                    from_inputs[index[&device]].push(input.io.y_pos);
+                }
+            }
+        }
+        for (idx, id) in ids.iter().enumerate() {
+            for link in self.devices[id].links.iter().flatten() {
+                if let LinkTarget::DeviceInput(device, _) = link.target {
+                    from_devices[index[&device]].push(idx);
+                }
+            }
+        }
+
+        // loops would go on forever, so stop after the longest possible chain
+        let mut depth = vec![0; ids.len()];
+        for _ in 0..ids.len() {
+            let mut changed = false;
+            for (idx, sources) in from_devices.iter().enumerate() {
+                for &src in sources {
+                    if depth[idx] < depth[src] + 1 {
+                        depth[idx] = depth[src] + 1;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let sizes: Vec<Vec2> = (ids.iter())
+            .map(|id| device_size(&self.devices[id], settings))
+            .collect();
+        let mut center_y: Vec<Option<f32>> = vec![None; ids.len()];
+        let mut x = self.rect.min.x + settings.board_io_col_w + settings.board_io_pin_size + GAP;
+        for col in 0..depth.iter().max().map_or(0, |max| max + 1) {
+            let mut members: Vec<(usize, f32)> = (0..ids.len())
+                .filter(|idx| depth[*idx] == col)
+                .map(|idx| {
+                    let devices = from_devices[idx].iter().filter_map(|src| center_y[*src]);
+                    let ys: Vec<f32> = from_inputs[idx].iter().copied().chain(devices).collect();
+                    let key = match ys.is_empty() {
+                        true => self.devices[&ids[idx]].pos.y,
+                        false => ys.iter().sum::<f32>() / ys.len() as f32,
+                    };
+                    (idx, key)
+                })
+                .collect();
+            members.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+            let col_w = members
+                .iter()
+                .map(|(idx, _)| sizes[*idx].x)
+                .fold(0.0, f32::max);
+            let col_h: f32 = members.iter().map(|(idx, _)| sizes[*idx].y + GAP).sum();
+            let mut y = self.rect.center().y - (col_h - GAP) * 0.5;
+            for (idx, _) in members {
+                self.devices.get_mut(&ids[idx]).unwrap().pos = pos2(x, y);
+                center_y[idx] = Some(y + sizes[idx].y * 0.5);
+                y += sizes[idx].y + GAP;
+            }
+            x += col_w + GAP;
+        }
+        self.modified = true;
     }
 
     pub fn remove_device(&mut self, id: u64) {
@@ -347,6 +1284,7 @@ impl Board {
             }
         }
         self.devices.remove(&id).unwrap();
+        self.modified = true;
     }
 
     pub fn set_device_input(&mut self, id: u64, input: usize, state: bool) {
@@ -383,6 +1321,7 @@ impl Board {
         }
     }
     pub fn add_io(&mut self, sel: IoSel, id: u64, io: Io) {
+        self.modified = true;
         match sel {
             IoSel::Input => {
                 self.inputs.insert(id, Input::new(io));
@@ -393,6 +1332,7 @@ impl Board {
         }
     }
     pub fn remove_io_alone(&mut self, sel: IoSel, id: u64) {
+        self.modified = true;
         match sel {
             IoSel::Input => {
                 self.inputs.remove(&id).unwrap();
@@ -416,6 +1356,7 @@ impl Board {
         }
     }
     pub fn insert_io_group(&mut self, sel: IoSel, id: u64, group: Group) {
+        self.modified = true;
         match sel {
             IoSel::Input => self.input_groups.insert(id, group),
             IoSel::Output => self.output_groups.insert(id, group),
@@ -433,6 +1374,7 @@ impl Board {
     }
 
     pub fn drag_io(&mut self, sel: IoSel, id: u64, drag: Vec2) {
+        self.modified = true;
         let io = self.mut_io(sel, id).unwrap();
         if let Some(group_id) = io.group_member {
             let group = self.get_io_group(sel, group_id).unwrap();
@@ -460,19 +1402,13 @@ impl Board {
         let state = io.state;
         let name = io.name.clone();
         let y_pos = io.y_pos;
-
-        fn new_name(name: &str, i: usize) -> String {
-            if name.trim().is_empty() {
-                return String::new();
-            }
-            format!("{}{}", name, i)
-        }
+        let naming = settings.stack_io_naming;
 
         let sp = settings.board_io_col_w;
         if let Some(group_id) = io.group_member {
             let group = self.get_io_group(sel, group_id).unwrap();
             let first_member = self.get_io(sel, group.members[0]).unwrap();
-            let new_name = new_name(&first_member.name, group.members.len());
+            let new_name = naming.member_name(&first_member.name, group.members.len());
             let bottom_y = self
                 .get_io(sel, *group.members.last().unwrap())
                 .unwrap()
@@ -493,12 +1429,15 @@ impl Board {
             let group_id = rand_id();
             let new_id = rand_id();
             self.insert_io_group(sel, group_id, Group::new(vec![id, new_id]));
-            self.mut_io(sel, id).unwrap().group_member = Some(group_id);
+            let first_name = naming.first_name(&name);
+            let first = self.mut_io(sel, id).unwrap();
+            first.group_member = Some(group_id);
+            first.name = first_name.clone();
 
             let io = Io {
                 y_pos: y_pos + sp,
                 group_member: Some(group_id),
-                name: new_name(&name, 1),
+                name: naming.member_name(&first_name, 1),
                 state,
             };
             self.add_io(sel, new_id, io);
@@ -519,16 +1458,134 @@ impl Board {
         }
         self.remove_io_alone(sel, member);
     }
+    /// Takes `id` out of its group, keeping it as a standalone input/output.
+    /// The group is removed if only one member is left.
+    pub fn detach_io_member(&mut self, sel: IoSel, id: u64) {
+        let Some(group_id) = self.get_io(sel, id).unwrap().group_member else { return };
+        let group = self.mut_io_group(sel, group_id).unwrap();
+        group.members.retain(|member| *member != id);
+        let members = group.members.clone();
+
+        self.mut_io(sel, id).unwrap().group_member = None;
+        if members.len() == 1 {
+            self.remove_io_group(sel, group_id);
+            self.mut_io(sel, members[0]).unwrap().group_member = None;
+        }
+        self.modified = true;
+    }
+
+    /// Moves the input or output column, but keeps the board wide enough for both columns
+    pub fn drag_io_col(&mut self, sel: IoSel, drag: f32, settings: &Settings) {
+        const MIN_GAP: f32 = 20.0;
+
+        let min_w = settings.board_io_col_w * 2.0 + MIN_GAP;
+        match sel {
+            IoSel::Input => self.rect.min.x = (self.rect.min.x + drag).min(self.rect.max.x - min_w),
+            IoSel::Output => {
+                self.rect.max.x = (self.rect.max.x + drag).max(self.rect.min.x + min_w)
+            }
+        }
+        self.modified = true;
+    }
+
+    /// Replaces an input with a High or Low device (by the state of the input), linked to the same pins.
+    /// Returns the ID of the device.
+    pub fn input_to_constant(
+        &mut self,
+        id: u64,
+        library: &Library,
+        settings: &Settings,
+    ) -> Option<u64> {
+        const GAP: f32 = 20.0;
+
+        let input = self.inputs.get(&id)?;
+        let name = match input.state() {
+            true => HIGH_PRESET,
+            false => LOW_PRESET,
+        };
+        let preset = library.get_preset(name)?;
+        let y = input.io.y_pos;
+        let links = input.links.clone();
+
+        self.detach_input(id);
+        self.remove_io_alone(IoSel::Input, id);
+        let x = self.rect.min.x + settings.board_io_col_w + settings.board_io_pin_size + GAP;
+        let pos = pos2(x, y - preset_size(preset, settings).y * 0.5);
+        let device_id = rand_id();
+        self.add_device(device_id, Device::from_preset(preset, pos));
+        for link in links {
+            self.add_link(LinkStart::DeviceOutput(device_id, 0), link);
+        }
+        Some(device_id)
+    }
+    /// Replaces a High or Low device with an input in that state, linked to the same pins.
+    /// Returns the ID of the input.
+    pub fn constant_to_input(&mut self, id: u64, settings: &Settings) -> Option<u64> {
+        let device = self.devices.get(&id)?;
+        let state = device.constant_state()?;
+        let y = device.pos.y + device_size(device, settings).y * 0.5;
+        let links = device.links[0].clone();
+
+        self.remove_device(id);
+        let mut io = Io::new(y);
+        io.state = state;
+        let input_id = rand_id();
+        self.add_io(IoSel::Input, input_id, io);
+        for link in links {
+            self.add_link(LinkStart::Input(input_id), link);
+        }
+        Some(input_id)
+    }
 
     pub fn add_input(&mut self, y: f32) {
         self.inputs.insert(rand_id(), Input::new(Io::new(y)));
+        self.modified = true;
     }
 
     pub fn set_input(&mut self, input: u64, state: bool) {
         let Some(input) = self.inputs.get_mut(&input) else { return };
         input.io.state = state;
+        let state = input.state();
         for link in &input.links {
-            self.write_queue.push(link.target, state);
+            self.write_queue
+                .push_delayed(link.target, state, link.delay);
+        }
+    }
+    /// Counts the clock inputs up, and toggles the ones that reached their period
+    pub fn update_clocks(&mut self) {
+        let mut toggled = Vec::new();
+        for (id, input) in &mut self.inputs {
+            let Some(clock) = &mut input.clock else { continue };
+            clock.counter += 1;
+            if clock.counter >= clock.period {
+                clock.counter = 0;
+                toggled.push(*id);
+            }
+        }
+        for id in toggled {
+            let state = self.inputs[&id].io.state;
+            self.set_input(id, !state);
+        }
+    }
+    /// Makes the input a clock that toggles every `period` updates, 0 makes it a normal input again
+    pub fn set_clock(&mut self, input: u64, period: u32) {
+        let Some(input) = self.inputs.get_mut(&input) else { return };
+        input.clock = match (period, input.clock) {
+            (0, _) => None,
+            (period, Some(clock)) => Some(Clock { period, ..clock }),
+            (period, None) => Some(Clock::new(period)),
+        };
+        self.modified = true;
+    }
+    /// Overrides the propagated state of an input without changing its base state,
+    /// `None` restores the base state
+    pub fn force_input(&mut self, input: u64, force: Option<bool>) {
+        let Some(input) = self.inputs.get_mut(&input) else { return };
+        input.force = force;
+        let state = input.state();
+        for link in &input.links {
+            self.write_queue
+                .push_delayed(link.target, state, link.delay);
         }
     }
     pub fn drag_input(&mut self, id: u64, drag: Vec2) {
@@ -543,9 +1600,13 @@ impl Board {
     pub fn unstack_input(&mut self, id: u64) {
         self.unstack_io(IoSel::Input, id)
     }
+    pub fn detach_input(&mut self, id: u64) {
+        self.detach_io_member(IoSel::Input, id)
+    }
 
     pub fn add_output(&mut self, y: f32) {
         self.outputs.insert(rand_id(), Output::new(Io::new(y)));
+        self.modified = true;
     }
     pub fn drag_output(&mut self, id: u64, drag: Vec2) {
         self.drag_io(IoSel::Output, id, drag)
@@ -559,22 +1620,51 @@ impl Board {
     pub fn unstack_output(&mut self, id: u64) {
         self.unstack_io(IoSel::Output, id)
     }
+    pub fn detach_output(&mut self, id: u64) {
+        self.detach_io_member(IoSel::Output, id)
+    }
+
+    /// The base states of the inputs as a number, the first of `inputs_sorted` is the lowest bit
+    pub fn input_combination(&self) -> u64 {
+        let mut value = 0;
+        for (idx, id) in self.inputs_sorted().into_iter().take(64).enumerate() {
+            value |= (self.inputs[&id].io.state as u64) << idx;
+        }
+        value
+    }
+    /// Sets the inputs to the bits of `value`, like `CombGatePreset::from_board` does for every combination
+    pub fn set_input_combination(&mut self, value: u64) {
+        for (idx, id) in self.inputs_sorted().into_iter().take(64).enumerate() {
+            let state = (value >> idx) & 1 == 1;
+            if self.inputs[&id].io.state != state {
+                self.set_input(id, state);
+            }
+        }
+    }
+    /// Counts the inputs up (or down) by `step` as one number, wrapping around
+    pub fn step_input_combination(&mut self, step: i64) {
+        let mask = match self.inputs.len() {
+            len if len >= 64 => u64::MAX,
+            len => (1 << len) - 1,
+        };
+        let value = self.input_combination().wrapping_add(step as u64) & mask;
+        self.set_input_combination(value);
+        self.modified = true;
+    }
 
+    /// The bits are in the same order as `inputs_sorted`
     pub fn input_field(&self) -> BitField {
         let mut field = BitField::empty(self.inputs.len());
-        let mut idx = 0;
-        for (_, input) in &self.inputs {
-            field.set(idx, input.io.state);
-            idx += 1;
+        for (idx, id) in self.inputs_sorted().into_iter().enumerate() {
+            field.set(idx, self.inputs.get(&id).unwrap().state());
         }
         field
     }
+    /// The bits are in the same order as `outputs_sorted`
     pub fn output_field(&self) -> BitField {
         let mut field = BitField::empty(self.outputs.len());
-        let mut idx = 0;
-        for (_, input) in &self.outputs {
-            field.set(idx, input.io.state);
-            idx += 1;
+        for (idx, id) in self.outputs_sorted().into_iter().enumerate() {
+            field.set(idx, self.outputs.get(&id).unwrap().io.state);
         }
         field
     }
@@ -585,12 +1675,19 @@ impl Board {
         }
     }
 
+    /// Returns `None` if no output has an expected state
+    pub fn assertions_pass(&self) -> Option<bool> {
+        let mut checked = self.outputs.values().filter(|o| o.expected.is_some());
+        checked.next()?;
+        Some(!self.outputs.values().any(Output::failed))
+    }
+
     pub fn inputs_sorted(&self) -> Vec<u64> {
         let mut keys: Vec<_> = self.inputs.keys().cloned().collect();
         keys.sort_by(|a, b| {
             let a_y = self.inputs.get(a).unwrap().io.y_pos;
             let b_y = self.inputs.get(b).unwrap().io.y_pos;
-            a_y.partial_cmp(&b_y).unwrap()
+            a_y.total_cmp(&b_y)
         });
         keys
     }
@@ -599,21 +1696,157 @@ impl Board {
         keys.sort_by(|a, b| {
             let a_y = self.outputs.get(a).unwrap().io.y_pos;
             let b_y = self.outputs.get(b).unwrap().io.y_pos;
-            a_y.partial_cmp(&b_y).unwrap()
+            a_y.total_cmp(&b_y)
         });
         keys
     }
+    /// Every pin a link can start from: the inputs, then the device outputs in `devices_sorted` order
+    pub fn link_starts(&self) -> Vec<LinkStart<u64>> {
+        let mut starts: Vec<_> = self
+            .inputs_sorted()
+            .into_iter()
+            .map(LinkStart::Input)
+            .collect();
+        for (id, device) in self.devices_sorted() {
+            starts.extend((0..device.num_outputs()).map(|idx| LinkStart::DeviceOutput(id, idx)));
+        }
+        starts
+    }
+    /// Every pin a link can end at: the device inputs in `devices_sorted` order, then the outputs
+    pub fn link_targets(&self) -> Vec<LinkTarget<u64>> {
+        let mut targets = Vec::new();
+        for (id, device) in self.devices_sorted() {
+            targets.extend((0..device.num_inputs()).map(|idx| LinkTarget::DeviceInput(id, idx)));
+        }
+        targets.extend(self.outputs_sorted().into_iter().map(LinkTarget::Output));
+        targets
+    }
+
+    /// If any input or device output links to the target
+    pub fn is_linked(&self, target: LinkTarget<u64>) -> bool {
+        let input_links = self.inputs.values().flat_map(|input| &input.links);
+        let device_links = self
+            .devices
+            .values()
+            .flat_map(|device| device.links.iter().flatten());
+        input_links
+            .chain(device_links)
+            .any(|link| link.target == target)
+    }
+    /// The start of the net a pin or link is part of. A target's net is the one of the first link to it
+    pub fn net_of(&self, item: BoardItem) -> Option<LinkStart<u64>> {
+        let target = match item {
+            BoardItem::InputBulb(id) | BoardItem::InputPin(id) | BoardItem::InputLink(id, _) => {
+                return Some(LinkStart::Input(id))
+            }
+            BoardItem::DeviceOutput(id, output) | BoardItem::DeviceOutputLink(id, output, _) => {
+                return Some(LinkStart::DeviceOutput(id, output))
+            }
+            BoardItem::LinkAnchor(start, _, _) => return Some(start),
+            BoardItem::DeviceInput(id, input) => LinkTarget::DeviceInput(id, input),
+            BoardItem::OutputBulb(id) | BoardItem::OutputPin(id) => LinkTarget::Output(id),
+            _ => return None,
+        };
+        let input_links = self.inputs.iter().flat_map(|(id, input)| {
+            (input.links.iter()).map(move |link| (LinkStart::Input(*id), link.target))
+        });
+        let device_links = self.devices.iter().flat_map(|(id, device)| {
+            (device.links.iter().enumerate()).flat_map(move |(output, links)| {
+                (links.iter()).map(move |link| (LinkStart::DeviceOutput(*id, output), link.target))
+            })
+        });
+        input_links
+            .chain(device_links)
+            .find(|(_, link_target)| *link_target == target)
+            .map(|(start, _)| start)
+    }
+    /// The devices on the net from `start`: the one it starts from, and the ones it links to
+    pub fn net_devices(&self, start: LinkStart<u64>) -> HashSet<u64> {
+        let mut devices = HashSet::new();
+        let links = match start {
+            LinkStart::Input(id) => self.inputs.get(&id).map(|input| &input.links),
+            LinkStart::DeviceOutput(id, output) => {
+                devices.insert(id);
+                self.devices
+                    .get(&id)
+                    .and_then(|device| device.links.get(output))
+            }
+        };
+        for link in links.into_iter().flatten() {
+            if let LinkTarget::DeviceInput(id, _) = link.target {
+                devices.insert(id);
+            }
+        }
+        devices
+    }
+    /// Removes the links to device inputs or outputs that were removed, returns how many there were
+    pub fn remove_dangling_links(&mut self) -> usize {
+        let num_inputs: HashMap<u64, usize> = (self.devices.iter())
+            .map(|(id, device)| (*id, device.num_inputs()))
+            .collect();
+        let outputs: HashSet<u64> = self.outputs.keys().copied().collect();
+        let exists = |target: &LinkTarget<u64>| match *target {
+            LinkTarget::DeviceInput(device, input) => num_inputs.get(&device) > Some(&input),
+            LinkTarget::Output(output) => outputs.contains(&output),
+        };
+
+        let mut removed = 0;
+        let input_links = self.inputs.values_mut().map(|input| &mut input.links);
+        let device_links = self
+            .devices
+            .values_mut()
+            .flat_map(|device| &mut device.links);
+        for links in input_links.chain(device_links) {
+            let len = links.len();
+            links.retain(|link| exists(&link.target));
+            removed += len - links.len();
+        }
+        self.modified |= removed > 0;
+        removed
+    }
+    /// The device inputs and outputs that no link goes to, in `link_targets` order
+    pub fn unconnected_targets(&self) -> Vec<LinkTarget<u64>> {
+        let input_links = self.inputs.values().flat_map(|input| &input.links);
+        let device_links = self
+            .devices
+            .values()
+            .flat_map(|device| device.links.iter().flatten());
+        let linked: HashSet<LinkTarget<u64>> = input_links
+            .chain(device_links)
+            .map(|link| link.target)
+            .collect();
+        self.link_targets()
+            .into_iter()
+            .filter(|target| !linked.contains(target))
+            .collect()
+    }
+
+    /// The devices from top to bottom, then left to right
+    pub fn devices_sorted(&self) -> impl Iterator<Item = (u64, &Device)> {
+        let mut devices: Vec<_> = self
+            .devices
+            .iter()
+            .map(|(id, device)| (*id, device))
+            .collect();
+        devices.sort_by(|(a_id, a), (b_id, b)| {
+            (a.pos.y.total_cmp(&b.pos.y))
+                .then(a.pos.x.total_cmp(&b.pos.x))
+                .then(a_id.cmp(b_id))
+        });
+        devices.into_iter()
+    }
 }
 impl Board {
     pub fn add_link(&mut self, start: LinkStart<u64>, link: Link) {
         self.remove_link_to(link.target);
+        self.modified = true;
         let target = link.target;
         match start {
             LinkStart::Input(id) => {
                 let input = self.inputs.get_mut(&id).unwrap();
                 input.links.push(link);
 
-                self.write_queue.push(target, input.io.state);
+                self.write_queue.push(target, input.state());
             }
             LinkStart::DeviceOutput(id, idx) => {
                 let device = self.devices.get_mut(&id).unwrap();
@@ -625,6 +1858,62 @@ impl Board {
         }
     }
 
+    /// The targets a bus link to a hovered item connects to: all the inputs of a device,
+    /// or all the members of an output's group
+    pub fn bus_targets(&self, item: BoardItem) -> Vec<LinkTarget<u64>> {
+        match item {
+            BoardItem::Device(id) | BoardItem::DeviceInput(id, _) => {
+                let num_inputs = self.devices.get(&id).map_or(0, Device::num_inputs);
+                (0..num_inputs)
+                    .map(|idx| LinkTarget::DeviceInput(id, idx))
+                    .collect()
+            }
+            BoardItem::OutputBulb(id) | BoardItem::OutputPin(id) => {
+                let group = self
+                    .outputs
+                    .get(&id)
+                    .and_then(|output| output.io.group_member);
+                match group.and_then(|group| self.output_groups.get(&group)) {
+                    Some(group) => group
+                        .members
+                        .iter()
+                        .copied()
+                        .map(LinkTarget::Output)
+                        .collect(),
+                    None => vec![LinkTarget::Output(id)],
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+    /// Links each of `starts` to the target at the same index,
+    /// or a single start to all of `targets`
+    pub fn bus_link(
+        &mut self,
+        starts: &[LinkStart<u64>],
+        targets: &[LinkTarget<u64>],
+        color: usize,
+        rgb: Option<[egui::Color32; 2]>,
+    ) -> Result<(), String> {
+        if targets.is_empty() {
+            return Err(String::from("there's nothing to link to here"));
+        }
+        if starts.len() != 1 && starts.len() != targets.len() {
+            return Err(format!(
+                "can't link {} outputs to {} inputs, the counts have to match",
+                starts.len(),
+                targets.len()
+            ));
+        }
+        for (idx, target) in targets.iter().enumerate() {
+            let start = starts[idx.min(starts.len() - 1)];
+            let mut link = Link::new(*target, color, Vec::new());
+            link.rgb = rgb;
+            self.add_link(start, link);
+        }
+        Ok(())
+    }
+
     #[inline(always)]
     pub fn link_target_state(&self, target: LinkTarget<u64>) -> Option<bool> {
         match target {
@@ -642,7 +1931,7 @@ impl Board {
                 let device = self.devices.get(&device)?;
                 Some(device.data.output().get(output))
             }
-            LinkStart::Input(input) => Some(self.inputs.get(&input)?.io.state),
+            LinkStart::Input(input) => Some(self.inputs.get(&input)?.state()),
         }
     }
 
@@ -651,6 +1940,7 @@ impl Board {
             for link_idx in 0..input.links.len() {
                 if input.links[link_idx].target == target {
                     input.links.remove(link_idx);
+                    self.modified = true;
                     return true;
                 }
             }
@@ -660,6 +1950,7 @@ impl Board {
                 for link_idx in 0..links.len() {
                     if links[link_idx].target == target {
                         links.remove(link_idx);
+                        self.modified = true;
                         return true;
                     }
                 }
@@ -667,23 +1958,313 @@ impl Board {
         }
         false
     }
+
+    /// Removes every link that crosses the path, writing `false` to their targets.
+    /// Returns the number of links removed.
+    pub fn slice_links(&mut self, path: &[Pos2], settings: &Settings) -> usize {
+        let crosses = |start: Pos2, link: &Link| {
+            let Some(end) = link_target_pos(settings, self, link.target) else { return false };
+            let mut points = vec![start];
+            points.extend(&link.anchors);
+            points.push(end);
+
+            points.windows(2).any(|segment| {
+                let segment = (segment[0], segment[1]);
+                path.windows(2)
+                    .any(|cut| segments_intersect(segment, (cut[0], cut[1])))
+            })
+        };
+
+        let mut targets = Vec::new();
+        for (id, input) in &self.inputs {
+            let start = LinkStart::Input(*id);
+            let Some(start) = link_start_pos(settings, self, start) else { continue };
+            for link in &input.links {
+                if crosses(start, link) {
+                    targets.push(link.target);
+                }
+            }
+        }
+        for (id, device) in &self.devices {
+            for (output, links) in device.links.iter().enumerate() {
+                let start = LinkStart::DeviceOutput(*id, output);
+                let Some(start) = link_start_pos(settings, self, start) else { continue };
+                for link in links {
+                    if crosses(start, link) {
+                        targets.push(link.target);
+                    }
+                }
+            }
+        }
+
+        for target in &targets {
+            self.remove_link_to(*target);
+            self.write_queue.push(*target, false);
+        }
+        targets.len()
+    }
+
+    /// Rebuilds every device from its preset in the library, so the board matches after presets were changed.
+    /// Links to pins that no longer exist are removed.
+    /// Returns the IDs of the devices whose pin counts changed.
+    pub fn refresh_devices(&mut self, library: &Library) -> Vec<u64> {
+        let mut changed = Vec::new();
+        let ids: Vec<u64> = self.devices.keys().copied().collect();
+        for id in ids {
+            let name = &self.devices.get(&id).unwrap().preset;
+            let Some(preset) = library.get_preset(name) else { continue };
+            if self.swap_device_preset(id, preset) {
+                changed.push(id);
+            }
+        }
+        changed
+    }
+
+    /// Replaces the data of a device with a new one from `preset`, keeping its position,
+    /// and the links to and from pins that still exist. Links from removed outputs are written `false`.
+    /// Returns true if the pin counts changed.
+    pub fn swap_device_preset(&mut self, id: u64, preset: &DevicePreset) -> bool {
+        self.modified = true;
+        let device = self.devices.get_mut(&id).unwrap();
+        let data = DeviceData::from_preset(&preset.data);
+        let (num_inputs, num_outputs) = (data.input().len, data.output().len);
+        let changed = num_inputs != device.num_inputs() || num_outputs != device.num_outputs();
+
+        if device.links.len() > num_outputs {
+            for links in device.links.drain(num_outputs..) {
+                for link in links {
+                    self.write_queue.push(link.target, false);
+                }
+            }
+        }
+        device.links.resize(num_outputs, Vec::new());
+        device.data = data;
+        device.preset = preset.name.clone();
+
+        // the outputs may have changed, so rewrite them
+        for (output, links) in device.links.iter().enumerate() {
+            let state = device.data.output().get(output);
+            for link in links {
+                self.write_queue.push(link.target, state);
+            }
+        }
+        self.refresh_links_to_device(id, num_inputs);
+        changed
+    }
+
+    /// The library presets for the gates inside a placed chip, which `flatten_chip` places
+    pub fn chip_gate_presets<'a>(
+        &self,
+        id: u64,
+        library: &'a Library,
+    ) -> Result<Vec<&'a DevicePreset>, String> {
+        let Some(device) = self.devices.get(&id) else {
+            return Err(String::from("No device with that ID"));
+        };
+        let DeviceData::Chip(chip) = &device.data else {
+            return Err(String::from("Only chips can be flattened"));
+        };
+        let gate_preset = |(idx, gate): (usize, &ChipDevice)| {
+            let equivalent = library.find_equivalent(&gate.data.table);
+            let preset = equivalent.first().copied();
+            preset.ok_or(format!(
+                "No preset in the library has the logic of gate {idx}"
+            ))
+        };
+        chip.devices.iter().enumerate().map(gate_preset).collect()
+    }
+
+    /// Replaces a placed chip with the gates inside it, laid out in columns from its inputs to its outputs.
+    /// The links to and from the chip are moved to the gates.
+    /// Returns the IDs of the new devices.
+    pub fn flatten_chip(
+        &mut self,
+        id: u64,
+        library: &Library,
+        settings: &Settings,
+    ) -> Result<Vec<u64>, String> {
+        const GAP: f32 = 20.0;
+
+        let presets = self.chip_gate_presets(id, library)?;
+        let device = self.devices.remove(&id).unwrap();
+        let DeviceData::Chip(chip) = device.data else { unreachable!() };
+        let gates = &chip.devices;
+
+        // the gates (and their outputs) that write to each chip output
+        let mut drivers = vec![Vec::new(); chip.output.len];
+        for (idx, gate) in gates.iter().enumerate() {
+            for (output, targets) in gate.links.iter().enumerate() {
+                for target in targets {
+                    if let LinkTarget::Output(chip_output) = *target {
+                        drivers[chip_output].push((idx, output));
+                    }
+                }
+            }
+        }
+
+        // the links to the chip inputs, from the board or from the chip itself
+        let mut incoming = Vec::new();
+        let mut take_incoming = |start: LinkStart<u64>, links: &mut Vec<Link>| {
+            links.retain(|link| match link.target {
+                LinkTarget::DeviceInput(device, input) if device == id => {
+                    incoming.push((start, input, link.clone()));
+                    false
+                }
+                _ => true,
+            });
+        };
+        for (input_id, input) in &mut self.inputs {
+            take_incoming(LinkStart::Input(*input_id), &mut input.links);
+        }
+        for (device_id, device) in &mut self.devices {
+            for (output, links) in device.links.iter_mut().enumerate() {
+                take_incoming(LinkStart::DeviceOutput(*device_id, output), links);
+            }
+        }
+        let mut outgoing = device.links;
+        for (output, links) in outgoing.iter_mut().enumerate() {
+            take_incoming(LinkStart::DeviceOutput(id, output), links);
+        }
+
+        // --- Place the gates, in columns by how many gates come before them ---
+        let mut depth = vec![0; gates.len()];
+        for _ in 0..gates.len() {
+            let mut changed = false;
+            for (idx, gate) in gates.iter().enumerate() {
+                for target in gate.links.iter().flatten() {
+                    let LinkTarget::DeviceInput(next, _) = *target else { continue };
+                    if depth[next] < depth[idx] + 1 {
+                        depth[next] = depth[idx] + 1;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        let sizes: Vec<Vec2> = (presets.iter())
+            .map(|preset| preset_size(preset, settings))
+            .collect();
+        let col_w = sizes.iter().map(|size| size.x).fold(0.0, f32::max) + GAP;
+        let mut col_h = vec![0.0; depth.iter().max().map_or(0, |max| max + 1)];
+
+        let ids: Vec<u64> = gates.iter().map(|_| rand_id()).collect();
+        for (idx, gate) in gates.iter().enumerate() {
+            let pos = device.pos + vec2(depth[idx] as f32 * col_w, col_h[depth[idx]]);
+            col_h[depth[idx]] += sizes[idx].y + GAP;
+            let device = Device {
+                pos,
+                data: DeviceData::CombGate(gate.data.clone()),
+                links: vec![Vec::new(); gate.data.output.len],
+                preset: presets[idx].name.clone(),
+            };
+            self.devices.insert(ids[idx], device);
+        }
+
+        // --- Link the gates ---
+        for (idx, gate) in gates.iter().enumerate() {
+            for (output, targets) in gate.links.iter().enumerate() {
+                let start = LinkStart::DeviceOutput(ids[idx], output);
+                for target in targets {
+                    match *target {
+                        LinkTarget::DeviceInput(gate, input) => {
+                            let target = LinkTarget::DeviceInput(ids[gate], input);
+                            self.add_link(start, Link::new(target, 0, Vec::new()));
+                        }
+                        LinkTarget::Output(chip_output) => {
+                            for link in &outgoing[chip_output] {
+                                let link = Link {
+                                    anchors: Vec::new(),
+                                    ..link.clone()
+                                };
+                                self.add_link(start, link);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        // outputs that no gate wrote to are off now
+        for (chip_output, links) in outgoing.iter().enumerate() {
+            if drivers[chip_output].is_empty() {
+                for link in links {
+                    self.write_queue.push(link.target, false);
+                }
+            }
+        }
+        for (start, chip_input, link) in incoming {
+            // links from the chip's own outputs now come from the gates that wrote to them
+            let starts = match start {
+                LinkStart::DeviceOutput(device, output) if device == id => (drivers[output].iter())
+                    .map(|(gate, output)| LinkStart::DeviceOutput(ids[*gate], *output))
+                    .collect(),
+                start => vec![start],
+            };
+            for start in starts {
+                for DeviceInput(gate, input) in &chip.input_links[chip_input] {
+                    let link = Link {
+                        target: LinkTarget::DeviceInput(ids[*gate], *input),
+                        anchors: Vec::new(),
+                        ..link.clone()
+                    };
+                    self.add_link(start, link);
+                }
+            }
+        }
+        self.modified = true;
+        Ok(ids)
+    }
+
+    /// Removes the links to inputs of the device past `num_inputs`,
+    /// and rewrites the state of the remaining links to the device.
+    fn refresh_links_to_device(&mut self, id: u64, num_inputs: usize) {
+        let is_removed = |target: &LinkTarget<u64>| matches!(*target, LinkTarget::DeviceInput(device, input) if device == id && input >= num_inputs);
+        let targets_device = |target: &LinkTarget<u64>| matches!(*target, LinkTarget::DeviceInput(device, _) if device == id);
+
+        for (_, input) in &mut self.inputs {
+            input.links.retain(|link| !is_removed(&link.target));
+            for link in &input.links {
+                if targets_device(&link.target) {
+                    self.write_queue.push(link.target, input.state());
+                }
+            }
+        }
+        for (_, device) in &mut self.devices {
+            for (output, links) in device.links.iter_mut().enumerate() {
+                links.retain(|link| !is_removed(&link.target));
+                let state = device.data.output().get(output);
+                for link in links.iter() {
+                    if targets_device(&link.target) {
+                        self.write_queue.push(link.target, state);
+                    }
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ChipDevice {
     pub links: Vec<Vec<LinkTarget<usize>>>,
     pub data: CombGate,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Chip {
     pub write_queue: WriteQueue<usize>,
     pub input: BitField,
     pub output: BitField,
     pub input_links: Vec<Vec<DeviceInput<usize>>>,
     pub devices: Vec<ChipDevice>,
+    /// How many updates in a row the chip has still had pending writes after
+    #[serde(skip)]
+    pub busy_updates: usize,
 }
 impl Chip {
+    /// After this many busy updates in a row, the chip is considered to be oscillating
+    pub const LOOP_CAP: usize = 1000;
+
     pub fn from_preset(preset: &ChipPreset) -> Self {
         let input = BitField::empty(preset.inputs.len());
         let output = BitField::empty(preset.outputs.len());
@@ -724,17 +2305,90 @@ impl Chip {
             output,
             input_links,
             devices,
+            busy_updates: 0,
         }
     }
 
+    /// Clears the pending writes and sets every gate back to its state for all inputs off
+    pub fn reset(&mut self) {
+        self.write_queue = WriteQueue::empty();
+        self.busy_updates = 0;
+        self.input.clear();
+        self.output.clear();
+
+        for device in &mut self.devices {
+            device.data.reset();
+            for i in 0..device.data.output.len {
+                if !device.data.output.get(i) {
+                    continue;
+                }
+                for target in &device.links[i] {
+                    self.write_queue.push(*target, true);
+                }
+            }
+        }
+    }
+
+    /// Simulates a copy of the chip for every input combination
+    pub fn truth_table(&self) -> Result<TruthTable, &'static str> {
+        const MAX_INPUTS: usize = 16;
+        const MAX_UPDATES: usize = 1000;
+
+        let (num_inputs, num_outputs) = (self.input.len, self.output.len);
+        if num_inputs > MAX_INPUTS {
+            return Err("Too many inputs (max is 16)");
+        }
+        let mut chip = self.clone();
+        let mut map = Vec::with_capacity((1 << num_inputs) * BitField::num_words(num_outputs));
+        for input_state in 0..(1u64 << num_inputs) {
+            for i in 0..num_inputs {
+                chip.set_input(i, ((input_state >> i) & 1) == 1);
+            }
+            let mut updates = 0;
+            while chip.write_queue.len() > 0 || !chip.write_queue.buffer.is_empty() {
+                if updates > MAX_UPDATES {
+                    return Err("Has a loop or is too big");
+                }
+                chip.update();
+                updates += 1;
+            }
+            map.extend(chip.output.words());
+        }
+        Ok(TruthTable {
+            num_inputs,
+            num_outputs,
+            map: map.into(),
+        })
+    }
+
     pub fn update(&mut self) -> ChangedOutputs {
-        let prev_output = self.output;
+        let prev_output = self.output.clone();
         while let Some(write) = self.write_queue.next() {
             self.set_link_target(write.target, write.state);
         }
         self.write_queue.update();
         self.write_queue.flush();
-        ChangedOutputs::new(prev_output, self.output)
+        self.busy_updates = match self.write_queue.len() {
+            0 => 0,
+            _ => self.busy_updates + 1,
+        };
+        ChangedOutputs::new(prev_output, self.output.clone())
+    }
+
+    pub fn is_oscillating(&self) -> bool {
+        self.busy_updates > Self::LOOP_CAP
+    }
+
+    /// Recomputes every gate, queueing writes for the outputs that were out of date
+    pub fn recompute(&mut self) {
+        for device in &mut self.devices {
+            let mut changed_outputs = device.data.recompute();
+            while let Some((output, state)) = changed_outputs.next() {
+                for target in &device.links[output] {
+                    self.write_queue.push(*target, state);
+                }
+            }
+        }
     }
 
     pub fn set_input(&mut self, input: usize, state: bool) {
@@ -772,7 +2426,7 @@ impl Chip {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct CombGate {
     pub input: BitField,
     pub output: BitField,
@@ -781,25 +2435,33 @@ pub struct CombGate {
 impl CombGate {
     pub fn new(table: TruthTable) -> Self {
         Self {
-            input: BitField {
-                len: table.num_inputs,
-                data: 0,
-            },
+            input: BitField::empty(table.num_inputs),
             output: table.get(0),
             table,
         }
     }
 
+    pub fn reset(&mut self) {
+        self.input.data = 0;
+        self.output = self.table.get(0);
+    }
+
+    /// Evaluates the table again, in case `output` got out of date with `input`
+    pub fn recompute(&mut self) -> ChangedOutputs {
+        let prev_output =
+            std::mem::replace(&mut self.output, self.table.get(self.input.data as usize));
+        ChangedOutputs::new(prev_output, self.output.clone())
+    }
+
     pub fn set_input(&mut self, input: usize, state: bool) -> ChangedOutputs {
         self.input.set(input, state);
         let result = self.table.get(self.input.data as usize);
-        let prev_output = self.output;
-        self.output = result;
+        let prev_output = std::mem::replace(&mut self.output, result.clone());
         ChangedOutputs::new(prev_output, result)
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Group {
     pub lsb_top: bool,
     pub signed: bool,
@@ -819,42 +2481,139 @@ impl Group {
     pub fn field(&self, board: &Board, sel: IoSel) -> BitField {
         let mut field = BitField::empty(self.members.len());
         for (idx, id) in self.members.iter().enumerate() {
-            field.set(idx, board.get_io(sel, *id).unwrap().state);
+            let state = match sel {
+                IoSel::Input => board.inputs.get(id).unwrap().state(),
+                IoSel::Output => board.outputs.get(id).unwrap().io.state,
+            };
+            field.set(idx, state);
         }
         field
     }
 
     pub fn display_value(&self, field: BitField) -> String {
-        let mut value: i64 = 0;
-        let mut bit_value: i64 = 1;
-        let mut last_idx = 0;
-
-        if self.lsb_top {
-            for idx in 0..self.members.len() - 1 {
-                if field.get(idx) {
-                    value += bit_value;
-                }
-                bit_value *= 2;
+        let len = self.members.len();
+        // the bits from the least significant one, the last one counts negative when signed
+        let bit = |idx: usize| match self.lsb_top {
+            true => field.get(idx),
+            false => field.get(len - 1 - idx),
+        };
+        let negative = self.signed && len > 0 && bit(len - 1);
+
+        // the value can be any number of bits long, so it's kept as words (least significant first).
+        // A negative value is shown as minus its magnitude, which is the inverted bits plus one
+        let mut words = vec![0u64; BitField::num_words(len)];
+        for idx in 0..len {
+            if bit(idx) != negative {
+                words[idx / 64] |= 1 << (idx % 64);
             }
-            last_idx = self.members.len() - 1;
-        } else {
-            for idx in (1..self.members.len()).rev() {
-                if field.get(idx) {
-                    value += bit_value;
+        }
+        if negative {
+            for word in &mut words {
+                let (sum, carry) = word.overflowing_add(1);
+                *word = sum;
+                if !carry {
+                    break;
                 }
-                bit_value *= 2;
             }
         }
-        if field.get(last_idx) {
-            if self.signed {
-                bit_value *= -1;
-            }
-            value += bit_value;
+        let digits = match self.hex {
+            true => hex_digits(&words),
+            false => decimal_digits(words),
+        };
+        match negative {
+            true => format!("-{digits}"),
+            false => digits,
         }
-        if self.hex {
-            format!("{:X}", value)
-        } else {
-            format!("{}", value)
+    }
+}
+
+/// The words as a hexadecimal number, the first word is the least significant
+fn hex_digits(words: &[u64]) -> String {
+    let mut words = words.iter().rev().skip_while(|word| **word == 0);
+    let Some(first) = words.next() else { return String::from("0") };
+    let mut digits = format!("{first:X}");
+    for word in words {
+        digits.push_str(&format!("{word:016X}"));
+    }
+    digits
+}
+/// The words as a decimal number, the first word is the least significant
+fn decimal_digits(mut words: Vec<u64>) -> String {
+    // the largest power of 10 that fits in a word, the number is split into chunks of 19 digits
+    const CHUNK: u128 = 10_000_000_000_000_000_000;
+
+    let mut chunks: Vec<u64> = Vec::new();
+    while words.iter().any(|word| *word != 0) {
+        let mut rem: u128 = 0;
+        for word in words.iter_mut().rev() {
+            let value = (rem << 64) | *word as u128;
+            *word = (value / CHUNK) as u64;
+            rem = value % CHUNK;
+        }
+        chunks.push(rem as u64);
+    }
+    let Some((first, rest)) = chunks.split_last() else { return String::from("0") };
+    let mut digits = format!("{first}");
+    for chunk in rest.iter().rev() {
+        digits.push_str(&format!("{chunk:019}"));
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_fields_follow_sorted_order() {
+        let mut board = Board::new();
+        // (y_pos, state), not in order from top to bottom
+        let pins = [(40.0, true), (10.0, false), (30.0, true), (20.0, false)];
+        for (id, (y_pos, state)) in pins.into_iter().enumerate() {
+            let mut io = Io::new(y_pos);
+            io.state = state;
+            board.inputs.insert(id as u64, Input::new(io.clone()));
+            board.outputs.insert(id as u64, Output::new(io));
+        }
+
+        let inputs = board.input_field();
+        for (idx, id) in board.inputs_sorted().into_iter().enumerate() {
+            assert_eq!(inputs.get(idx), board.inputs[&id].io.state);
+        }
+        let outputs = board.output_field();
+        for (idx, id) in board.outputs_sorted().into_iter().enumerate() {
+            assert_eq!(outputs.get(idx), board.outputs[&id].io.state);
         }
+        // bit 0 is the top pin
+        assert_eq!(inputs.data, 0b1100);
+        assert_eq!(outputs.data, 0b1100);
+    }
+
+    /// The number of updates it takes an input to reach an output through a link with `delay`, after a reset
+    fn updates_after_reset(delay: u8) -> usize {
+        let mut board = Board::new();
+        board.add_input(50.0);
+        board.add_output(50.0);
+        let input = board.inputs_sorted()[0];
+        let output = board.outputs_sorted()[0];
+        let mut link = Link::new(LinkTarget::Output(output), 0, Vec::new());
+        link.delay = delay;
+        board.add_link(LinkStart::Input(input), link);
+        board.inputs.get_mut(&input).unwrap().io.state = true;
+
+        board.reset_sim();
+        // the app sets the delays every frame, after the reset gives the board a new queue
+        board.set_write_delays([0, 0]);
+        (1..100)
+            .find(|_| {
+                board.update();
+                board.outputs[&output].io.state
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn reset_keeps_link_delays() {
+        assert_eq!(updates_after_reset(3), updates_after_reset(0) + 3);
     }
 }
@@ -1,7 +1,7 @@
 use crate::app::CreateLinks;
 use crate::board::{Board, BoardItem, IoSel};
 use crate::presets::DevicePreset;
-use crate::settings::Settings;
+use crate::settings::{Palette, PinShape, Settings};
 use crate::*;
 use egui::*;
 
@@ -20,6 +20,100 @@ pub const LINK_COLORS: &[[Color32; 2]] = &[
 ];
 pub const NUM_LINK_COLORS: usize = LINK_COLORS.len();
 
+/// The Okabe-Ito colors, with the off colors at 40% brightness
+#[rustfmt::skip]
+pub const COLORBLIND_LINK_PALETTE: &[[Color32; 2]; NUM_LINK_COLORS] = &[
+    [Color32::from_rgb(92, 64, 0), Color32::from_rgb(230, 159, 0)],
+    [Color32::from_rgb(34, 72, 93), Color32::from_rgb(86, 180, 233)],
+    [Color32::from_rgb(0, 63, 46), Color32::from_rgb(0, 158, 115)],
+    [Color32::from_rgb(96, 91, 26), Color32::from_rgb(240, 228, 66)],
+    [Color32::from_rgb(0, 46, 71), Color32::from_rgb(0, 114, 178)],
+    [Color32::from_rgb(85, 38, 0), Color32::from_rgb(213, 94, 0)],
+    [Color32::from_rgb(82, 48, 67), Color32::from_rgb(204, 121, 167)],
+];
+
+#[inline(always)]
+pub fn link_palette(palette: Palette) -> &'static [[Color32; 2]] {
+    match palette {
+        Palette::Default => LINK_COLORS,
+        Palette::Colorblind => COLORBLIND_LINK_PALETTE,
+    }
+}
+
+/// The off and on colors of a link, `rgb` overrides the palette `color`
+#[inline(always)]
+pub fn link_colors(color: usize, rgb: Option<[Color32; 2]>, palette: Palette) -> [Color32; 2] {
+    rgb.unwrap_or(link_palette(palette)[color])
+}
+
+/// The colors a board is drawn with, so it can be exported in a different style than it's edited in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoardStyle {
+    pub board: Color32,
+    pub io_col: Color32,
+    /// The off and on colors of pins and IO bulbs
+    pub pins: [Color32; 2],
+    /// Replaces the colors of every link if set
+    pub links: Option<[Color32; 2]>,
+    /// Replaces the preset color of every device if set
+    pub device_fill: Option<Color32>,
+    /// The outline of devices and pins
+    pub outline: Color32,
+    pub text: Color32,
+    pub palette: Palette,
+}
+impl BoardStyle {
+    /// Black on white with grayscale states, for printing regardless of the theme
+    pub const PRINT: Self = Self {
+        board: Color32::WHITE,
+        io_col: Color32::from_gray(220),
+        pins: [Color32::from_gray(200), Color32::BLACK],
+        links: Some([Color32::from_gray(170), Color32::BLACK]),
+        device_fill: Some(Color32::WHITE),
+        outline: Color32::BLACK,
+        text: Color32::BLACK,
+        palette: Palette::Default,
+    };
+
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            board: settings.board_color,
+            io_col: settings.board_io_col_color,
+            pins: [settings.pin_color(false), settings.pin_color(true)],
+            links: None,
+            device_fill: None,
+            outline: Color32::from_rgb(200, 200, 200),
+            text: Color32::WHITE,
+            palette: settings.palette,
+        }
+    }
+
+    #[inline(always)]
+    pub fn pin_color(&self, state: bool) -> Color32 {
+        self.pins[state as usize]
+    }
+    #[inline(always)]
+    pub fn link_color(&self, link: &crate::Link, state: bool) -> Color32 {
+        let colors =
+            (self.links).unwrap_or_else(|| link_colors(link.color, link.rgb, self.palette));
+        colors[state as usize]
+    }
+}
+
+/// Black or white, whichever is readable on `bg`
+pub fn contrast_text(bg: Color32) -> Color32 {
+    match Rgba::from(bg).intensity() {
+        v if v > 0.5 => Color32::BLACK,
+        _ => Color32::WHITE,
+    }
+}
+
+/// The color of the ring around inputs whose state is forced
+pub const FORCED_COLOR: Color32 = Color32::from_rgb(80, 160, 255);
+
+/// The color of pins (and chips) with pending writes in the write queue overlay
+pub const PENDING_COLOR: Color32 = Color32::from_rgb(255, 170, 0);
+
 pub struct Spread {
     pub count: usize,
     pub counter: usize,
@@ -27,6 +121,8 @@ pub struct Spread {
     pub step: f32,
 }
 impl Spread {
+    /// `count` positions evenly between `min` and `max`, leaving a step of space at both ends.
+    /// A `count` of 0 gives no positions, for devices without inputs or outputs
     pub fn new(min: f32, max: f32, count: usize) -> Self {
         let step = (max - min) / (count + 1) as f32;
         let value = min + step;
@@ -107,13 +203,16 @@ impl std::ops::Mul<f32> for Transform {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct View {
     pub origin: Pos2,
     pub offset: Vec2,
     pub zoom: f32,
 }
 impl View {
+    pub const MIN_ZOOM: f32 = 10.0;
+    pub const MAX_ZOOM: f32 = 400.0;
+
     pub fn default() -> Self {
         Self {
             origin: Pos2::ZERO,
@@ -127,11 +226,8 @@ impl View {
         let ys = (pos.y - self.offset.y) / self.scale();
         self.zoom *= delta;
 
-        const MIN_ZOOM: f32 = 10.0;
-        const MAX_ZOOM: f32 = 400.0;
-
-        self.zoom = f32::max(self.zoom, MIN_ZOOM);
-        self.zoom = f32::min(self.zoom, MAX_ZOOM);
+        self.zoom = f32::max(self.zoom, Self::MIN_ZOOM);
+        self.zoom = f32::min(self.zoom, Self::MAX_ZOOM);
 
         self.offset.x = pos.x - xs * self.scale();
         self.offset.y = pos.y - ys * self.scale();
@@ -139,6 +235,33 @@ impl View {
     pub fn drag(&mut self, drag: Vec2) {
         self.offset += drag;
     }
+    /// Zooms and moves so the world space `rect` fills the screen space `screen` (as far as the zoom limits allow)
+    pub fn fit(&mut self, rect: Rect, screen: Rect) {
+        let scale = f32::min(
+            screen.width() / rect.width(),
+            screen.height() / rect.height(),
+        );
+        self.zoom = (scale * 100.0).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+
+        // the inverse of `create_transform` for the centers
+        let scale = self.scale();
+        let origin = self.origin.to_vec2();
+        self.offset =
+            screen.center().to_vec2() - rect.center().to_vec2() * scale - origin * scale - origin;
+    }
+    /// Moves `amount` (0 to 1) of the way to `target`, returns true once it's there
+    pub fn approach(&mut self, target: &View, amount: f32) -> bool {
+        self.origin = target.origin;
+        self.offset += (target.offset - self.offset) * amount;
+        self.zoom += (target.zoom - self.zoom) * amount;
+
+        let arrived =
+            (target.offset - self.offset).length() < 0.5 && (target.zoom - self.zoom).abs() < 0.1;
+        if arrived {
+            *self = target.clone();
+        }
+        arrived
+    }
 
     #[inline(always)]
     pub fn scale(&self) -> f32 {
@@ -167,6 +290,17 @@ impl View {
     }
 }
 
+pub fn segments_intersect(a: (Pos2, Pos2), b: (Pos2, Pos2)) -> bool {
+    // which side of `line` the point is on
+    let side = |line: (Pos2, Pos2), p: Pos2| {
+        let (d, e) = (line.1 - line.0, p - line.0);
+        d.x * e.y - d.y * e.x
+    };
+    let (a0, a1) = (side(a, b.0), side(a, b.1));
+    let (b0, b1) = (side(b, a.0), side(b, a.1));
+    a0 * a1 < 0.0 && b0 * b1 < 0.0
+}
+
 // http://www.sunshine2k.de/coding/java/PointOnLine/PointOnLine.html
 pub fn project_point_onto_line(p: Pos2, line: (Pos2, Pos2)) -> Pos2 {
     let (v1, v2) = line;
@@ -213,6 +347,10 @@ pub struct Graphics<'a> {
     pub ctx: &'a Context,
     pub transform: Transform,
     pub pointer_pos: Pos2,
+    /// In world space, devices and links outside of it are skipped. `None` shows everything
+    pub view_rect: Option<Rect>,
+    /// How many devices and links were skipped by `view_rect`
+    pub culled: usize,
     shapes: Vec<Shape>,
 }
 impl<'a> Graphics<'a> {
@@ -221,6 +359,8 @@ impl<'a> Graphics<'a> {
             ctx,
             transform,
             pointer_pos,
+            view_rect: None,
+            culled: 0,
             shapes: Vec::new(),
         }
     }
@@ -228,6 +368,16 @@ impl<'a> Graphics<'a> {
         self.shapes
     }
 
+    /// Returns false (and counts it as culled) if the world space rect is outside `view_rect`
+    pub fn visible(&mut self, rect: Rect) -> bool {
+        let visible = match self.view_rect {
+            Some(view_rect) => view_rect.intersects(rect),
+            None => true,
+        };
+        self.culled += !visible as usize;
+        visible
+    }
+
     pub fn rect(
         &mut self,
         rect: Rect,
@@ -302,11 +452,7 @@ impl<'a> Graphics<'a> {
         stroke: Option<ShowStroke>,
     ) -> bool {
         let (center, radius) = (self.transform * center, self.transform * radius);
-        let rect = Rect {
-            min: center - Vec2::splat(radius),
-            max: center + Vec2::splat(radius),
-        };
-        let hovered = rect.contains(self.pointer_pos);
+        let hovered = center.distance(self.pointer_pos) <= radius;
 
         let color = if hovered { color[1] } else { color[0] };
         self.shapes
@@ -321,6 +467,42 @@ impl<'a> Graphics<'a> {
         }
         hovered
     }
+
+    /// The points must be in clockwise order
+    pub fn convex_polygon(
+        &mut self,
+        points: &[Pos2],
+        color: [Color32; 2],
+        stroke: Option<ShowStroke>,
+    ) -> bool {
+        let points: Vec<Pos2> = points.iter().map(|p| self.transform * *p).collect();
+        let hovered = convex_polygon_contains_point(&points, self.pointer_pos);
+
+        let color = if hovered { color[1] } else { color[0] };
+        let stroke = match stroke {
+            Some(ShowStroke { color, width }) => {
+                let color = if hovered { color[1] } else { color[0] };
+                let width = if hovered { width[1] } else { width[0] };
+                Stroke { width, color }
+            }
+            None => Stroke::NONE,
+        };
+        self.shapes
+            .push(Shape::convex_polygon(points, color, stroke));
+        hovered
+    }
+}
+
+pub fn convex_polygon_contains_point(points: &[Pos2], point: Pos2) -> bool {
+    for idx in 0..points.len() {
+        let (a, b) = (points[idx], points[(idx + 1) % points.len()]);
+        let edge = b - a;
+        let to_point = point - a;
+        if edge.x * to_point.y - edge.y * to_point.x < 0.0 {
+            return false;
+        }
+    }
+    true
 }
 
 // ---- SCENE GRAPHICS START HERE ----
@@ -364,17 +546,45 @@ pub fn link_start_pos(settings: &Settings, board: &Board, start: LinkStart<u64>)
     }
 }
 
-pub fn calc_device_size(num_inputs: usize, num_outputs: usize, min_pin_spacing: f32) -> Vec2 {
-    let num_io = num_inputs.max(num_outputs);
-    let h = (num_io + 1) as f32 * min_pin_spacing;
-    let w = h.max(70.0);
+/// The height fits the pins, and the width fits the name,
+/// without the device getting more than `MAX_DEVICE_ASPECT` times taller than it's wide.
+/// Devices without pins are as tall as ones with a single pin
+pub fn calc_device_size(
+    num_inputs: usize,
+    num_outputs: usize,
+    name: &str,
+    settings: &Settings,
+) -> Vec2 {
+    const MIN_W: f32 = 70.0;
+    const MAX_DEVICE_ASPECT: f32 = 3.0;
+    const NAME_PADDING: f32 = 16.0;
+
+    let num_io = num_inputs.max(num_outputs).max(1);
+    let h = (num_io + 1) as f32 * settings.device_min_pin_spacing;
+    let name_w = text_width(name, settings.device_name_size) + NAME_PADDING;
+    let w = MIN_W.max(name_w).max(h / MAX_DEVICE_ASPECT);
     vec2(w, h)
 }
+/// An estimate of the width of some text in the proportional font, without needing the fonts,
+/// so device sizes are the same everywhere (including where there's no `Context`)
+pub fn text_width(text: &str, size: f32) -> f32 {
+    const AVG_CHAR_W: f32 = 0.55;
+    text.chars().count() as f32 * size * AVG_CHAR_W
+}
 pub fn device_size(device: &board::Device, settings: &Settings) -> Vec2 {
     calc_device_size(
         device.num_inputs(),
         device.num_outputs(),
-        settings.device_min_pin_spacing,
+        &device.preset,
+        settings,
+    )
+}
+pub fn preset_size(preset: &DevicePreset, settings: &Settings) -> Vec2 {
+    calc_device_size(
+        preset.data.num_inputs(),
+        preset.data.num_outputs(),
+        &preset.name,
+        settings,
     )
 }
 
@@ -385,16 +595,77 @@ pub const BULB_STROKE: Option<ShowStroke> = Some(ShowStroke {
     color: [Color32::from_gray(200); 2],
 });
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkRouting {
+    Straight,
+    /// Horizontal and vertical segments only
+    Orthogonal,
+    Curved,
+}
+impl LinkRouting {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Straight => Self::Orthogonal,
+            Self::Orthogonal => Self::Curved,
+            Self::Curved => Self::Straight,
+        }
+    }
+
+    /// Returns the anchors for a link from `from` to `to`, passing through `anchors`
+    pub fn route(self, from: Pos2, anchors: &[Pos2], to: Pos2) -> Vec<Pos2> {
+        const CURVE_STEPS: usize = 12;
+
+        let mut points = vec![from];
+        points.extend(anchors);
+        points.push(to);
+
+        let mut result = Vec::new();
+        for idx in 1..points.len() {
+            let (a, b) = (points[idx - 1], points[idx]);
+            if idx > 1 {
+                result.push(a);
+            }
+            match self {
+                Self::Straight => {}
+                Self::Orthogonal => {
+                    if a.x != b.x && a.y != b.y {
+                        let mid_x = (a.x + b.x) * 0.5;
+                        result.push(pos2(mid_x, a.y));
+                        result.push(pos2(mid_x, b.y));
+                    }
+                }
+                Self::Curved => {
+                    // a cubic bezier that leaves and enters horizontally
+                    let handle = vec2((b.x - a.x).abs() * 0.5, 0.0);
+                    let (c0, c1) = (a + handle, b - handle);
+                    for step in 1..CURVE_STEPS {
+                        let t = step as f32 / CURVE_STEPS as f32;
+                        let it = 1.0 - t;
+                        let p = a.to_vec2() * (it * it * it)
+                            + c0.to_vec2() * (3.0 * it * it * t)
+                            + c1.to_vec2() * (3.0 * it * t * t)
+                            + b.to_vec2() * (t * t * t);
+                        result.push(p.to_pos2());
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
 pub fn show_link(
     g: &mut Graphics,
     width: f32,
-    state: bool,
-    color: usize,
+    color: Color32,
     from: Pos2,
     to: Pos2,
     anchors: &[Pos2],
-) -> bool {
-    let color = LINK_COLORS[color][state as usize];
+    dashed: bool,
+) -> (bool, Option<usize>) {
+    const DASH_LEN: f32 = 8.0;
+    const GAP_LEN: f32 = 5.0;
+
     let stroke = ShowStroke {
         color: [color; 2],
         width: [width, width + 2.0],
@@ -406,24 +677,60 @@ pub fn show_link(
 
     for idx in 1..points.len() {
         let (from, to) = (points[idx - 1], points[idx]);
-        if g.line(from, to, width, stroke) {
-            hovered = true;
+        if !dashed {
+            hovered |= g.line(from, to, width, stroke);
+            continue;
+        }
+        let (len, dir) = ((to - from).length(), (to - from).normalized());
+        let mut dist = 0.0;
+        while dist < len {
+            let dash_end = (dist + DASH_LEN).min(len);
+            hovered |= g.line(from + dir * dist, from + dir * dash_end, width, stroke);
+            dist += DASH_LEN + GAP_LEN;
         }
     }
-    hovered
+    // the handles only show up when hovered
+    let mut hovered_anchor = None;
+    for (idx, anchor) in anchors.iter().enumerate() {
+        if g.circle(*anchor, width + 2.0, [Color32::TRANSPARENT, color], None) {
+            hovered_anchor = Some(idx);
+        }
+    }
+    (hovered, hovered_anchor)
 }
-pub fn show_pin(g: &mut Graphics, pos: Pos2, size: f32, color: Color32, name: &str) -> bool {
-    let hovered = g.circle(
-        pos,
-        size,
-        [color; 2],
-        Some(ShowStroke {
-            color: [Color32::WHITE; 2],
-            width: [0.0, 1.0],
-        }),
-    );
-    if !name.trim().is_empty() {
-        // TODO show name popup
+pub fn show_pin(
+    g: &mut Graphics,
+    pos: Pos2,
+    size: f32,
+    shape: PinShape,
+    color: Color32,
+    outline: Color32,
+    name: &str,
+) -> bool {
+    let stroke = Some(ShowStroke {
+        color: [outline; 2],
+        width: [0.0, 1.0],
+    });
+    let hovered = match shape {
+        PinShape::Circle => g.circle(pos, size, [color; 2], stroke),
+        PinShape::Square => {
+            let rect = Rect::from_center_size(pos, Vec2::splat(size * 2.0));
+            g.rect(rect, 0.0, [color; 2], stroke)
+        }
+        // points right, in the direction signals flow
+        PinShape::Triangle => {
+            let points = [
+                pos + vec2(-size, -size),
+                pos + vec2(size, 0.0),
+                pos + vec2(-size, size),
+            ];
+            g.convex_polygon(&points, [color; 2], stroke)
+        }
+    };
+    // only the hovered pin shows its name, above it
+    if hovered && !name.trim().is_empty() {
+        let pos = pos - vec2(0.0, size + 2.0);
+        g.text(pos, size * 1.5, name, outline, Align2::CENTER_BOTTOM);
     }
     hovered
 }
@@ -441,15 +748,28 @@ pub struct ShowDevice<'a> {
     show_id: Option<u64>,
     alpha: Option<u8>,
 }
+/// A color picked from a hash of the category name, so every preset in a category shares it
+pub fn category_color(cat: &str) -> Color32 {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in cat.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    let hue = (hash % 360) as f32 / 360.0;
+    ecolor::Hsva::new(hue, 0.7, 0.9, 1.0).into()
+}
+
 pub fn show_device(
     g: &mut Graphics,
     settings: &Settings,
+    style: &BoardStyle,
     pos: Pos2,
     size: Vec2,
     device: ShowDevice,
 ) -> Option<DeviceItem> {
     let color = {
-        let [r, g, b, a]: [u8; 4] = device.preset.color.into();
+        let fill = style.device_fill.map(|color| color.to_array());
+        let [r, g, b, a] = fill.unwrap_or(device.preset.color);
         let a = device.alpha.unwrap_or(a);
         Color32::from_rgba_premultiplied(r, g, b, a)
     };
@@ -458,24 +778,51 @@ pub fn show_device(
     // --- Show rectangle ---
     let hovered = g.rect(
         rect,
-        5.0,
+        settings.device_rounding,
         [color; 2],
         Some(ShowStroke {
-            color: [Color32::from_rgb(200, 200, 200); 2],
+            color: [style.outline; 2],
             width: [1.0, 3.0],
         }),
     );
     let mut hovered = hovered.then(|| DeviceItem::Device);
 
-    // --- Show name ---
-    let name_color = match Rgba::from(color).intensity() {
-        v if v > 0.5 => Color32::BLACK,
-        _ => Color32::WHITE,
+    // --- Show category stripe ---
+    if settings.category_stripes {
+        const STRIPE_H: f32 = 4.0;
+        let stripe = Rect::from_min_size(rect.min, vec2(rect.width(), STRIPE_H));
+        let color = category_color(&device.preset.cat);
+        g.rect(
+            stripe,
+            settings.device_rounding.min(STRIPE_H),
+            [color; 2],
+            None,
+        );
+    }
+
+    // --- Show name (or glyph when the name is too small to read) ---
+    const MIN_NAME_SIZE: f32 = 9.0;
+    let name_color = contrast_text(color);
+    let glyph = match &device.preset.glyph {
+        Some(glyph) if settings.device_glyphs => {
+            let name_size = g.transform * settings.device_name_size;
+            let name_fits = text_width(&device.preset.name, settings.device_name_size) <= size.x;
+            (name_size < MIN_NAME_SIZE || !name_fits).then_some(glyph)
+        }
+        _ => None,
+    };
+    let (text, text_size) = match glyph {
+        // as big as fits, since there's only a character or two
+        Some(glyph) => {
+            let fit_w = size.x / text_width(glyph, 1.0).max(1.0);
+            (glyph, (size.y * 0.6).min(fit_w * 0.8))
+        }
+        None => (&device.preset.name, settings.device_name_size),
     };
     g.text(
         pos + size * 0.5,
-        settings.device_name_size,
-        &device.preset.name,
+        text_size,
+        text,
         name_color,
         Align2::CENTER_CENTER,
     );
@@ -484,18 +831,20 @@ pub fn show_device(
     let input_locs = device_input_locs(settings, rect, device.inputs.len);
     for (index, pos) in input_locs.enumerate() {
         let state = device.inputs.get(index);
-        let color = settings.pin_color(state);
+        let color = style.pin_color(state);
         let name = &device.preset.data.input_names()[index];
-        if show_pin(g, pos, settings.device_pin_size, color, name) {
+        let (size, shape) = (settings.device_pin_size, settings.input_pin_shape);
+        if show_pin(g, pos, size, shape, color, style.outline, name) {
             hovered = Some(DeviceItem::Input(index));
         }
     }
     let output_locs = device_output_locs(settings, rect, device.outputs.len);
     for (index, pos) in output_locs.enumerate() {
         let state = device.outputs.get(index);
-        let color = settings.pin_color(state);
+        let color = style.pin_color(state);
         let name = &device.preset.data.output_names()[index];
-        if show_pin(g, pos, settings.device_pin_size, color, name) {
+        let (size, shape) = (settings.device_pin_size, settings.output_pin_shape);
+        if show_pin(g, pos, size, shape, color, style.outline, name) {
             hovered = Some(DeviceItem::Output(index));
         }
     }
@@ -514,11 +863,7 @@ pub fn show_device(
 }
 
 pub fn show_preset_device(g: &mut Graphics, settings: &Settings, pos: Pos2, preset: &DevicePreset) {
-    let size = calc_device_size(
-        preset.data.num_inputs(),
-        preset.data.num_outputs(),
-        settings.device_min_pin_spacing,
-    );
+    let size = preset_size(preset, settings);
     let show = ShowDevice {
         inputs: BitField::empty(preset.data.num_inputs()),
         outputs: BitField::empty(preset.data.num_outputs()),
@@ -526,41 +871,154 @@ pub fn show_preset_device(g: &mut Graphics, settings: &Settings, pos: Pos2, pres
         show_id: None,
         alpha: Some(255 / 5),
     };
-    show_device(g, settings, pos, size, show);
+    let style = BoardStyle::from_settings(settings);
+    show_device(g, settings, &style, pos, size, show);
+}
+
+/// Draws the preset at `(0, 0)` with its pins, scaled down to fit in `max_size`.
+/// Returns the shapes and their size.
+pub fn preset_thumbnail(
+    ctx: &Context,
+    settings: &Settings,
+    preset: &DevicePreset,
+    max_size: Vec2,
+) -> (Vec<Shape>, Vec2) {
+    // room for the pins, which stick out of the device
+    let margin = settings.device_pin_size * 2.0;
+    let device_size = preset_size(preset, settings);
+    let size = device_size + Vec2::splat(margin * 2.0);
+    let scale = (max_size.x / size.x).min(max_size.y / size.y).min(1.0);
+
+    let transform = Transform {
+        scale,
+        offset: [margin * scale; 2],
+    };
+    // a NaN pointer position, so nothing is drawn hovered
+    let mut g = Graphics::new(ctx, transform, pos2(f32::NAN, f32::NAN));
+    let show = ShowDevice {
+        inputs: BitField::empty(preset.data.num_inputs()),
+        outputs: BitField::empty(preset.data.num_outputs()),
+        preset,
+        show_id: None,
+        alpha: None,
+    };
+    let style = BoardStyle::from_settings(settings);
+    show_device(&mut g, settings, &style, Pos2::ZERO, device_size, show);
+    (g.finish(), size * scale)
+}
+
+/// The preset of a device, or a placeholder if it isn't in the library
+pub fn board_device_preset<'a>(
+    library: &'a Library,
+    device: &board::Device,
+) -> std::borrow::Cow<'a, DevicePreset> {
+    use std::borrow::Cow;
+    match library.get_preset(&device.preset) {
+        Some(preset) => Cow::Borrowed(preset),
+        None => Cow::Owned(DevicePreset::missing(
+            &device.preset,
+            device.num_inputs(),
+            device.num_outputs(),
+        )),
+    }
 }
 
 pub fn show_board_device(
     g: &mut Graphics,
     settings: &Settings,
+    style: &BoardStyle,
     device: &board::Device,
     preset: &DevicePreset,
     show_id: Option<u64>,
+    alpha: Option<u8>,
 ) -> Option<DeviceItem> {
     let show = ShowDevice {
-        inputs: device.data.input(),
-        outputs: device.data.output(),
+        inputs: device.data.input().clone(),
+        outputs: device.data.output().clone(),
         preset,
         show_id,
-        alpha: None,
+        alpha,
     };
     let size = device_size(device, settings);
-    show_device(g, settings, device.pos, size, show)
+    show_device(g, settings, style, device.pos, size, show)
+}
+
+/// Shows only the given devices on a background of `rect`, and the links between them
+pub fn show_devices(
+    g: &mut Graphics,
+    settings: &Settings,
+    style: &BoardStyle,
+    board: &board::Board,
+    library: &Library,
+    ids: &[u64],
+    rect: Rect,
+) {
+    g.rect(rect, 5.0, [style.board; 2], None);
+    for id in ids {
+        let Some(device) = board.devices.get(id) else { continue };
+        let device_rect = Rect::from_min_size(device.pos, device_size(device, settings));
+        let output_locs = device_output_locs(settings, device_rect, device.num_outputs());
+        for (output_idx, output_loc) in output_locs.enumerate() {
+            for link in &device.links[output_idx] {
+                if !matches!(link.target, LinkTarget::DeviceInput(target, _) if ids.contains(&target))
+                {
+                    continue;
+                }
+                let Some(target_pos) = link_target_pos(settings, board, link.target) else { continue };
+                let state = device.data.output().get(output_idx);
+                show_link(
+                    g,
+                    settings.link_width(state),
+                    style.link_color(link, state),
+                    output_loc,
+                    target_pos,
+                    &link.anchors,
+                    link.delay > 0,
+                );
+            }
+        }
+    }
+    for id in ids {
+        let Some(device) = board.devices.get(id) else { continue };
+        let preset = board_device_preset(library, device);
+        show_board_device(g, settings, style, device, &preset, None, None);
+    }
+}
+
+/// The bounds of a link, including its anchors
+fn link_rect(from: Pos2, to: Pos2, anchors: &[Pos2]) -> Rect {
+    let mut rect = Rect::from_two_pos(from, to);
+    for anchor in anchors {
+        rect.extend_with(*anchor);
+    }
+    rect
 }
 
 pub fn show_board(
     g: &mut Graphics,
     settings: &Settings,
+    style: &BoardStyle,
     board: &board::Board,
     library: &Library,
-    show_device_ids: bool,
+    outputs_as_number: bool,
+    focus_net: Option<LinkStart<u64>>,
 ) -> Option<BoardItem> {
+    // everything outside of the focused net is dimmed
+    const DIM_LINK: f32 = 0.15;
+    const DIM_DEVICE_ALPHA: u8 = 255 / 5;
+    let net_devices = focus_net.map(|start| board.net_devices(start));
+    let link_color = |start: LinkStart<u64>, color: Color32| match focus_net {
+        Some(focus) if focus != start => color.linear_multiply(DIM_LINK),
+        _ => color,
+    };
+
     let mut result: Option<BoardItem> = None;
     let rect = board.rect;
     if rect.contains(g.pointer_pos) {
         result = Some(BoardItem::Board);
     }
 
-    g.rect(rect, 5.0, [settings.board_color; 2], None);
+    g.rect(rect, 5.0, [style.board; 2], None);
 
     // --- Show links from devices ---
     for (device_id, device) in &board.devices {
@@ -572,17 +1030,25 @@ pub fn show_board(
             for (link_idx, link) in device.links[output_idx].iter().enumerate() {
                 let state = device.data.output().get(output_idx);
 
-                let target_pos = link_target_pos(settings, board, link.target).unwrap();
-                let hovered = show_link(
+                // dangling until `Board::remove_dangling_links` sweeps it
+                let Some(target_pos) = link_target_pos(settings, board, link.target) else { continue };
+                if !g.visible(link_rect(output_loc, target_pos, &link.anchors)) {
+                    continue;
+                }
+                let color = style.link_color(link, state);
+                let start = LinkStart::DeviceOutput(*device_id, output_idx);
+                let (hovered, anchor) = show_link(
                     g,
-                    settings.link_width,
-                    state,
-                    link.color,
+                    settings.link_width(state),
+                    link_color(start, color),
                     output_loc,
                     target_pos,
                     &link.anchors,
+                    link.delay > 0,
                 );
-                if hovered {
+                if let Some(anchor) = anchor {
+                    result = Some(BoardItem::LinkAnchor(start, link_idx, anchor));
+                } else if hovered {
                     result = Some(BoardItem::DeviceOutputLink(
                         *device_id, output_idx, link_idx,
                     ));
@@ -598,17 +1064,24 @@ pub fn show_board(
             y: input.io.y_pos,
         };
         for (link_idx, link) in input.links.iter().enumerate() {
-            let target_pos = link_target_pos(settings, board, link.target).unwrap();
-            let hovered = show_link(
+            let Some(target_pos) = link_target_pos(settings, board, link.target) else { continue };
+            if !g.visible(link_rect(start_pos, target_pos, &link.anchors)) {
+                continue;
+            }
+            let color = style.link_color(link, input.state());
+            let (hovered, anchor) = show_link(
                 g,
-                settings.link_width,
-                input.io.state,
-                link.color,
+                settings.link_width(input.state()),
+                link_color(LinkStart::Input(*input_id), color),
                 start_pos,
                 target_pos,
                 &link.anchors,
+                link.delay > 0,
             );
-            if hovered {
+            if let Some(anchor) = anchor {
+                let start = LinkStart::Input(*input_id);
+                result = Some(BoardItem::LinkAnchor(start, link_idx, anchor));
+            } else if hovered {
                 result = Some(BoardItem::InputLink(*input_id, link_idx));
             }
         }
@@ -616,9 +1089,19 @@ pub fn show_board(
 
     // --- Show devices ---
     for (device_id, device) in &board.devices {
-        let show_id = show_device_ids.then(|| *device_id);
-        let preset = library.get_preset(&device.preset).unwrap();
-        let device_hovered = show_board_device(g, settings, device, preset, show_id);
+        if !g.visible(Rect::from_min_size(
+            device.pos,
+            device_size(device, settings),
+        )) {
+            continue;
+        }
+        // device IDs are shown in debug mode
+        let show_id = settings.debug.then_some(*device_id);
+        let preset = board_device_preset(library, device);
+        let alpha = (net_devices.as_ref())
+            .filter(|ids| !ids.contains(device_id))
+            .map(|_| DIM_DEVICE_ALPHA);
+        let device_hovered = show_board_device(g, settings, style, device, &preset, show_id, alpha);
 
         if let Some(device_item) = device_hovered {
             let board_item = match device_item {
@@ -636,7 +1119,7 @@ pub fn show_board(
     let col_size = vec2(col_w, rect.height()) - margin * 2.0;
     let input_rect = Rect::from_min_size(rect.min + margin, col_size);
     let output_rect = Rect::from_min_size(rect.max - margin - col_size, col_size);
-    let color = [settings.board_io_col_color; 2];
+    let color = [style.io_col; 2];
 
     if g.rect(input_rect, 5.0, color, None) {
         result = Some(BoardItem::InputCol);
@@ -645,19 +1128,43 @@ pub fn show_board(
         result = Some(BoardItem::OutputCol);
     }
 
+    // the outputs can be read as one number, so their bulbs are hidden (but can still be hovered)
+    let output_number = match board.outputs.len() {
+        1.. if outputs_as_number => {
+            let group = board::Group {
+                lsb_top: true,
+                signed: false,
+                hex: false,
+                members: board.outputs_sorted(),
+            };
+            Some(group.display_value(board.output_field()))
+        }
+        _ => None,
+    };
     let show_io_bulb = move |g: &mut Graphics, state: bool, x: f32, y: f32| -> bool {
-        g.circle(
+        let hovered = g.circle(
             pos2(x, y),
             col_w * 0.5,
-            [settings.pin_color(state); 2],
+            [style.pin_color(state); 2],
             BULB_STROKE,
-        )
+        );
+        if settings.io_bulb_values {
+            let text = if state { "1" } else { "0" };
+            g.text(
+                pos2(x, y),
+                col_w * 0.6,
+                text,
+                contrast_text(style.pin_color(state)),
+                Align2::CENTER_CENTER,
+            );
+        }
+        hovered
     };
     let show_io_decor = move |g: &mut Graphics, x: f32, y: f32| {
         let (x0, x1) = (x - col_w * 0.5, x + col_w * 0.5);
         let (y0, y1) = (y - col_w * 0.5, y + col_w * 0.5);
         let stroke = ShowStroke {
-            color: [settings.board_io_col_color; 2],
+            color: [style.io_col; 2],
             width: [4.0; 2],
         };
         g.line(pos2(x0, y0), pos2(x0, y1), 0.0, stroke);
@@ -667,20 +1174,42 @@ pub fn show_board(
     // --- Show input pins ---
     let pin_size = settings.board_io_pin_size;
     for (input_id, input) in &board.inputs {
+        let (state, force) = (input.state(), input.force);
         let input = &input.io;
         let (x, y) = (rect.min.x + col_w * 0.5, input.y_pos);
 
         let pin_pos = pos2(rect.min.x + col_w + pin_size * 0.5, y);
-        let color = settings.pin_color(input.state);
-        if show_pin(g, pin_pos, pin_size, color, &input.name) {
+        let color = style.pin_color(state);
+        if show_pin(
+            g,
+            pin_pos,
+            pin_size,
+            settings.input_pin_shape,
+            color,
+            style.outline,
+            &input.name,
+        ) {
             result = Some(BoardItem::InputPin(*input_id));
         }
         if input.group_member.is_some() {
             show_io_decor(g, x, y);
         }
-        if show_io_bulb(g, input.state, x, y) {
+        if show_io_bulb(g, state, x, y) {
             result = Some(BoardItem::InputBulb(*input_id));
         }
+        if force.is_some() {
+            // a ring, so it's clear the bulb isn't the input's own state
+            let stroke = ShowStroke {
+                color: [FORCED_COLOR; 2],
+                width: [2.0; 2],
+            };
+            g.circle(
+                pos2(x, y),
+                col_w * 0.5 + 3.0,
+                [Color32::TRANSPARENT; 2],
+                Some(stroke),
+            );
+        }
     }
 
     // --- Show input group headers ---
@@ -692,7 +1221,7 @@ pub fn show_board(
             pos2(center, top_member_y - settings.board_io_col_w * 0.5),
             10.0,
             &text,
-            Color32::WHITE,
+            style.text,
             Align2::CENTER_BOTTOM,
         );
     }
@@ -703,20 +1232,68 @@ pub fn show_board(
         let (x, y) = (rect.max.x - col_w * 0.5, output.y_pos);
 
         let pin_pos = pos2(rect.max.x - col_w - pin_size * 0.5, y);
-        let color = settings.pin_color(output.state);
-        if show_pin(g, pin_pos, pin_size, color, &output.name) {
+        let color = style.pin_color(output.state);
+        if show_pin(
+            g,
+            pin_pos,
+            pin_size,
+            settings.output_pin_shape,
+            color,
+            style.outline,
+            &output.name,
+        ) {
             result = Some(BoardItem::OutputPin(*output_id));
         }
         if output.group_member.is_some() {
             show_io_decor(g, x, y);
         }
-        if show_io_bulb(g, output.state, x, y) {
+        if board.outputs.get(output_id).unwrap().failed() {
+            let stroke = ShowStroke {
+                color: [Color32::RED; 2],
+                width: [3.0; 2],
+            };
+            g.circle(
+                pos2(x, y),
+                col_w * 0.5 + 3.0,
+                [Color32::TRANSPARENT; 2],
+                Some(stroke),
+            );
+        }
+        let hovered = match output_number {
+            Some(_) => g.circle(pos2(x, y), col_w * 0.5, [Color32::TRANSPARENT; 2], None),
+            None => show_io_bulb(g, output.state, x, y),
+        };
+        if hovered {
             result = Some(BoardItem::OutputBulb(*output_id));
         }
     }
+    if let Some(text) = &output_number {
+        let center = output_rect.center();
+        let size = vec2(text_width(text, col_w * 0.5) + 10.0, col_w * 0.8);
+        let bg = Rect::from_center_size(center, size.max(vec2(col_w, 0.0)));
+        g.rect(bg, 5.0, [Color32::from_gray(40); 2], BULB_STROKE);
+        g.text(
+            center,
+            col_w * 0.5,
+            text,
+            Color32::WHITE,
+            Align2::CENTER_CENTER,
+        );
+    }
 
     // --- Show output group headers ---
-    for (_group_id, _group) in &board.output_groups {}
+    for (_, group) in &board.output_groups {
+        let center = rect.max.x - col_w * 0.5;
+        let text = group.display_value(group.field(board, IoSel::Output));
+        let top_member_y = board.outputs.get(&group.members[0]).unwrap().io.y_pos;
+        g.text(
+            pos2(center, top_member_y - settings.board_io_col_w * 0.5),
+            10.0,
+            &text,
+            style.text,
+            Align2::CENTER_BOTTOM,
+        );
+    }
     result
 }
 
@@ -729,6 +1306,122 @@ pub fn outline_devices(g: &mut Graphics, settings: &Settings, devices: &[u64], b
     }
 }
 
+/// Outlines devices that are exactly on top of another device, which "spread out" fixes
+pub fn show_stacked_devices(g: &mut Graphics, settings: &Settings, devices: &[u64], board: &Board) {
+    const STACKED_COLOR: Color32 = Color32::from_rgb(255, 150, 0);
+    for device_id in devices {
+        let device = board.devices.get(device_id).unwrap();
+        let rect = Rect::from_min_size(device.pos, device_size(device, settings));
+        g.rect_stroke(rect.expand(3.0), 2.0, Stroke::new(2.0, STACKED_COLOR));
+        g.text(
+            rect.center_top() - vec2(0.0, 4.0),
+            10.0,
+            "stacked",
+            STACKED_COLOR,
+            Align2::CENTER_BOTTOM,
+        );
+    }
+}
+
+/// The box that's dragged to select devices, in world space
+pub fn show_select_box(g: &mut Graphics, rect: Rect) {
+    const SELECT_COLOR: Color32 = Color32::from_rgba_premultiplied(40, 80, 120, 60);
+    g.rect(rect, 0.0, [SELECT_COLOR; 2], None);
+    g.rect_stroke(rect, 0.0, Stroke::new(1.0, Color32::WHITE));
+}
+
+pub fn show_slice_path(g: &mut Graphics, path: &[Pos2]) {
+    let stroke = ShowStroke {
+        color: [Color32::from_rgb(255, 60, 60); 2],
+        width: [2.0; 2],
+    };
+    for idx in 1..path.len() {
+        g.line(path[idx - 1], path[idx], 0.0, stroke);
+    }
+}
+
+/// Circles the device inputs and outputs that no link goes to
+pub fn show_unconnected(
+    g: &mut Graphics,
+    settings: &Settings,
+    board: &Board,
+    targets: &[LinkTarget<u64>],
+) {
+    let stroke = ShowStroke {
+        color: [Color32::from_rgb(255, 150, 0); 2],
+        width: [2.0; 2],
+    };
+    for target in targets {
+        let Some(pos) = link_target_pos(settings, board, *target) else { continue };
+        let radius = match target {
+            LinkTarget::DeviceInput(_, _) => settings.device_pin_size * 1.4,
+            LinkTarget::Output(_) => settings.board_io_pin_size * 1.4,
+        };
+        g.circle(pos, radius, [Color32::TRANSPARENT; 2], Some(stroke));
+    }
+}
+
+/// Circles the pin that's selected for keyboard linking
+pub fn show_pin_cursor(g: &mut Graphics, settings: &Settings, pos: Pos2) {
+    let stroke = ShowStroke {
+        color: [Color32::YELLOW; 2],
+        width: [2.0; 2],
+    };
+    let radius = settings.device_pin_size * 1.6;
+    g.circle(pos, radius, [Color32::TRANSPARENT; 2], Some(stroke));
+}
+
+/// Marks where the link anchor being placed snapped to
+pub fn show_anchor_snap(g: &mut Graphics, settings: &Settings, pos: Pos2) {
+    let stroke = ShowStroke {
+        color: [Color32::from_rgb(0, 200, 255); 2],
+        width: [1.0; 2],
+    };
+    let radius = settings.link_width * 0.5 + 2.0;
+    g.circle(pos, radius, [Color32::TRANSPARENT; 2], Some(stroke));
+}
+
+pub fn show_alignment_guides(g: &mut Graphics, guides: &[(Pos2, Pos2)]) {
+    let stroke = ShowStroke {
+        color: [Color32::from_rgb(0, 200, 255); 2],
+        width: [1.0; 2],
+    };
+    for (from, to) in guides {
+        g.line(*from, *to, 0.0, stroke);
+    }
+}
+
+/// Highlights the targets of the pending writes in the board's write queue,
+/// brighter the longer they have to wait, and outlines chips that have pending writes inside them
+pub fn show_write_queue(g: &mut Graphics, settings: &Settings, board: &Board) {
+    const MAX_DELAY: f32 = 4.0;
+
+    let pending_color = |intensity: f32| {
+        let alpha = (intensity.clamp(0.0, 1.0) * 255.0) as u8;
+        Color32::from_rgba_unmultiplied(
+            PENDING_COLOR.r(),
+            PENDING_COLOR.g(),
+            PENDING_COLOR.b(),
+            alpha,
+        )
+    };
+
+    for write in &board.write_queue.writes {
+        let Some(pos) = link_target_pos(settings, board, write.target) else { continue };
+        let color = pending_color((write.delay as f32 + 1.0) / MAX_DELAY);
+        g.circle(pos, settings.device_pin_size * 1.5, [color; 2], None);
+    }
+    for (_, device) in &board.devices {
+        let board::DeviceData::Chip(chip) = &device.data else { continue };
+        if chip.write_queue.len() == 0 {
+            continue;
+        }
+        let rect = Rect::from_min_size(device.pos, device_size(device, settings));
+        let color = pending_color(chip.write_queue.len() as f32 / MAX_DELAY);
+        g.rect_stroke(rect, settings.device_rounding, Stroke::new(3.0, color));
+    }
+}
+
 pub fn show_create_links(
     g: &mut Graphics,
     settings: &Settings,
@@ -736,14 +1429,26 @@ pub fn show_create_links(
     links: &CreateLinks,
     target: Pos2,
 ) {
-    let width = settings.link_width;
-    let color = links.color;
+    let colors = link_colors(links.color, links.rgb, settings.palette);
 
     for idx in (0..links.starts.len()).rev() {
         let link_start = links.starts[idx].clone();
-        let state = board.link_start_state(link_start).unwrap();
-        let pos = link_start_pos(settings, board, link_start).unwrap();
-        show_link(g, width, state, color, pos, target, &links.anchors);
+        let (Some(state), Some(pos)) = (
+            board.link_start_state(link_start),
+            link_start_pos(settings, board, link_start),
+        ) else {
+            continue;
+        };
+        let anchors = links.routing.route(pos, &links.anchors, target);
+        show_link(
+            g,
+            settings.link_width(state),
+            colors[state as usize],
+            pos,
+            target,
+            &anchors,
+            false,
+        );
     }
 }
 
@@ -768,11 +1473,43 @@ pub fn show_held_presets(
         let preset = library.get_preset(name).unwrap();
 
         show_preset_device(g, settings, pos, preset);
-        let size = calc_device_size(
-            preset.data.num_inputs(),
-            preset.data.num_outputs(),
-            settings.device_min_pin_spacing,
-        );
-        pos.y += size.y;
+        pos.y += preset_size(preset, settings).y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Device;
+    use crate::presets::{Library, HIGH_PRESET};
+
+    #[test]
+    fn device_without_inputs() {
+        let settings = Settings::default();
+        let library = Library::new();
+        let preset = library.get_preset(HIGH_PRESET).unwrap();
+        assert_eq!(preset.data.num_inputs(), 0);
+
+        // as tall as a device with a single pin
+        let size = preset_size(preset, &settings);
+        assert_eq!(size.y, 2.0 * settings.device_min_pin_spacing);
+        let rect = Rect::from_min_size(pos2(100.0, 50.0), size);
+        assert_eq!(device_input_locs(&settings, rect, 0).count(), 0);
+        let outputs: Vec<_> = device_output_locs(&settings, rect, 1).collect();
+        let x = rect.max.x + settings.device_pin_size * 0.5;
+        assert_eq!(outputs, [pos2(x, rect.center().y)]);
+
+        let mut board = Board::new();
+        board.add_device(1, Device::from_preset(preset, rect.min));
+        let start = link_start_pos(&settings, &board, LinkStart::DeviceOutput(1, 0));
+        assert_eq!(start, Some(outputs[0]));
+        let target = link_target_pos(&settings, &board, LinkTarget::DeviceInput(1, 0));
+        assert_eq!(target, None);
+
+        let ctx = Context::default();
+        ctx.begin_frame(RawInput::default());
+        let mut g = Graphics::new(&ctx, Transform::identity(), pos2(f32::NAN, f32::NAN));
+        show_preset_device(&mut g, &settings, rect.min, preset);
+        assert!(!g.finish().is_empty());
     }
 }
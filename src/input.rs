@@ -1,5 +1,5 @@
 use crate::app::AppItem;
-use egui::{Context, Event, Key, Modifiers, Pos2, TouchPhase, Vec2};
+use egui::{Context, Event, Key, Modifiers, MultiTouchInfo, Pos2, TouchPhase, Vec2};
 use hashbrown::HashSet;
 
 #[derive(Default)]
@@ -7,6 +7,8 @@ pub struct Input {
     pub native: bool,
     pub pressed_keys: HashSet<Key>,
     pub modifiers: Modifiers,
+    /// If a text field had keyboard focus at the start of the frame, see `shortcut`
+    pub typing: bool,
     pub press_pos: Pos2,
 
     // pointer
@@ -20,9 +22,19 @@ pub struct Input {
     pub clicked_prim: bool,
     /// If the secondary pointer button was clicked this frame
     pub clicked_sec: bool,
+    /// If the primary pointer button was clicked twice in a row, in the same place, this frame
+    pub double_clicked_prim: bool,
+    /// When and where the last primary click was (egui time, in seconds)
+    last_click: Option<(f64, Pos2)>,
 
     pub drag: Option<(Vec2, AppItem)>,
     pub scroll_delta: Vec2,
+    /// If the last press was with a finger
+    pub touch: bool,
+    /// Two or more fingers are down, which pan and zoom the view instead of dragging or clicking
+    pub multi_touch: Option<MultiTouchInfo>,
+    /// The middle of the fingers of `multi_touch`
+    pub touch_center: Pos2,
     /// The app item that was hovered last frame
     prev_hovered: AppItem,
     /// The app item that was determined to be hovered this frame
@@ -46,12 +58,15 @@ impl Input {
         }
     }
 
-    pub fn update(&mut self, ctx: &Context) {
+    /// A release within `click_tolerance` points of where the pointer was pressed is still a click
+    pub fn update(&mut self, ctx: &Context, click_tolerance: f32) {
         self.hovered_changed = self.prev_hovered != self.new_hovered;
         self.prev_hovered = self.new_hovered;
+        self.typing = ctx.wants_keyboard_input();
 
         let input = ctx.input();
         let mut released_press = input.pointer.any_released();
+        let mut touch_started = false;
 
         // key presses
         self.pressed_keys.clear();
@@ -62,6 +77,12 @@ impl Input {
                 } => {
                     self.pressed_keys.insert(*key);
                 }
+                Event::Touch {
+                    phase: TouchPhase::Start,
+                    ..
+                } => {
+                    touch_started = true;
+                }
                 Event::Touch {
                     phase: TouchPhase::End | TouchPhase::Cancel,
                     ..
@@ -85,18 +106,54 @@ impl Input {
 
         if self.pressed_prim {
             self.drag = Some((Vec2::ZERO, self.prev_hovered));
+        }
+        if self.pressed_prim || self.pressed_sec {
             self.press_pos = self.pointer_pos;
         }
         let pointer_delta = self.pointer_pos - self.prev_pointer_pos;
         if let Some((delta, _)) = &mut self.drag {
             *delta = pointer_delta;
         }
-        self.clicked_prim = input.pointer.primary_released() && self.press_pos == self.pointer_pos;
-        self.clicked_sec = input.pointer.secondary_released() && self.press_pos == self.pointer_pos;
+        let in_place = self.press_pos.distance(self.pointer_pos) <= click_tolerance;
+        self.clicked_prim = input.pointer.primary_released() && in_place;
+        self.clicked_sec = input.pointer.secondary_released() && in_place;
+
+        const DOUBLE_CLICK_TIME: f64 = 0.3;
+        self.double_clicked_prim = false;
+        if self.clicked_prim {
+            self.double_clicked_prim = self.last_click.is_some_and(|(time, pos)| {
+                input.time - time <= DOUBLE_CLICK_TIME
+                    && pos.distance(self.pointer_pos) <= click_tolerance
+            });
+            // a third click starts over, instead of being another double click
+            self.last_click = match self.double_clicked_prim {
+                true => None,
+                false => Some((input.time, self.pointer_pos)),
+            };
+        }
 
         if released_press {
             self.drag = None;
         }
+
+        if input.pointer.any_pressed() {
+            self.touch = touch_started;
+        }
+        let multi_touch = input.multi_touch().filter(|touch| touch.num_touches >= 2);
+        if let Some(touch) = multi_touch {
+            if self.multi_touch.is_none() {
+                self.touch_center = touch.start_pos;
+            }
+            self.touch_center += touch.translation_delta;
+            // the first finger is also the pointer, but shouldn't drag or click what it's on
+            self.drag = None;
+            self.pressed_prim = false;
+            self.pressed_sec = false;
+            self.clicked_prim = false;
+            self.clicked_sec = false;
+            self.double_clicked_prim = false;
+        }
+        self.multi_touch = multi_touch;
         self.new_hovered = AppItem::None;
     }
 
@@ -108,6 +165,12 @@ impl Input {
     pub fn pressed(&self, key: Key) -> bool {
         self.pressed_keys.contains(&key)
     }
+    /// Determines if a key was pressed as a board shortcut.
+    /// Shortcuts are ignored while typing in a text field (like the preset placer or a name),
+    /// so typing a name or pressing Backspace there doesn't edit the board.
+    pub fn shortcut(&self, key: Key) -> bool {
+        !self.typing && self.pressed(key)
+    }
 
     /// Determines if a key was pressed as a command keybind.
     /// The modifiers are:
@@ -118,6 +181,7 @@ impl Input {
     /// | Linux    | Ctrl    | Alt    |
     /// |:--------:|:-------:|:------:|
     ///
+    /// Like `shortcut`, these are ignored while typing in a text field.
     pub fn command_used(&self, key: Key) -> bool {
         // On web, I can't use Ctrl/command because those will trigger browser shortcuts.
         let mod_cond = if cfg!(wasm) {
@@ -127,7 +191,7 @@ impl Input {
             // .command is `command` on MacOS, but `Ctrl` on Windows/Linux
             self.modifiers.command
         };
-        mod_cond && self.pressed_keys.contains(&key)
+        mod_cond && self.shortcut(key)
     }
 
     pub fn display_command(key: Key) -> String {
@@ -2,13 +2,18 @@ pub mod app;
 pub mod board;
 pub mod graphics;
 pub mod input;
+pub mod netlist;
 pub mod old_data;
 pub mod presets;
+pub mod profiler;
 pub mod settings;
+pub mod sim;
+pub mod svg;
 pub mod ui;
 
 use crate::presets::Library;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 pub struct IntegrationInfo {
     pub name: String,
@@ -19,11 +24,36 @@ pub struct IntegrationInfo {
 pub enum OutEvent {
     None,
     Quit,
+    /// Save everything, then quit (from the quit prompt)
+    SaveAndQuit,
+    /// Quit without saving (from the quit prompt)
+    DiscardAndQuit,
     ToggleFullscreen,
 
     ImportPresets,
     RevealConfigDir,
 
+    ExportBoard,
+    ImportBoard,
+    /// Export the board as a `Netlist`
+    ExportNetlist,
+    ImportNetlist,
+    ExportLibrary,
+    ImportBundle,
+    ImportVectors,
+    ExportVectors,
+    ExportSelection,
+    /// Export the truth table of the preset as CSV, see `App::preset_csv`
+    ExportCsv(String),
+
+    /// Save the board in the boards directory, under a name
+    SaveBoardAs(String),
+    /// Open a board from the boards directory
+    OpenBoard(String),
+    DeleteBoard(String),
+    /// Fill `BoardsMenu::saved` with the boards in the boards directory
+    ListBoards,
+
     LoadBoard,
     LoadLibrary,
     LoadSettings,
@@ -45,7 +75,7 @@ pub fn rand_id() -> u64 {
     u64::from_le_bytes(bytes)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum LinkTarget<T> {
     DeviceInput(T, usize),
     Output(T),
@@ -63,11 +93,18 @@ impl<T: Copy> DeviceInput<T> {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Link {
     pub target: LinkTarget<u64>,
     pub anchors: Vec<egui::Pos2>,
+    /// An index into `LINK_COLORS`, used if `rgb` isn't set
     pub color: usize,
+    /// An explicit off and on color
+    #[serde(default)]
+    pub rgb: Option<[egui::Color32; 2]>,
+    /// Extra updates that writes through this link are delayed by
+    #[serde(default)]
+    pub delay: u8,
 }
 impl Link {
     pub fn new(target: LinkTarget<u64>, color: usize, anchors: Vec<egui::Pos2>) -> Self {
@@ -75,103 +112,320 @@ impl Link {
             target,
             anchors,
             color,
+            rgb: None,
+            delay: 0,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// The first 64 bits are kept in `data`, only wider fields allocate `wide` for the rest.
+/// Fields saved before they could be wider than 64 bits are loaded with `old_data::OldBitField`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "SavedBitField")]
 pub struct BitField {
     pub data: u64,
     len: usize,
+    wide: Box<[u64]>,
 }
 impl BitField {
-    pub const fn empty(len: usize) -> Self {
-        assert!(len <= 64);
-        Self { len, data: 0 }
+    /// How many bits fit in `data`, which is all that's used when a field is read as one number
+    pub const MAX_LEN: usize = 64;
+
+    pub fn empty(len: usize) -> Self {
+        Self {
+            len,
+            data: 0,
+            wide: vec![0; Self::num_words(len) - 1].into(),
+        }
+    }
+    /// A field that fits in `data`, without allocating
+    #[inline(always)]
+    pub fn narrow(len: usize, data: u64) -> Self {
+        debug_assert!(len <= Self::MAX_LEN);
+        Self {
+            len,
+            data,
+            wide: Box::default(),
+        }
+    }
+    /// A field with the bits of `words`, see `words`
+    pub fn from_words(len: usize, words: &[u64]) -> Self {
+        debug_assert_eq!(words.len(), Self::num_words(len));
+        Self {
+            len,
+            data: words[0],
+            wide: words[1..].into(),
+        }
+    }
+    /// How many words a field of `len` bits takes, there's always at least `data`
+    pub fn num_words(len: usize) -> usize {
+        len.div_ceil(64).max(1)
     }
 
     // NOTE: hot code!
     #[inline(always)]
     pub fn set(&mut self, pos: usize, state: bool) {
         debug_assert!(pos < self.len);
-        self.data = (self.data & !(1 << pos as u64)) | ((state as u64) << pos);
+        let (word, bit) = match pos < Self::MAX_LEN {
+            true => (&mut self.data, pos),
+            false => {
+                let pos = pos - Self::MAX_LEN;
+                (&mut self.wide[pos / 64], pos % 64)
+            }
+        };
+        *word = (*word & !(1 << bit as u64)) | ((state as u64) << bit);
     }
     // NOTE: hot code!
     #[inline(always)]
     pub fn get(&self, pos: usize) -> bool {
         debug_assert!(pos < self.len);
-        ((self.data >> pos as u64) & 1) == 1
+        ((self.word(pos / 64) >> (pos % 64) as u64) & 1) == 1
+    }
+    /// The bits `64 * idx` to `64 * idx + 63`, `data` is the first word
+    #[inline(always)]
+    pub fn word(&self, idx: usize) -> u64 {
+        match idx {
+            0 => self.data,
+            idx => self.wide[idx - 1],
+        }
+    }
+    /// `data`, then the words of the bits after it
+    pub fn words(&self) -> impl Iterator<Item = u64> + '_ {
+        std::iter::once(self.data).chain(self.wide.iter().copied())
+    }
+    pub fn any_on(&self) -> bool {
+        self.data != 0 || self.wide.iter().any(|word| *word != 0)
+    }
+    /// Turns every bit off
+    pub fn clear(&mut self) {
+        self.data = 0;
+        self.wide.fill(0);
+    }
+}
+
+/// A `BitField` as it's loaded, to check that `wide` has a word for every 64 bits after `data`
+#[derive(Deserialize)]
+struct SavedBitField {
+    data: u64,
+    len: usize,
+    /// Text files from before fields could be wider don't have it
+    #[serde(default)]
+    wide: Box<[u64]>,
+}
+impl TryFrom<SavedBitField> for BitField {
+    type Error = String;
+
+    fn try_from(saved: SavedBitField) -> Result<Self, String> {
+        if saved.wide.len() != Self::num_words(saved.len) - 1 {
+            return Err(format!(
+                "A bit field of {} bits has the wrong length",
+                saved.len
+            ));
+        }
+        Ok(Self {
+            data: saved.data,
+            len: saved.len,
+            wide: saved.wide,
+        })
     }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+/// Saved with `map` run-length encoded when that makes it smaller, see `SavedTruthTable`.
+/// `map` is shared, so cloning a table (like placing a device) doesn't copy it
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "SavedTruthTable", into = "SavedTruthTable")]
 pub struct TruthTable {
     pub num_inputs: usize,
     pub num_outputs: usize,
-    pub map: Vec<u64>,
+    /// The outputs for every input combination, one word per 64 outputs (see `BitField::words`)
+    pub map: Arc<[u64]>,
 }
+
+/// The saved form of a `TruthTable`, with the same layout as the table itself so old files still load.
+/// If `map` doesn't have every word of the table, it's a list of (word, run length) pairs.
+#[derive(Serialize, Deserialize)]
+struct SavedTruthTable {
+    num_inputs: usize,
+    num_outputs: usize,
+    map: Vec<u64>,
+}
+impl From<TruthTable> for SavedTruthTable {
+    fn from(table: TruthTable) -> Self {
+        let mut runs: Vec<u64> = Vec::new();
+        for output in table.map.iter() {
+            match runs.len() {
+                len if len >= 2 && runs[len - 2] == *output => runs[len - 1] += 1,
+                _ => runs.extend([*output, 1]),
+            }
+            if runs.len() >= table.map.len() {
+                break;
+            }
+        }
+        let map = match runs.len() < table.map.len() {
+            true => runs,
+            false => table.map.to_vec(),
+        };
+        Self {
+            num_inputs: table.num_inputs,
+            num_outputs: table.num_outputs,
+            map,
+        }
+    }
+}
+impl TryFrom<SavedTruthTable> for TruthTable {
+    type Error = String;
+
+    fn try_from(saved: SavedTruthTable) -> Result<Self, String> {
+        let Some(len) = TruthTable::map_len(saved.num_inputs, saved.num_outputs) else {
+            return Err(format!(
+                "Too big a truth table: {} inputs, {} outputs",
+                saved.num_inputs, saved.num_outputs
+            ));
+        };
+        if saved.map.len() == len {
+            return Ok(Self {
+                num_inputs: saved.num_inputs,
+                num_outputs: saved.num_outputs,
+                map: saved.map.into(),
+            });
+        }
+        let runs = saved.map.chunks_exact(2);
+        if !runs.remainder().is_empty() {
+            return Err(String::from("Invalid truth table runs"));
+        }
+        // the runs have to fill the table exactly, check that before allocating it
+        let total = (runs.clone()).try_fold(0usize, |total, run| {
+            total.checked_add(usize::try_from(run[1]).ok()?)
+        });
+        if total != Some(len) {
+            return Err(String::from("Invalid truth table runs"));
+        }
+        let mut map = Vec::with_capacity(len);
+        for run in runs {
+            map.resize(map.len() + run[1] as usize, run[0]);
+        }
+        Ok(Self {
+            num_inputs: saved.num_inputs,
+            num_outputs: saved.num_outputs,
+            map: map.into(),
+        })
+    }
+}
+
 impl TruthTable {
+    /// The most inputs a table can have, the most `Settings::max_comb_inputs` allows
+    pub const MAX_INPUTS: usize = 24;
+    /// The most words `map` can have, as many as a table with `MAX_INPUTS` inputs and up to 64 outputs
+    pub const MAX_MAP_LEN: usize = 1 << Self::MAX_INPUTS;
+
+    /// How many words `map` has for the pin counts, `None` if that's more than `MAX_MAP_LEN`
+    pub fn map_len(num_inputs: usize, num_outputs: usize) -> Option<usize> {
+        if num_inputs > Self::MAX_INPUTS {
+            return None;
+        }
+        let len = (1usize << num_inputs).checked_mul(BitField::num_words(num_outputs))?;
+        (len <= Self::MAX_MAP_LEN).then_some(len)
+    }
+    /// How many input combinations there are
+    pub fn num_rows(&self) -> usize {
+        1 << self.num_inputs
+    }
+
     // NOTE: hot code!
     #[inline(always)]
     pub fn get(&self, input: usize) -> BitField {
-        BitField {
-            len: self.num_outputs,
-            data: self.map[input],
+        if self.num_outputs <= BitField::MAX_LEN {
+            return BitField::narrow(self.num_outputs, self.map[input]);
         }
+        let row_len = BitField::num_words(self.num_outputs);
+        BitField::from_words(self.num_outputs, &self.map[input * row_len..][..row_len])
+    }
+    /// The outputs for every input combination
+    pub fn rows(&self) -> impl Iterator<Item = BitField> + '_ {
+        (0..self.num_rows()).map(|input| self.get(input))
+    }
+
+    /// The words of `map` without the bits that aren't actual outputs
+    fn masked_map(&self) -> impl Iterator<Item = u64> + '_ {
+        let row_len = BitField::num_words(self.num_outputs);
+        let mask = move |idx: usize| match self.num_outputs.saturating_sub(idx % row_len * 64) {
+            n if n >= 64 => u64::MAX,
+            n => (1 << n) - 1,
+        };
+        (self.map.iter().enumerate()).map(move |(idx, word)| word & mask(idx))
+    }
+
+    /// A hash of the pin counts and the outputs for every input, so that functionally
+    /// identical tables have the same key (FNV-1a, so it's stable between runs)
+    pub fn canonical_key(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let words = [self.num_inputs as u64, self.num_outputs as u64];
+        let words = words.into_iter().chain(self.masked_map());
+        for word in words {
+            for byte in word.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        hash
+    }
+
+    /// If both tables have the same pin counts and give the same outputs for every input
+    pub fn same_logic(&self, other: &Self) -> bool {
+        self.num_inputs == other.num_inputs
+            && self.num_outputs == other.num_outputs
+            && self.masked_map().eq(other.masked_map())
     }
 }
 use std::fmt;
 impl fmt::Debug for TruthTable {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut f = f.debug_struct("TruthTable");
-        let mut input = 0;
-        for output in &self.map {
-            f.field(
-                &format!("{:01$b}", input, self.num_inputs),
-                &format!("{:01$b}", *output, self.num_outputs),
-            );
-            input += 1;
+        for (input, output) in self.rows().enumerate() {
+            let output: String = (0..self.num_outputs)
+                .rev()
+                .map(|idx| if output.get(idx) { '1' } else { '0' })
+                .collect();
+            f.field(&format!("{:01$b}", input, self.num_inputs), &output);
         }
         f.finish()
     }
 }
 
 pub struct ChangedOutputs {
-    prev_output: u64,
-    new_output: u64,
-    len: usize,
+    prev_output: BitField,
+    new_output: BitField,
     index: usize,
 }
 impl ChangedOutputs {
     #[inline(always)]
-    pub const fn new(prev: BitField, new: BitField) -> Self {
+    pub fn new(prev: BitField, new: BitField) -> Self {
         debug_assert!(prev.len == new.len);
         Self {
-            prev_output: prev.data,
-            new_output: new.data,
-            len: prev.len,
+            prev_output: prev,
+            new_output: new,
             index: 0,
         }
     }
     #[inline(always)]
-    pub const fn none() -> Self {
-        Self {
-            prev_output: 0,
-            new_output: 0,
-            len: 0,
-            index: 0,
-        }
+    pub fn none() -> Self {
+        Self::new(BitField::narrow(0, 0), BitField::narrow(0, 0))
     }
 
     #[inline(always)]
     pub fn next(&mut self) -> Option<(usize, bool)> {
-        while self.index < self.len {
+        let (prev, new) = (&self.prev_output, &self.new_output);
+        while self.index < prev.len {
             let idx = self.index;
-            let prev_bit = (self.prev_output >> idx as u64) & 1;
-            let new_bit = (self.new_output >> idx as u64) & 1;
+            let (word, bit) = (idx / 64, idx % 64);
+            // skip whole words that didn't change
+            if bit == 0 && prev.word(word) == new.word(word) {
+                self.index += 64;
+                continue;
+            }
             self.index += 1;
-            if prev_bit != new_bit {
-                return Some((idx, new_bit == 1));
+            if prev.get(idx) != new.get(idx) {
+                return Some((idx, new.get(idx)));
             }
         }
         None
@@ -181,3 +435,86 @@ pub struct ChangedOutput {
     pub output: usize,
     pub state: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(num_inputs: usize, num_outputs: usize, map: Vec<u64>) -> TruthTable {
+        TruthTable {
+            num_inputs,
+            num_outputs,
+            map: map.into(),
+        }
+    }
+    fn load(num_inputs: usize, num_outputs: usize, map: Vec<u64>) -> Result<TruthTable, String> {
+        TruthTable::try_from(SavedTruthTable {
+            num_inputs,
+            num_outputs,
+            map,
+        })
+    }
+
+    #[test]
+    fn repetitive_table_saves_runs() {
+        let mut map = vec![0b01; 200];
+        map.extend([0b10; 56]);
+        let table = table(8, 2, map);
+        let saved = SavedTruthTable::from(table.clone());
+        assert_eq!(saved.map, [0b01, 200, 0b10, 56]);
+
+        let bytes = bincode::serialize(&table).unwrap();
+        assert_eq!(bincode::deserialize::<TruthTable>(&bytes).unwrap(), table);
+    }
+
+    #[test]
+    fn varied_table_saves_raw() {
+        let table = table(2, 2, vec![0, 1, 2, 3]);
+        assert_eq!(SavedTruthTable::from(table.clone()).map, [0, 1, 2, 3]);
+
+        let bytes = bincode::serialize(&table).unwrap();
+        assert_eq!(bincode::deserialize::<TruthTable>(&bytes).unwrap(), table);
+    }
+
+    #[test]
+    fn invalid_runs_dont_load() {
+        // odd length
+        assert!(load(2, 1, vec![1, 2, 3]).is_err());
+        // too few rows
+        assert!(load(2, 1, vec![1, 3]).is_err());
+        // too many rows
+        assert!(load(2, 1, vec![1, 5]).is_err());
+        // runs that overflow when summed (6 words, so it isn't read as the 4 rows)
+        assert!(load(2, 1, vec![1, u64::MAX, 0, 4, 1, 1]).is_err());
+        // too big to allocate
+        assert!(load(TruthTable::MAX_INPUTS + 1, 1, vec![1, 1 << 25]).is_err());
+        assert!(load(2, 1, vec![1, 2, 0, 2]).is_ok());
+    }
+
+    #[test]
+    fn wide_bit_field_round_trip() {
+        let mut field = BitField::from_words(130, &[0; 3]);
+        for pos in [0, 63, 64, 100, 129] {
+            field.set(pos, true);
+        }
+        let text = ron::ser::to_string(&field).unwrap();
+        assert_eq!(ron::de::from_str::<BitField>(&text).unwrap(), field);
+        let bytes = bincode::serialize(&field).unwrap();
+        assert_eq!(bincode::deserialize::<BitField>(&bytes).unwrap(), field);
+
+        // a word short
+        let text = "(data: 1, len: 130, wide: [2])";
+        assert!(ron::de::from_str::<BitField>(text).is_err());
+    }
+
+    #[test]
+    fn old_bit_field_loads() {
+        let old = BitField::narrow(3, 0b101);
+        let loaded: BitField = ron::de::from_str("(data: 5, len: 3)").unwrap();
+        assert_eq!(loaded, old);
+        // binary files need `OldBitField`, their layout doesn't say that `wide` is missing
+        let bytes = bincode::serialize(&(0b101u64, 3usize)).unwrap();
+        let loaded: old_data::OldBitField = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(loaded.update(), old);
+    }
+}
@@ -0,0 +1,388 @@
+use crate::board::{Board, Device, DeviceData, Io, IoSel};
+use crate::presets::{DevicePreset, Library};
+use crate::settings::Settings;
+use crate::*;
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// A board as a flat list of gates and the nets between them, for moving circuits to and from other simulators.
+/// Each net is a number, driven by one board input or gate output.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Netlist {
+    pub inputs: Vec<Port>,
+    pub outputs: Vec<Port>,
+    pub cells: Vec<Cell>,
+}
+
+/// A board input or output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Port {
+    pub name: String,
+    /// None if nothing is linked to it
+    pub net: Option<usize>,
+}
+
+/// A gate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cell {
+    /// One of `GATES`, or the name of the preset for other gates
+    pub kind: String,
+    /// The net each input reads, None if it isn't linked
+    pub inputs: Vec<Option<usize>>,
+    /// The net each output drives
+    pub outputs: Vec<usize>,
+    /// For gates that aren't in `GATES`, the outputs (as bits) for every combination of inputs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<Vec<u64>>,
+}
+
+/// The gates that other simulators know by name
+pub const GATES: &[&str] = &["BUF", "NOT", "AND", "OR", "NAND", "NOR", "XOR", "XNOR"];
+
+/// The truth table of one of `GATES`, BUF and NOT have 1 input and the others at least 2
+pub fn gate_table(kind: &str, num_inputs: usize) -> Option<TruthTable> {
+    match (kind, num_inputs) {
+        ("BUF" | "NOT", 1) => {}
+        ("AND" | "OR" | "NAND" | "NOR" | "XOR" | "XNOR", 2..=16) => {}
+        _ => return None,
+    }
+    let output = |input: u64| {
+        let ones = input.count_ones() as usize;
+        match kind {
+            "BUF" | "OR" => ones > 0,
+            "NOT" | "NOR" => ones == 0,
+            "AND" => ones == num_inputs,
+            "NAND" => ones != num_inputs,
+            "XOR" => ones & 1 == 1,
+            _ => ones & 1 == 0,
+        }
+    };
+    Some(TruthTable {
+        num_inputs,
+        num_outputs: 1,
+        map: (0..1 << num_inputs)
+            .map(|input| output(input) as u64)
+            .collect(),
+    })
+}
+
+impl Netlist {
+    /// Only combinational gates are written, chips are left out.
+    /// Returns the netlist and warnings about what couldn't be written.
+    pub fn from_board(board: &Board) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut warn = |warning: String| {
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        };
+
+        let inputs = board.inputs_sorted();
+        let devices: Vec<(u64, &Device)> = board
+            .devices_sorted()
+            .filter(|(_, device)| match device.data {
+                DeviceData::CombGate(_) => true,
+                DeviceData::Chip(_) => {
+                    warn(format!("{} is a chip, so it was left out", device.preset));
+                    false
+                }
+            })
+            .collect();
+
+        // every input and gate output drives a net, read by what it links to
+        let mut nets = 0;
+        let mut target_nets: HashMap<LinkTarget<u64>, usize> = HashMap::new();
+        let mut linked_twice = false;
+        let mut add_net = |links: &[Link]| {
+            for link in links {
+                linked_twice |= target_nets.try_insert(link.target, nets).is_err();
+            }
+            nets += 1;
+            nets - 1
+        };
+        let mut netlist = Self::default();
+        for id in &inputs {
+            let input = &board.inputs[id];
+            netlist.inputs.push(Port {
+                name: input.io.name.clone(),
+                net: Some(add_net(&input.links)),
+            });
+        }
+        let mut outputs = Vec::with_capacity(devices.len());
+        for (_, device) in &devices {
+            let links = device.links.iter();
+            outputs.push(links.map(|links| add_net(links)).collect());
+        }
+        if linked_twice {
+            warn(String::from(
+                "A pin has more than one link to it, only the first was kept",
+            ));
+        }
+
+        for ((id, device), outputs) in devices.into_iter().zip(outputs) {
+            let table = device.data.truth_table().unwrap();
+            let kind = GATES
+                .iter()
+                .find(|kind| matches!(gate_table(kind, table.num_inputs), Some(gate) if gate.same_logic(&table)));
+            let inputs = (0..device.num_inputs())
+                .map(|input| {
+                    target_nets
+                        .get(&LinkTarget::DeviceInput(id, input))
+                        .copied()
+                })
+                .collect();
+            netlist.cells.push(match kind {
+                Some(kind) => Cell {
+                    kind: String::from(*kind),
+                    inputs,
+                    outputs,
+                    table: None,
+                },
+                None => Cell {
+                    kind: device.preset.clone(),
+                    inputs,
+                    outputs,
+                    table: Some(table.map.to_vec()),
+                },
+            });
+        }
+        for id in board.outputs_sorted() {
+            netlist.outputs.push(Port {
+                name: board.outputs[&id].io.name.clone(),
+                net: target_nets.get(&LinkTarget::Output(id)).copied(),
+            });
+        }
+        (netlist, warnings)
+    }
+
+    /// Places a preset from the library for every cell, and lays them out with `Board::arrange_devices`.
+    /// Returns the board and warnings about the cells that were left out.
+    pub fn to_board(&self, library: &Library, settings: &Settings) -> (Board, Vec<String>) {
+        let mut board = Board::new();
+        let mut warnings = Vec::new();
+        let mut drivers: HashMap<usize, LinkStart<u64>> = HashMap::new();
+        let sp = settings.board_io_col_w;
+        let io_h = (self.inputs.len().max(self.outputs.len()) + 1) as f32 * sp;
+        board.rect.max.y = board.rect.max.y.max(board.rect.min.y + io_h);
+
+        for (idx, port) in self.inputs.iter().enumerate() {
+            let id = rand_id();
+            let mut io = Io::new(board.rect.min.y + (idx + 1) as f32 * sp);
+            io.name = port.name.clone();
+            board.add_io(IoSel::Input, id, io);
+            if let Some(net) = port.net {
+                drivers.insert(net, LinkStart::Input(id));
+            }
+        }
+        let mut cells = Vec::new();
+        for cell in &self.cells {
+            let preset = match find_preset(cell, library) {
+                Ok(preset) => preset,
+                Err(err) => {
+                    warnings.push(err);
+                    continue;
+                }
+            };
+            let id = rand_id();
+            board.add_device(id, Device::from_preset(preset, board.rect.min));
+            for (output, net) in cell.outputs.iter().enumerate() {
+                if drivers
+                    .insert(*net, LinkStart::DeviceOutput(id, output))
+                    .is_some()
+                {
+                    warnings.push(format!("Net {net} is driven more than once"));
+                }
+            }
+            cells.push((id, cell));
+        }
+
+        let mut link =
+            |board: &mut Board, net: usize, target: LinkTarget<u64>| match drivers.get(&net) {
+                Some(start) => board.add_link(*start, Link::new(target, 0, Vec::new())),
+                None => warnings.push(format!("Nothing drives net {net}")),
+            };
+        for (id, cell) in cells {
+            for (input, net) in cell.inputs.iter().enumerate() {
+                if let Some(net) = net {
+                    link(&mut board, *net, LinkTarget::DeviceInput(id, input));
+                }
+            }
+        }
+        for (idx, port) in self.outputs.iter().enumerate() {
+            let id = rand_id();
+            let mut io = Io::new(board.rect.min.y + (idx + 1) as f32 * sp);
+            io.name = port.name.clone();
+            board.add_io(IoSel::Output, id, io);
+            if let Some(net) = port.net {
+                link(&mut board, net, LinkTarget::Output(id));
+            }
+        }
+
+        board.arrange_devices(settings);
+        let ids: Vec<u64> = board.devices.keys().copied().collect();
+        if let Some(rect) = board.devices_rect(&ids, settings) {
+            board.rect = board.rect.union(rect.expand(sp * 2.0));
+        }
+        board.reset_sim();
+        (board, warnings)
+    }
+}
+
+/// A preset with the name of the cell, or else one with the same logic
+fn find_preset<'a>(cell: &Cell, library: &'a Library) -> Result<&'a DevicePreset, String> {
+    let num_inputs = cell.inputs.len();
+    let num_outputs = cell.outputs.len();
+    let preset = match library.get_preset(&cell.kind) {
+        Some(preset) => preset,
+        None => {
+            let table = match (gate_table(&cell.kind, num_inputs), &cell.table) {
+                (Some(table), _) => table,
+                (None, Some(map))
+                    if TruthTable::map_len(num_inputs, num_outputs) == Some(map.len()) =>
+                {
+                    TruthTable {
+                        num_inputs,
+                        num_outputs,
+                        map: map.as_slice().into(),
+                    }
+                }
+                (None, Some(_)) => {
+                    return Err(format!(
+                        "The table of {} has the wrong length, so it was left out",
+                        cell.kind
+                    ))
+                }
+                (None, None) => {
+                    return Err(format!(
+                        "{} isn't a known gate or preset, so it was left out",
+                        cell.kind
+                    ))
+                }
+            };
+            match library.find_equivalent(&table).first() {
+                Some(preset) => preset,
+                None => {
+                    return Err(format!(
+                        "No preset has the logic of {}, so it was left out",
+                        cell.kind
+                    ))
+                }
+            }
+        }
+    };
+    match preset.data.num_inputs() == num_inputs && preset.data.num_outputs() == num_outputs {
+        true => Ok(preset),
+        false => Err(format!(
+            "{} has the wrong number of pins, so it was left out",
+            cell.kind
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::{ChipPreset, CombGatePreset, PresetData, PresetSource};
+    use egui::pos2;
+
+    /// A Nand made of an And and a Not
+    fn nand_board(library: &Library) -> Board {
+        let mut board = Board::new();
+        board.add_input(50.0);
+        board.add_input(100.0);
+        board.add_output(75.0);
+        let and = library.get_preset("And").unwrap();
+        board.add_device(1, Device::from_preset(and, pos2(100.0, 60.0)));
+        let not = library.get_preset("Not").unwrap();
+        board.add_device(2, Device::from_preset(not, pos2(200.0, 60.0)));
+
+        for (idx, input) in board.inputs_sorted().into_iter().enumerate() {
+            let link = Link::new(LinkTarget::DeviceInput(1, idx), 0, Vec::new());
+            board.add_link(LinkStart::Input(input), link);
+        }
+        let link = Link::new(LinkTarget::DeviceInput(2, 0), 0, Vec::new());
+        board.add_link(LinkStart::DeviceOutput(1, 0), link);
+        let output = board.outputs_sorted()[0];
+        let link = Link::new(LinkTarget::Output(output), 0, Vec::new());
+        board.add_link(LinkStart::DeviceOutput(2, 0), link);
+        board
+    }
+
+    fn truth_table(board: &mut Board) -> TruthTable {
+        let max_inputs = CombGatePreset::DEFAULT_MAX_INPUTS;
+        CombGatePreset::from_board(board, max_inputs).unwrap().table
+    }
+
+    #[test]
+    fn round_trip_keeps_logic() {
+        let library = Library::new();
+        let mut board = nand_board(&library);
+        let (netlist, warnings) = Netlist::from_board(&board);
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let mut kinds: Vec<_> = (netlist.cells.iter()).map(|cell| &cell.kind).collect();
+        kinds.sort();
+        assert_eq!(kinds, ["AND", "NOT"]);
+
+        let (mut loaded, warnings) = netlist.to_board(&library, &Settings::default());
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert_eq!(loaded.devices.len(), 2);
+        let table = truth_table(&mut loaded);
+        assert_eq!(table, truth_table(&mut board));
+        assert!(table.same_logic(&gate_table("NAND", 2).unwrap()));
+    }
+
+    #[test]
+    fn chips_are_left_out() {
+        let library = Library::new();
+        let inner = nand_board(&library);
+        let chip = DevicePreset {
+            name: String::from("Chip Nand"),
+            cat: String::from("Chips"),
+            color: [0, 100, 200, 255],
+            data: PresetData::Chip(ChipPreset::from_board(&inner)),
+            src: PresetSource::Board(inner),
+            glyph: None,
+        };
+        let mut board = Board::new();
+        board.add_device(1, Device::from_preset(&chip, pos2(100.0, 60.0)));
+
+        let (netlist, warnings) = Netlist::from_board(&board);
+        assert!(netlist.cells.is_empty());
+        assert_eq!(warnings, ["Chip Nand is a chip, so it was left out"]);
+    }
+
+    #[test]
+    fn undriven_net_warns() {
+        let netlist = Netlist {
+            inputs: Vec::new(),
+            outputs: vec![Port {
+                name: String::from("out"),
+                net: Some(5),
+            }],
+            cells: Vec::new(),
+        };
+        let (board, warnings) = netlist.to_board(&Library::new(), &Settings::default());
+        assert_eq!(board.outputs.len(), 1);
+        assert_eq!(warnings, ["Nothing drives net 5"]);
+    }
+
+    #[test]
+    fn wrong_table_length_warns() {
+        let netlist = Netlist {
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            cells: vec![Cell {
+                kind: String::from("Weird"),
+                inputs: vec![None, None],
+                outputs: vec![0],
+                table: Some(vec![0, 1]),
+            }],
+        };
+        let (board, warnings) = netlist.to_board(&Library::new(), &Settings::default());
+        assert!(board.devices.is_empty());
+        assert_eq!(
+            warnings,
+            ["The table of Weird has the wrong length, so it was left out"]
+        );
+    }
+}
@@ -1,6 +1,8 @@
-use crate::board::{Board, Device, DeviceData, Group, Input, Io, Output, WriteQueue};
+use crate::board::{
+    Board, Chip, ChipDevice, CombGate, Device, DeviceData, Group, Input, Io, Output, WriteQueue,
+};
 use crate::presets::{DevicePreset, PresetData, PresetSource};
-use crate::{DeviceInput, Link, LinkTarget};
+use crate::{BitField, DeviceInput, Link, LinkTarget, TruthTable};
 use egui::{Color32, Pos2, Rect};
 use hashbrown::HashMap;
 use serde::Deserialize;
@@ -28,6 +30,8 @@ impl OldInput {
                 state: self.state,
                 group_member: self.group_member,
             },
+            force: None,
+            clock: None,
         }
     }
 }
@@ -48,6 +52,7 @@ impl OldOutput {
                 state: self.state,
                 group_member: self.group_member,
             },
+            expected: None,
         }
     }
 }
@@ -55,7 +60,7 @@ impl OldOutput {
 #[derive(Deserialize)]
 pub struct OldDevice {
     pub pos: Pos2,
-    pub data: DeviceData,
+    pub data: OldDeviceData,
     pub links: Vec<Vec<LinkTarget<u64>>>,
     pub name: String,
     pub color: Color32,
@@ -76,7 +81,7 @@ impl OldDevice {
             .collect();
         Device {
             pos: self.pos,
-            data: self.data,
+            data: self.data.update(),
             links,
             preset: String::from("unknown"),
         }
@@ -113,6 +118,107 @@ impl OldScene {
             .map(|(id, device)| (id, device.update()))
             .collect();
         Board {
+            meta: Default::default(),
+            rect: self.rect,
+            write_queue: self.write_queue,
+            inputs,
+            outputs,
+            devices,
+            input_groups: self.input_groups,
+            output_groups: self.output_groups,
+            sim_state: None,
+            sim_speed: None,
+            seed: None,
+            modified: false,
+        }
+    }
+}
+
+/// A link before it could have an explicit color
+#[derive(Deserialize)]
+pub struct OldLink {
+    pub target: LinkTarget<u64>,
+    pub anchors: Vec<Pos2>,
+    pub color: usize,
+}
+impl OldLink {
+    pub fn update(self) -> Link {
+        Link::new(self.target, self.color, self.anchors)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct OldBoardInput {
+    pub io: Io,
+    pub links: Vec<OldLink>,
+}
+
+#[derive(Deserialize)]
+pub struct OldBoardOutput {
+    pub io: Io,
+}
+
+#[derive(Deserialize)]
+pub struct OldBoardDevice {
+    pub pos: Pos2,
+    pub data: OldDeviceData,
+    pub links: Vec<Vec<OldLink>>,
+    pub preset: String,
+}
+
+/// A board before it had metadata, expected outputs, and link colors
+#[derive(Deserialize)]
+pub struct OldBoard {
+    pub rect: Rect,
+    pub write_queue: WriteQueue<u64>,
+
+    pub inputs: HashMap<u64, OldBoardInput>,
+    pub outputs: HashMap<u64, OldBoardOutput>,
+    pub devices: HashMap<u64, OldBoardDevice>,
+
+    pub input_groups: HashMap<u64, Group>,
+    pub output_groups: HashMap<u64, Group>,
+}
+impl OldBoard {
+    pub fn update(self) -> Board {
+        let update_links =
+            |links: Vec<OldLink>| -> Vec<Link> { links.into_iter().map(OldLink::update).collect() };
+        let inputs = self
+            .inputs
+            .into_iter()
+            .map(|(id, input)| {
+                let links = update_links(input.links);
+                (
+                    id,
+                    Input {
+                        io: input.io,
+                        links,
+                        force: None,
+                        clock: None,
+                    },
+                )
+            })
+            .collect();
+        let outputs = self
+            .outputs
+            .into_iter()
+            .map(|(id, output)| (id, Output::new(output.io)))
+            .collect();
+        let devices = self
+            .devices
+            .into_iter()
+            .map(|(id, device)| {
+                let device = Device {
+                    pos: device.pos,
+                    data: device.data.update(),
+                    links: device.links.into_iter().map(update_links).collect(),
+                    preset: device.preset,
+                };
+                (id, device)
+            })
+            .collect();
+        Board {
+            meta: Default::default(),
             rect: self.rect,
             write_queue: self.write_queue,
             inputs,
@@ -120,6 +226,48 @@ impl OldScene {
             devices,
             input_groups: self.input_groups,
             output_groups: self.output_groups,
+            sim_state: None,
+            sim_speed: None,
+            seed: None,
+            modified: false,
+        }
+    }
+}
+
+/// A preset whose source board is an `OldBoard`
+#[derive(Deserialize)]
+pub struct OldBoardPreset {
+    pub name: String,
+    pub cat: String,
+    pub color: [u8; 4],
+    pub data: PresetData,
+    pub src: OldBoardPresetSource,
+}
+impl OldBoardPreset {
+    pub fn update(self) -> DevicePreset {
+        DevicePreset {
+            name: self.name,
+            cat: self.cat,
+            color: self.color,
+            data: self.data,
+            src: self.src.update(),
+            glyph: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub enum OldBoardPresetSource {
+    Default,
+    Builtin,
+    Board(Box<OldBoard>),
+}
+impl OldBoardPresetSource {
+    pub fn update(self) -> PresetSource {
+        match self {
+            Self::Default => PresetSource::Default,
+            Self::Builtin => PresetSource::Builtin,
+            Self::Board(board) => PresetSource::Board(board.update()),
         }
     }
 }
@@ -141,6 +289,7 @@ impl OldDevicePreset {
             color: self.color,
             data: self.data,
             src: self.src.update(),
+            glyph: None,
         }
     }
 }
@@ -159,3 +308,81 @@ impl OldPresetSource {
         }
     }
 }
+
+/// A bit field before it could be wider than 64 bits
+#[derive(Deserialize)]
+pub struct OldBitField {
+    pub data: u64,
+    pub len: usize,
+}
+impl OldBitField {
+    pub fn update(self) -> BitField {
+        BitField::narrow(self.len.min(BitField::MAX_LEN), self.data)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct OldCombGate {
+    pub input: OldBitField,
+    pub output: OldBitField,
+    pub table: TruthTable,
+}
+impl OldCombGate {
+    pub fn update(self) -> CombGate {
+        CombGate {
+            input: self.input.update(),
+            output: self.output.update(),
+            table: self.table,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct OldChipDevice {
+    pub links: Vec<Vec<LinkTarget<usize>>>,
+    pub data: OldCombGate,
+}
+
+#[derive(Deserialize)]
+pub struct OldChip {
+    pub write_queue: WriteQueue<usize>,
+    pub input: OldBitField,
+    pub output: OldBitField,
+    pub input_links: Vec<Vec<DeviceInput<usize>>>,
+    pub devices: Vec<OldChipDevice>,
+}
+impl OldChip {
+    pub fn update(self) -> Chip {
+        let devices = self
+            .devices
+            .into_iter()
+            .map(|device| ChipDevice {
+                links: device.links,
+                data: device.data.update(),
+            })
+            .collect();
+        Chip {
+            write_queue: self.write_queue,
+            input: self.input.update(),
+            output: self.output.update(),
+            input_links: self.input_links,
+            devices,
+            busy_updates: 0,
+        }
+    }
+}
+
+/// The state of a device with `OldBitField`s
+#[derive(Deserialize)]
+pub enum OldDeviceData {
+    CombGate(OldCombGate),
+    Chip(OldChip),
+}
+impl OldDeviceData {
+    pub fn update(self) -> DeviceData {
+        match self {
+            Self::CombGate(comb_gate) => DeviceData::CombGate(comb_gate.update()),
+            Self::Chip(chip) => DeviceData::Chip(chip.update()),
+        }
+    }
+}
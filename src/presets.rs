@@ -1,53 +1,79 @@
 pub mod chip;
 
-use crate::board::Board;
+use crate::board::{Board, CombGate, DeviceData, Unsettled};
 use crate::{BitField, TruthTable};
 pub use chip::ChipPreset;
+use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Why a board can't be packed as a combinational gate
+#[derive(Debug, Clone, PartialEq)]
+pub enum PackErr {
+    Invalid(&'static str),
+    /// It didn't settle with one of the input combinations
+    Unsettled(Unsettled),
+}
+impl From<&'static str> for PackErr {
+    fn from(msg: &'static str) -> Self {
+        Self::Invalid(msg)
+    }
+}
+impl From<Unsettled> for PackErr {
+    fn from(unsettled: Unsettled) -> Self {
+        Self::Unsettled(unsettled)
+    }
+}
+impl std::fmt::Display for PackErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Invalid(msg) => f.write_str(msg),
+            Self::Unsettled(unsettled) => write!(
+                f,
+                "Has a loop or is too big, {} devices kept changing",
+                unsettled.devices.len()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct CombGatePreset {
     pub inputs: Vec<String>,
     pub outputs: Vec<String>,
     pub table: TruthTable,
 }
 impl CombGatePreset {
-    pub fn from_board(board: &mut Board) -> Result<Self, &'static str> {
+    /// Every input doubles the combinations to check, 16 inputs is already 65536 of them
+    pub const DEFAULT_MAX_INPUTS: usize = 16;
+
+    pub fn from_board(board: &mut Board, max_inputs: usize) -> Result<Self, PackErr> {
         let original_board = board.clone();
 
-        if board.inputs.len() > 64 {
-            return Err("Too many inputs (max is 64)");
-        }
-        if board.outputs.len() > 64 {
-            return Err("Too many outputs (max is 64)");
+        if board.inputs.len() > max_inputs {
+            let msg = "Too many inputs to check every combination, pack it as a chip instead";
+            return Err(PackErr::Invalid(msg));
         }
 
         // create truth table from board
         let num_inputs = board.inputs.len();
         let num_outputs = board.outputs.len();
         let total_states: u64 = 1 << num_inputs;
+        let Some(map_len) = TruthTable::map_len(num_inputs, num_outputs) else {
+            let msg = "Too many inputs and outputs for a truth table, pack it as a chip instead";
+            return Err(PackErr::Invalid(msg));
+        };
 
         let inputs = board.inputs_sorted();
         let outputs = board.outputs_sorted();
 
-        let mut output_states = Vec::with_capacity(total_states as usize);
+        let mut output_states = Vec::with_capacity(map_len);
         let mut input_state: u64 = 0;
         while input_state < total_states {
-            // set inputs
-            for i in 0..num_inputs {
-                let state = ((input_state >> i as u64) & 1) == 1;
-                board.set_input(inputs[i], state);
-            }
-
-            // execute queued writes
-            let mut total_updates = 0;
-            while board.write_queue.len() > 0 {
-                if total_updates > 1000 {
-                    return Err("Has a loop or is too big");
-                }
-                board.update();
-                total_updates += 1;
-            }
+            let states: Vec<_> = (0..num_inputs)
+                .map(|i| (inputs[i], ((input_state >> i as u64) & 1) == 1))
+                .collect();
+            board.settle_inputs(&states, 1000)?;
 
             // store output
             let mut output = BitField::empty(num_outputs);
@@ -55,7 +81,7 @@ impl CombGatePreset {
                 let state = board.outputs.get(&outputs[i]).unwrap().io.state;
                 output.set(i, state);
             }
-            output_states.push(output.data);
+            output_states.extend(output.words());
 
             input_state += 1;
         }
@@ -75,16 +101,73 @@ impl CombGatePreset {
             table: TruthTable {
                 num_inputs,
                 num_outputs,
-                map: output_states,
+                map: output_states.into(),
             },
         })
     }
+
+    /// A header of the input then output names, and a row of 0/1 cells for every input combination
+    pub fn to_csv(&self) -> String {
+        let name = |names: &[String], idx: usize, prefix: &str| match names[idx].trim() {
+            "" => format!("{prefix}{idx}"),
+            name => name.replace(',', " "),
+        };
+        let mut header: Vec<String> = (0..self.inputs.len())
+            .map(|idx| name(&self.inputs, idx, "in"))
+            .collect();
+        header.extend((0..self.outputs.len()).map(|idx| name(&self.outputs, idx, "out")));
+
+        let mut csv = header.join(",");
+        csv.push('\n');
+        for (input, output) in self.table.rows().enumerate() {
+            let input_cells = (0..self.table.num_inputs).map(|idx| (input as u64 >> idx) & 1);
+            let output_cells = (0..self.table.num_outputs).map(|idx| output.get(idx) as u64);
+            let row: Vec<String> = (input_cells.chain(output_cells))
+                .map(|bit| format!("{bit}"))
+                .collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub enum BuiltinPreset {}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BuiltinPreset {
+    /// No inputs, and one output that's always on
+    ConstOn,
+    /// No inputs, and one output that's always off
+    ConstOff,
+}
+impl BuiltinPreset {
+    pub fn input_names(&self) -> &[String] {
+        match self {
+            Self::ConstOn | Self::ConstOff => &[],
+        }
+    }
+    pub fn output_names(&self) -> &[String] {
+        const ONE_OUTPUT: &[String] = &[String::new()];
+        match self {
+            Self::ConstOn | Self::ConstOff => ONE_OUTPUT,
+        }
+    }
+    /// The logic of the builtin as a truth table
+    pub fn table(&self) -> TruthTable {
+        let state = matches!(self, Self::ConstOn);
+        TruthTable {
+            num_inputs: 0,
+            num_outputs: 1,
+            map: Arc::new([state as u64]),
+        }
+    }
+    /// The device placed for the builtin, simulated like the other devices
+    pub fn device_data(&self) -> DeviceData {
+        // a gate without inputs never changes its output
+        DeviceData::CombGate(CombGate::new(self.table()))
+    }
+}
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum PresetData {
     CombGate(CombGatePreset),
     Chip(ChipPreset),
@@ -95,14 +178,14 @@ impl PresetData {
         match self {
             Self::CombGate(e) => e.inputs.len(),
             Self::Chip(e) => e.inputs.len(),
-            Self::Builtin(_) => todo!(),
+            Self::Builtin(e) => e.input_names().len(),
         }
     }
     pub fn num_outputs(&self) -> usize {
         match self {
             Self::CombGate(e) => e.outputs.len(),
             Self::Chip(e) => e.outputs.len(),
-            Self::Builtin(_) => todo!(),
+            Self::Builtin(e) => e.output_names().len(),
         }
     }
 
@@ -110,32 +193,57 @@ impl PresetData {
         match self {
             Self::CombGate(e) => &e.inputs,
             Self::Chip(e) => &e.inputs,
-            Self::Builtin(_) => todo!(),
+            Self::Builtin(e) => e.input_names(),
         }
     }
     pub fn output_names(&self) -> &[String] {
         match self {
             Self::CombGate(e) => &e.outputs,
             Self::Chip(e) => &e.outputs,
-            Self::Builtin(_) => todo!(),
+            Self::Builtin(e) => e.output_names(),
         }
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum PresetSource {
     Default,
     Builtin,
     Board(Board),
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct DevicePreset {
     pub name: String,
     pub cat: String,
     pub color: [u8; 4],
     pub data: PresetData,
     pub src: PresetSource,
+    /// A short symbol (like "&" or "=1") shown instead of the name when the device is too small to read it
+    #[serde(default)]
+    pub glyph: Option<String>,
+}
+impl DevicePreset {
+    /// Stands in for a preset that isn't in the library, so devices using it can still be drawn
+    pub fn missing(name: &str, num_inputs: usize, num_outputs: usize) -> Self {
+        Self {
+            name: String::from(name),
+            cat: String::from("Missing"),
+            color: [90, 90, 90, 255],
+            data: PresetData::CombGate(CombGatePreset {
+                inputs: vec![String::new(); num_inputs],
+                outputs: vec![String::new(); num_outputs],
+                // only drawn, never simulated
+                table: TruthTable {
+                    num_inputs,
+                    num_outputs,
+                    map: Arc::from([]),
+                },
+            }),
+            src: PresetSource::Default,
+            glyph: Some(String::from("?")),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -163,11 +271,18 @@ impl Library {
         }
     }
 
+    /// If presets were changed since they were last saved
+    pub fn has_changes(&self) -> bool {
+        !self.changes.is_empty()
+    }
     pub fn consume_changes(&mut self) -> Vec<(String, Change)> {
         let mut new = Vec::new();
         std::mem::swap(&mut self.changes, &mut new);
         new
     }
+    pub fn presets(&self) -> &[DevicePreset] {
+        &self.presets
+    }
     pub fn preset_names(&self) -> Vec<String> {
         self.presets
             .iter()
@@ -194,6 +309,30 @@ impl Library {
             self.changes.push((name, change));
         }
     }
+    /// Renames the pins of a preset, and the IO of the board it was packed from
+    pub fn set_pin_names(&mut self, name: &str, inputs: Vec<String>, outputs: Vec<String>) {
+        let Some(idx) = self.get_preset_idx(name) else { return };
+        let preset = &mut self.presets[idx];
+        let (old_inputs, old_outputs) = match &mut preset.data {
+            PresetData::Chip(chip) => (&mut chip.inputs, &mut chip.outputs),
+            PresetData::CombGate(comb_gate) => (&mut comb_gate.inputs, &mut comb_gate.outputs),
+            PresetData::Builtin(_) => return,
+        };
+        if old_inputs.len() != inputs.len() || old_outputs.len() != outputs.len() {
+            return;
+        }
+        if let PresetSource::Board(board) = &mut preset.src {
+            for (id, name) in board.inputs_sorted().into_iter().zip(&inputs) {
+                board.inputs.get_mut(&id).unwrap().io.name = name.clone();
+            }
+            for (id, name) in board.outputs_sorted().into_iter().zip(&outputs) {
+                board.outputs.get_mut(&id).unwrap().io.name = name.clone();
+            }
+        }
+        *old_inputs = inputs;
+        *old_outputs = outputs;
+        self.changes.push((name.to_owned(), Change::Modified));
+    }
     pub fn remove_preset(&mut self, name: &str) {
         let idx = self.get_preset_idx(name).unwrap();
         self.presets.remove(idx);
@@ -213,6 +352,45 @@ impl Library {
             .find(|preset| preset.name.as_str() == name)
     }
 
+    /// The combinational presets with the same logic as `table`
+    pub fn find_equivalent(&self, table: &TruthTable) -> Vec<&DevicePreset> {
+        self.presets
+            .iter()
+            .filter(|preset| match &preset.data {
+                PresetData::CombGate(comb_gate) => comb_gate.table.same_logic(table),
+                PresetData::Builtin(builtin) => builtin.table().same_logic(table),
+                _ => false,
+            })
+            .collect()
+    }
+    /// Groups of combinational presets that have the same logic as each other
+    pub fn find_duplicates(&self) -> Vec<Vec<String>> {
+        let mut groups: Vec<(&TruthTable, Vec<String>)> = Vec::new();
+        let mut keys: HashMap<u64, Vec<usize>> = HashMap::new();
+        for preset in &self.presets {
+            let PresetData::CombGate(comb_gate) = &preset.data else { continue };
+            let table = &comb_gate.table;
+
+            // the key can collide, so still compare the tables
+            let group_idxs = keys.entry(table.canonical_key()).or_default();
+            match group_idxs
+                .iter()
+                .find(|idx| groups[**idx].0.same_logic(table))
+            {
+                Some(idx) => groups[*idx].1.push(preset.name.clone()),
+                None => {
+                    group_idxs.push(groups.len());
+                    groups.push((table, vec![preset.name.clone()]));
+                }
+            }
+        }
+        groups
+            .into_iter()
+            .map(|(_, names)| names)
+            .filter(|names| names.len() > 1)
+            .collect()
+    }
+
     pub fn cats_sorted(&self) -> Vec<(&str, Vec<&DevicePreset>)> {
         let mut cats: Vec<(&str, Vec<&DevicePreset>)> = Vec::new();
         for preset in &self.presets {
@@ -304,7 +482,22 @@ pub fn str_match_level(s: &str, q: &str) -> u8 {
     }
 }
 
-fn default_presets() -> [DevicePreset; 2] {
+/// The default presets without inputs, that always output on or off
+pub const HIGH_PRESET: &str = "High";
+pub const LOW_PRESET: &str = "Low";
+
+fn constant_preset(name: &str, glyph: &str, builtin: BuiltinPreset) -> DevicePreset {
+    DevicePreset {
+        name: String::from(name),
+        cat: String::from("Basic"),
+        color: [255, 255, 0, 255],
+        data: PresetData::Builtin(builtin),
+        src: PresetSource::Builtin,
+        glyph: Some(String::from(glyph)),
+    }
+}
+
+fn default_presets() -> [DevicePreset; 4] {
     [
         DevicePreset {
             name: String::from("And"),
@@ -316,10 +509,11 @@ fn default_presets() -> [DevicePreset; 2] {
                 table: TruthTable {
                     num_inputs: 2,
                     num_outputs: 1,
-                    map: vec![0, 0, 0, 1],
+                    map: Arc::new([0, 0, 0, 1]),
                 },
             }),
             src: PresetSource::Default,
+            glyph: Some(String::from("&")),
         },
         DevicePreset {
             name: String::from("Not"),
@@ -331,10 +525,13 @@ fn default_presets() -> [DevicePreset; 2] {
                 table: TruthTable {
                     num_inputs: 1,
                     num_outputs: 1,
-                    map: vec![1, 0],
+                    map: Arc::new([1, 0]),
                 },
             }),
             src: PresetSource::Default,
+            glyph: Some(String::from("1")),
         },
+        constant_preset(HIGH_PRESET, "1", BuiltinPreset::ConstOn),
+        constant_preset(LOW_PRESET, "0", BuiltinPreset::ConstOff),
     ]
 }
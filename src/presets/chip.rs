@@ -1,13 +1,13 @@
 use crate::*;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct CombGate {
     pub table: TruthTable,
     pub links: Vec<Vec<LinkTarget<usize>>>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct ChipPreset {
     pub inputs: Vec<String>,
     pub outputs: Vec<String>,
@@ -26,6 +26,36 @@ impl ChipPreset {
             comb_gates: step2.comb_gates,
         }
     }
+
+    /// If no gate feeds back into itself, so the outputs only depend on the current inputs
+    /// and the chip could be packed as a truth table instead
+    pub fn is_combinational(&self) -> bool {
+        // remove gates without incoming links until none are left (or only loops are)
+        let mut incoming = vec![0; self.comb_gates.len()];
+        for gate in &self.comb_gates {
+            for target in gate.links.iter().flatten() {
+                if let LinkTarget::DeviceInput(next, _) = *target {
+                    incoming[next] += 1;
+                }
+            }
+        }
+        let mut ready: Vec<usize> = (0..incoming.len())
+            .filter(|idx| incoming[*idx] == 0)
+            .collect();
+        let mut removed = 0;
+        while let Some(idx) = ready.pop() {
+            removed += 1;
+            for target in self.comb_gates[idx].links.iter().flatten() {
+                if let LinkTarget::DeviceInput(next, _) = *target {
+                    incoming[next] -= 1;
+                    if incoming[next] == 0 {
+                        ready.push(next);
+                    }
+                }
+            }
+        }
+        removed == self.comb_gates.len()
+    }
 }
 
 pub fn map_links(links: &[Link]) -> Vec<LinkTarget<u64>> {
@@ -0,0 +1,115 @@
+use egui::*;
+use std::collections::VecDeque;
+
+/// `Instant` panics on the web, so timings there are always 0
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Copy)]
+pub struct Timer(std::time::Instant);
+#[cfg(not(target_arch = "wasm32"))]
+impl Timer {
+    pub fn start() -> Self {
+        Self(std::time::Instant::now())
+    }
+    /// In milliseconds
+    pub fn elapsed(self) -> f32 {
+        self.0.elapsed().as_secs_f32() * 1000.0
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[derive(Clone, Copy)]
+pub struct Timer;
+#[cfg(target_arch = "wasm32")]
+impl Timer {
+    pub fn start() -> Self {
+        Self
+    }
+    pub fn elapsed(self) -> f32 {
+        0.0
+    }
+}
+
+/// Timings are in milliseconds
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FrameStats {
+    pub frame_time: f32,
+    pub sim_time: f32,
+    pub graphics_time: f32,
+    pub shapes: usize,
+    /// Devices and links that were off screen, so no shapes were made for them
+    pub culled: usize,
+    pub devices: usize,
+    pub links: usize,
+}
+
+pub struct Profiler {
+    pub frames: VecDeque<FrameStats>,
+    pub current: FrameStats,
+}
+impl Profiler {
+    pub const MAX_FRAMES: usize = 120;
+
+    pub fn new() -> Self {
+        Self {
+            frames: VecDeque::with_capacity(Self::MAX_FRAMES),
+            current: FrameStats::default(),
+        }
+    }
+
+    /// Saves the stats of the current frame
+    pub fn finish_frame(&mut self) {
+        if self.frames.len() == Self::MAX_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(self.current);
+        self.current = FrameStats::default();
+    }
+
+    pub fn show(&self, ui: &mut Ui) {
+        let Some(last) = self.frames.back() else { return };
+        ui.style_mut().wrap = Some(false);
+        ui.label(format!("frame: {:.2}ms", last.frame_time));
+        ui.colored_label(Color32::LIGHT_BLUE, format!("sim: {:.2}ms", last.sim_time));
+        ui.colored_label(
+            Color32::LIGHT_GREEN,
+            format!("graphics: {:.2}ms", last.graphics_time),
+        );
+        ui.label(format!("shapes: {}, culled: {}", last.shapes, last.culled));
+        ui.label(format!("devices: {}, links: {}", last.devices, last.links));
+        if cfg!(target_arch = "wasm32") {
+            ui.small("sim and graphics timings aren't available on the web");
+        }
+
+        // --- Show graph of sim + graphics time ---
+        let (rect, _) = ui.allocate_exact_size(vec2(240.0, 60.0), Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::from_gray(30));
+
+        let max_time = self
+            .frames
+            .iter()
+            .map(|frame| frame.sim_time + frame.graphics_time)
+            .fold(1.0, f32::max);
+        let bar_w = rect.width() / Self::MAX_FRAMES as f32;
+        for (idx, frame) in self.frames.iter().enumerate() {
+            let x = rect.min.x + idx as f32 * bar_w;
+            let sim_h = frame.sim_time / max_time * rect.height();
+            let graphics_h = frame.graphics_time / max_time * rect.height();
+
+            let sim_rect =
+                Rect::from_min_max(pos2(x, rect.max.y - sim_h), pos2(x + bar_w, rect.max.y));
+            let graphics_rect = Rect::from_min_max(
+                pos2(x, sim_rect.min.y - graphics_h),
+                pos2(x + bar_w, sim_rect.min.y),
+            );
+            painter.rect_filled(sim_rect, 0.0, Color32::LIGHT_BLUE);
+            painter.rect_filled(graphics_rect, 0.0, Color32::LIGHT_GREEN);
+        }
+        ui.small(format!("max: {:.2}ms", max_time));
+    }
+}
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,6 +1,36 @@
-use egui::{Color32, FontId, Rounding, Style, Visuals};
+use crate::board::{Board, WriteQueue};
+use crate::presets::CombGatePreset;
+use egui::{Color32, FontId, Rounding, Style, Vec2, Visuals};
+use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
 
+/// How long writes take, see `Settings::write_delay`
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum WriteDelay {
+    /// Every write arrives the next update, the timing is always the same
+    Zero,
+    /// Every write takes this many extra updates
+    Fixed(u8),
+    /// A random delay between `min` and `max` (inclusive), which exaggerates races and glitches
+    Random { min: u8, max: u8 },
+}
+impl Default for WriteDelay {
+    fn default() -> Self {
+        let [min, max] = WriteQueue::<u64>::DEFAULT_DELAYS;
+        Self::Random { min, max }
+    }
+}
+impl WriteDelay {
+    /// The smallest and largest delay, see `WriteQueue::delays`
+    pub fn range(self) -> [u8; 2] {
+        match self {
+            Self::Zero => [0, 0],
+            Self::Fixed(delay) => [delay, delay],
+            Self::Random { min, max } => [min, max.max(min)],
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum Theme {
@@ -31,6 +61,77 @@ impl Theme {
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum PinShape {
+    Circle = 0,
+    Square = 1,
+    Triangle = 2,
+}
+
+/// The colors of pins and links
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum Palette {
+    /// Red on gray, using `pin_colors` and `link_colors`
+    Default = 0,
+    /// Orange on dark blue, which are also apart in brightness,
+    /// for red-green colorblindness
+    Colorblind = 1,
+}
+
+pub const COLORBLIND_PIN_COLORS: [Color32; 2] = [
+    Color32::from_rgb(30, 70, 130),
+    Color32::from_rgb(255, 170, 0),
+];
+pub const COLORBLIND_LINK_COLORS: [Color32; 2] = [
+    Color32::from_rgb(25, 55, 100),
+    Color32::from_rgb(230, 150, 0),
+];
+
+/// How the members of stacked inputs/outputs are named, from the name of the first member
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum IoNaming {
+    /// New members have no name
+    None = 0,
+    /// "name", "name1", "name2", ...
+    Numeric = 1,
+    /// "name[0]", "name[1]", "name[2]", ...
+    Bus = 2,
+}
+impl IoNaming {
+    /// The name the first member should have once `name` is stacked
+    pub fn first_name(self, name: &str) -> String {
+        match self {
+            Self::Bus if !name.trim().is_empty() => format!("{}[0]", self.base_name(name)),
+            _ => String::from(name),
+        }
+    }
+    /// The name of the member at `idx`, from the name of the first member
+    pub fn member_name(self, first: &str, idx: usize) -> String {
+        let base = self.base_name(first);
+        if base.trim().is_empty() {
+            return String::new();
+        }
+        match self {
+            Self::None => String::new(),
+            // so "in1" doesn't become "in11"
+            Self::Numeric if base.ends_with(|c: char| c.is_ascii_digit()) => {
+                format!("{base}_{idx}")
+            }
+            Self::Numeric => format!("{base}{idx}"),
+            Self::Bus => format!("{base}[{idx}]"),
+        }
+    }
+    fn base_name(self, first: &str) -> &str {
+        match self {
+            Self::Bus => first.strip_suffix("[0]").unwrap_or(first),
+            _ => first,
+        }
+    }
+}
+
 pub fn dark_mode_visuals() -> Visuals {
     let mut vis = Visuals::dark();
     vis.widgets.inactive.fg_stroke.color = Color32::WHITE;
@@ -60,12 +161,53 @@ pub fn dark_mode_visuals() -> Visuals {
     vis
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     // App
     pub theme: Theme,
     pub colorful_wires: bool,
     pub auto_link: bool,
+    /// If false, only redraw when something changes
+    pub continuous_redraw: bool,
+    /// If the menus are shown as floating windows instead of side panels
+    pub float_library_menu: bool,
+    pub float_pack_menu: bool,
+    pub float_sim_menu: bool,
+    pub stack_io_naming: IoNaming,
+    /// Draw a stripe on devices, colored by the category of their preset
+    pub category_stripes: bool,
+    /// Show the glyph of a preset instead of its name when the name would be too small to read
+    pub device_glyphs: bool,
+    pub palette: Palette,
+    /// Draw links that are on wider, so the state doesn't only show by color
+    pub wide_on_links: bool,
+    /// Write the 1/0 state in the IO bulbs, so it doesn't only show by color
+    pub io_bulb_values: bool,
+    /// Presets pinned to the top panel for quick placing
+    pub favorite_presets: Vec<String>,
+    /// Save every 30 seconds and on exit (native only)
+    pub autosave: bool,
+    /// Boards with more inputs can't be packed as combinational gates
+    pub max_comb_inputs: usize,
+    /// Clicking a device input that has a link to it toggles it, until the link writes to it again
+    pub toggle_linked_inputs: bool,
+    /// How close to the edge of the board panel (in points) dragging or linking pans the view, 0 turns it off
+    pub edge_scroll_margin: f32,
+    /// How fast the view pans right at the edge, in points per second
+    pub edge_scroll_speed: f32,
+    /// The grid that new link anchors snap to when they don't line up with anything, 0 turns it off
+    pub anchor_grid: f32,
+    /// How far (in points) the pointer can move between pressing and releasing for it to still be a click
+    pub click_tolerance: f32,
+    /// The delay (in updates) added to every write
+    pub write_delay: WriteDelay,
+    /// If exported images use `BoardStyle::PRINT` instead of the colors of the editor
+    pub print_export: bool,
+    /// The category and color of the last packed chip, to start the pack menu with
+    pub last_pack: Option<(String, Color32)>,
+    /// The color that new chips in a category start with
+    pub cat_colors: HashMap<String, Color32>,
 
     // Debug
     pub debug: bool,
@@ -75,6 +217,8 @@ pub struct Settings {
     pub board_io_pin_size: f32,
     pub board_io_col_color: Color32,
     pub board_io_col_w: f32,
+    /// The size of new and cleared boards
+    pub default_board_size: Vec2,
 
     pub pin_colors: [Color32; 2],
     pub link_width: f32,
@@ -83,6 +227,13 @@ pub struct Settings {
     pub device_name_size: f32,
     pub device_pin_size: f32,
     pub device_min_pin_spacing: f32,
+    pub device_rounding: f32,
+    pub input_pin_shape: PinShape,
+    pub output_pin_shape: PinShape,
+
+    /// If the settings were changed since they were last saved
+    #[serde(skip)]
+    pub modified: bool,
 }
 impl Default for Settings {
     fn default() -> Self {
@@ -91,6 +242,28 @@ impl Default for Settings {
             theme: Theme::Dark,
             colorful_wires: false,
             auto_link: false,
+            continuous_redraw: true,
+            float_library_menu: false,
+            float_pack_menu: false,
+            float_sim_menu: false,
+            stack_io_naming: IoNaming::Numeric,
+            category_stripes: false,
+            device_glyphs: true,
+            palette: Palette::Default,
+            wide_on_links: false,
+            io_bulb_values: false,
+            favorite_presets: vec![String::from("And"), String::from("Not")],
+            autosave: true,
+            max_comb_inputs: CombGatePreset::DEFAULT_MAX_INPUTS,
+            toggle_linked_inputs: false,
+            edge_scroll_margin: 30.0,
+            edge_scroll_speed: 600.0,
+            anchor_grid: 0.0,
+            click_tolerance: 4.0,
+            write_delay: WriteDelay::default(),
+            print_export: false,
+            last_pack: None,
+            cat_colors: HashMap::new(),
 
             // Debug
             debug: false,
@@ -100,6 +273,7 @@ impl Default for Settings {
             board_io_col_color: Color32::from_rgb(180, 180, 180),
             board_io_pin_size: 8.0,
             board_io_col_w: 40.0,
+            default_board_size: Board::DEFAULT_SIZE,
 
             pin_colors: [Color32::from_gray(100), Color32::from_rgb(255, 0, 0)],
             link_width: 4.0,
@@ -108,16 +282,34 @@ impl Default for Settings {
             device_name_size: 16.0,
             device_pin_size: 6.0,
             device_min_pin_spacing: 13.0,
+            device_rounding: 5.0,
+            input_pin_shape: PinShape::Circle,
+            output_pin_shape: PinShape::Circle,
+
+            modified: false,
         }
     }
 }
 impl Settings {
     #[inline(always)]
     pub fn pin_color(&self, state: bool) -> Color32 {
-        self.pin_colors[state as usize]
+        match self.palette {
+            Palette::Default => self.pin_colors[state as usize],
+            Palette::Colorblind => COLORBLIND_PIN_COLORS[state as usize],
+        }
     }
     #[inline(always)]
     pub fn link_color(&self, state: bool) -> Color32 {
-        self.link_colors[state as usize]
+        match self.palette {
+            Palette::Default => self.link_colors[state as usize],
+            Palette::Colorblind => COLORBLIND_LINK_COLORS[state as usize],
+        }
+    }
+    #[inline(always)]
+    pub fn link_width(&self, state: bool) -> f32 {
+        match self.wide_on_links && state {
+            true => self.link_width * 1.75,
+            false => self.link_width,
+        }
     }
 }
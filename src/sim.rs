@@ -0,0 +1,74 @@
+use crate::board::Board;
+
+/// Runs a `Board` without an `App` or any rendering context.
+/// Useful for embedding the simulator in tests or servers.
+pub struct Simulation {
+    pub board: Board,
+}
+impl Simulation {
+    pub fn new(board: Board) -> Self {
+        Self { board }
+    }
+
+    pub fn set_input(&mut self, id: u64, state: bool) {
+        self.board.set_input(id, state);
+    }
+
+    pub fn tick(&mut self) {
+        self.board.update();
+    }
+
+    /// Panics if there is no output with the given ID
+    pub fn output(&self, id: u64) -> bool {
+        self.board.outputs.get(&id).unwrap().io.state
+    }
+
+    /// Ticks the board until there are no more pending writes.
+    /// Returns the number of ticks it took, or `None` if the board didn't settle within `max_ticks`.
+    pub fn run_until_settled(&mut self, max_ticks: usize) -> Option<usize> {
+        for ticks in 0..max_ticks {
+            if self.board.is_settled() {
+                return Some(ticks);
+            }
+            self.board.update();
+        }
+        self.board.is_settled().then_some(max_ticks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Device;
+    use crate::presets::Library;
+    use crate::{Link, LinkStart, LinkTarget};
+    use egui::pos2;
+
+    #[test]
+    fn not_gate() {
+        let library = Library::new();
+        let mut board = Board::new();
+        board.add_input(50.0);
+        board.add_output(50.0);
+        let input = board.inputs_sorted()[0];
+        let output = board.outputs_sorted()[0];
+        let not = library.get_preset("Not").unwrap();
+        board.add_device(1, Device::from_preset(not, pos2(100.0, 40.0)));
+        let link = Link::new(LinkTarget::DeviceInput(1, 0), 0, Vec::new());
+        board.add_link(LinkStart::Input(input), link);
+        let link = Link::new(LinkTarget::Output(output), 0, Vec::new());
+        board.add_link(LinkStart::DeviceOutput(1, 0), link);
+
+        let mut sim = Simulation::new(board);
+        assert!(sim.run_until_settled(100).is_some());
+        assert!(sim.output(output));
+
+        sim.set_input(input, true);
+        sim.tick();
+        assert!(!sim.board.is_settled());
+        assert!(sim.run_until_settled(100).is_some());
+        assert!(!sim.output(output));
+        // a settled board takes no more ticks
+        assert_eq!(sim.run_until_settled(100), Some(0));
+    }
+}
@@ -0,0 +1,110 @@
+use egui::{Color32, Shape, Stroke, Vec2};
+
+/// Writes the shapes from `Graphics` as an SVG image of the given size.
+/// Meshes and curves are skipped, since the board graphics don't use them.
+pub fn shapes_to_svg(shapes: &[Shape], size: Vec2) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+        w = size.x.ceil(),
+        h = size.y.ceil(),
+    );
+    for shape in shapes {
+        write_shape(&mut svg, shape);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn write_shape(svg: &mut String, shape: &Shape) {
+    match shape {
+        Shape::Vec(shapes) => {
+            for shape in shapes {
+                write_shape(svg, shape);
+            }
+        }
+        Shape::Circle(circle) => svg.push_str(&format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {} {}/>\n",
+            circle.center.x,
+            circle.center.y,
+            circle.radius,
+            color_attrs("fill", circle.fill),
+            stroke_attrs(circle.stroke),
+        )),
+        Shape::LineSegment { points, stroke } => svg.push_str(&format!(
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke-linecap=\"round\" {}/>\n",
+            points[0].x,
+            points[0].y,
+            points[1].x,
+            points[1].y,
+            stroke_attrs(*stroke),
+        )),
+        Shape::Path(path) => {
+            let points: Vec<String> = path
+                .points
+                .iter()
+                .map(|point| format!("{},{}", point.x, point.y))
+                .collect();
+            let (tag, fill) = match path.closed {
+                true => ("polygon", color_attrs("fill", path.fill)),
+                false => ("polyline", String::from("fill=\"none\"")),
+            };
+            svg.push_str(&format!(
+                "<{tag} points=\"{}\" {fill} {}/>\n",
+                points.join(" "),
+                stroke_attrs(path.stroke),
+            ));
+        }
+        Shape::Rect(rect) => svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\" {} {}/>\n",
+            rect.rect.min.x,
+            rect.rect.min.y,
+            rect.rect.width(),
+            rect.rect.height(),
+            rect.rounding.nw,
+            color_attrs("fill", rect.fill),
+            stroke_attrs(rect.stroke),
+        )),
+        Shape::Text(text) => {
+            let job = &text.galley.job;
+            let Some(section) = job.sections.first() else { return };
+            let color = text.override_text_color.unwrap_or(section.format.color);
+            svg.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" dominant-baseline=\"text-before-edge\" {}>{}</text>\n",
+                text.pos.x,
+                text.pos.y,
+                section.format.font_id.size,
+                color_attrs("fill", color),
+                escape(&job.text),
+            ));
+        }
+        _ => {}
+    }
+}
+
+fn color_attrs(attr: &str, color: Color32) -> String {
+    if color.a() == 0 {
+        return format!("{attr}=\"none\"");
+    }
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    format!(
+        "{attr}=\"rgb({r},{g},{b})\" {attr}-opacity=\"{:.3}\"",
+        a as f32 / 255.0
+    )
+}
+
+fn stroke_attrs(stroke: Stroke) -> String {
+    if stroke.width <= 0.0 || stroke.color.a() == 0 {
+        return String::from("stroke=\"none\"");
+    }
+    format!(
+        "{} stroke-width=\"{}\"",
+        color_attrs("stroke", stroke.color),
+        stroke.width
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -1,14 +1,102 @@
-use crate::app::{App, AppAction, AppItem};
-use crate::board::{Board, BoardItem, DeviceData, IoSel};
-use crate::graphics::{Transform, View};
+use crate::app::{App, AppAction, AppItem, CreateLinks};
+use crate::board::{Board, BoardItem, BoardMeta, Device, DeviceData, IoSel, Period};
+use crate::graphics::{self, link_palette, Transform, View};
 use crate::input::Input;
-use crate::presets::{Library, PresetData, PresetSource};
+use crate::presets::{DevicePreset, Library, PresetData, PresetSource};
+use crate::settings::{Palette, Settings};
+use crate::{LinkTarget, TruthTable};
 use egui::*;
+use hashbrown::HashMap;
+
+/// Shows a menu in a side panel, or in a floating window if `floating` is true.
+/// The menu has a button to switch between the two.
+pub fn show_menu(
+    ctx: &Context,
+    id: &'static str,
+    side: panel::Side,
+    floating: &mut bool,
+    add_contents: impl FnOnce(&mut Ui),
+) {
+    let contents = |ui: &mut Ui, floating: &mut bool| {
+        ui.horizontal(|ui| {
+            let label = match *floating {
+                true => "dock",
+                false => "float",
+            };
+            if ui.small_button(label).clicked() {
+                *floating = !*floating;
+            }
+        });
+        add_contents(ui);
+    };
+    match *floating {
+        true => {
+            Window::new(id)
+                .title_bar(false)
+                .show(ctx, |ui| contents(ui, floating));
+        }
+        false => {
+            SidePanel::new(side, id).show(ctx, |ui| contents(ui, floating));
+        }
+    }
+}
 
 #[derive(Default, Clone)]
 pub struct LibraryMenu {
     pub open: bool,
     pub sel: Option<String>,
+    /// Groups of presets with the same logic, from the last time we checked
+    pub duplicates: Option<Vec<Vec<String>>>,
+    /// Thumbnails of the presets that were selected, by preset name
+    pub thumbnails: HashMap<String, Thumbnail>,
+    /// The pin names being edited, and the preset they're from
+    pub pin_names: Option<(String, Vec<String>, Vec<String>)>,
+}
+
+/// A preset drawn once, so it isn't drawn again every frame
+#[derive(Clone)]
+pub struct Thumbnail {
+    /// The color and pin counts of the preset, to redraw it if they changed
+    key: ([u8; 4], usize, usize),
+    shapes: Vec<Shape>,
+    size: Vec2,
+}
+impl Thumbnail {
+    const MAX_SIZE: Vec2 = vec2(200.0, 80.0);
+
+    fn key(preset: &DevicePreset) -> ([u8; 4], usize, usize) {
+        let data = &preset.data;
+        (preset.color, data.num_inputs(), data.num_outputs())
+    }
+
+    pub fn new(ctx: &Context, settings: &Settings, preset: &DevicePreset) -> Self {
+        let (shapes, size) = graphics::preset_thumbnail(ctx, settings, preset, Self::MAX_SIZE);
+        Self {
+            key: Self::key(preset),
+            shapes,
+            size,
+        }
+    }
+
+    pub fn show(&self, ui: &mut Ui) {
+        let (rect, _) = ui.allocate_exact_size(self.size, Sense::hover());
+        let painter = ui.painter_at(rect);
+        for shape in &self.shapes {
+            let mut shape = shape.clone();
+            shape.translate(rect.min.to_vec2());
+            painter.add(shape);
+        }
+    }
+}
+
+/// What to do with a chip right after it's packed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AfterPack {
+    Nothing,
+    /// Place one in the middle of the board
+    Place,
+    /// Hold it for placement, like picking it from the preset placer
+    Hold,
 }
 
 #[derive(Clone)]
@@ -19,6 +107,13 @@ pub struct PackMenu {
     pub cat: String,
     pub combinational: bool,
     pub err: Option<String>,
+    /// Library presets with the same logic as the board, from the last time we checked
+    pub equivalent: Option<Vec<String>>,
+    /// If the board settled with every input combination, from the last time we checked
+    pub settles: Option<bool>,
+    /// If the board is cleared after packing
+    pub clear_board: bool,
+    pub after_pack: AfterPack,
 }
 impl Default for PackMenu {
     fn default() -> Self {
@@ -29,6 +124,19 @@ impl Default for PackMenu {
             cat: format!("Basic"),
             combinational: false,
             err: None,
+            equivalent: None,
+            settles: None,
+            clear_board: false,
+            after_pack: AfterPack::Nothing,
+        }
+    }
+}
+impl PackMenu {
+    /// Uses the category and color of the last packed chip, if there is one
+    pub fn use_last_pack(&mut self, settings: &Settings) {
+        if let Some((cat, color)) = &settings.last_pack {
+            self.cat = cat.clone();
+            self.color = *color;
         }
     }
 }
@@ -39,6 +147,29 @@ pub struct SimMenu {
     pub speed: u32,
     pub paused: bool,
     pub view: View,
+    pub show_writes: bool,
+    pub show_profiler: bool,
+    pub show_unconnected: bool,
+    pub show_legend: bool,
+    /// How many ticks `Board::measure_period` runs for
+    pub period_ticks: usize,
+    pub period: Option<Period>,
+    /// If PageUp and PageDown count the board inputs up and down as one number
+    pub input_counter: bool,
+    /// Show the board outputs as one number instead of bulbs
+    pub outputs_as_number: bool,
+    /// Draw the board like it's exported with `Settings::print_export`
+    pub print_preview: bool,
+}
+impl SimMenu {
+    /// Takes the pause state and speed saved with the board, and pauses it if it's manual only
+    pub fn load_board(&mut self, board: &Board) {
+        if let Some(sim_speed) = board.sim_speed {
+            self.paused = sim_speed.paused;
+            self.speed = sim_speed.speed.max(1);
+        }
+        self.paused |= board.meta.manual_only;
+    }
 }
 impl Default for SimMenu {
     fn default() -> Self {
@@ -47,13 +178,22 @@ impl Default for SimMenu {
             view: View::default(),
             paused: false,
             speed: 1,
+            show_writes: false,
+            show_profiler: false,
+            show_unconnected: false,
+            show_legend: false,
+            period_ticks: 1000,
+            period: None,
+            input_counter: false,
+            outputs_as_number: false,
+            print_preview: false,
         }
     }
 }
 
 pub fn show_library_menu(
     ui: &mut Ui,
-    debug: bool,
+    settings: &Settings,
     menu: &mut LibraryMenu,
     native: bool,
     library: &Library,
@@ -68,9 +208,36 @@ pub fn show_library_menu(
         if ui.button("import").clicked() {
             action = AppAction::ImportLibrary;
         }
+        if native && ui.button("export").clicked() {
+            action = AppAction::ExportLibrary;
+        }
+        if ui.button("update devices").clicked() {
+            action = AppAction::RefreshDevices;
+        }
+        if ui.button("find duplicates").clicked() {
+            menu.duplicates = Some(library.find_duplicates());
+        }
     });
     ui.separator();
 
+    if let Some(duplicates) = &menu.duplicates {
+        let mut close = false;
+        ui.horizontal(|ui| {
+            ui.label("Presets with the same logic");
+            close = ui.small_button("x").clicked();
+        });
+        if duplicates.is_empty() {
+            ui.weak("none found");
+        }
+        for names in duplicates {
+            ui.label(format!("- {}", names.join(", ")));
+        }
+        if close {
+            menu.duplicates = None;
+        }
+        ui.separator();
+    }
+
     let sel_preset = menu.sel.clone().and_then(|name| {
         let preset = library.get_preset(&name);
         if preset.is_none() {
@@ -80,6 +247,14 @@ pub fn show_library_menu(
     });
     if let Some((name, preset)) = sel_preset {
         ui.heading(&name);
+        let thumbnails = &mut menu.thumbnails;
+        let key = Thumbnail::key(preset);
+        let outdated = !matches!(thumbnails.get(&name), Some(old) if old.key == key);
+        if outdated {
+            let thumbnail = Thumbnail::new(ui.ctx(), settings, preset);
+            thumbnails.insert(name.clone(), thumbnail);
+        }
+        thumbnails[&name].show(ui);
 
         let mut stat = |s: &str| {
             ui.horizontal(|ui| {
@@ -89,18 +264,33 @@ pub fn show_library_menu(
         };
         match &preset.data {
             PresetData::Chip(chip) => {
+                stat(match chip.is_combinational() {
+                    true => "combinational chip",
+                    false => "sequential chip (has feedback)",
+                });
                 stat(&format!("inputs: {}", chip.inputs.len()));
                 stat(&format!("outputs: {}", chip.outputs.len()));
             }
             PresetData::CombGate(comb_gate) => {
                 stat(&format!(
-                    "combinational ({} combinations)",
-                    comb_gate.table.map.len()
+                    "table gate ({} combinations)",
+                    comb_gate.table.num_rows()
                 ));
                 stat(&format!("inputs: {}", comb_gate.inputs.len()));
                 stat(&format!("outputs: {}", comb_gate.outputs.len()));
+                if comb_gate.inputs.is_empty() {
+                    stat("no inputs, so its outputs never change");
+                }
             }
-            _ => {}
+            PresetData::Builtin(builtin) => {
+                let state = builtin.table().get(0).get(0);
+                stat(&format!("constant, the output is always {}", state as u8));
+            }
+        }
+        if matches!(&preset.data, PresetData::Chip(_) | PresetData::CombGate(_))
+            && preset.data.num_outputs() == 0
+        {
+            stat("no outputs, so nothing can be linked from it");
         }
         let (stat_str, can_del, can_load) = match &preset.src {
             PresetSource::Default => ("source: default", false, false),
@@ -108,21 +298,41 @@ pub fn show_library_menu(
             PresetSource::Board(_) => ("source: user created", true, true),
         };
         stat(stat_str);
+        if let PresetData::Chip(_) | PresetData::CombGate(_) = &preset.data {
+            action.set(show_pin_names(ui, &mut menu.pin_names, &name, preset));
+        }
 
-        let [mut load, mut delete, mut place] = [false; 3];
+        if let PresetData::CombGate(_) = &preset.data {
+            let rs = ui
+                .button("export CSV")
+                .on_hover_text("save the truth table, a row for every input combination");
+            if rs.clicked() {
+                action = AppAction::ExportCsv(name.clone());
+            }
+        }
+        let [mut load, mut delete, mut place, mut pin] = [false; 4];
         ui.horizontal(|ui| {
-            if debug && ui.button("debug").clicked() {
+            if settings.debug && ui.button("debug").clicked() {
                 println!("{:#?}", preset);
             }
             delete = ui.add_enabled(can_del, Button::new("delete")).clicked();
             load = ui.add_enabled(can_load, Button::new("load")).clicked();
             place = ui.button("place").clicked();
+            let pin_label = match settings.favorite_presets.contains(&name) {
+                true => "unpin",
+                false => "pin",
+            };
+            pin = ui
+                .button(pin_label)
+                .on_hover_text("show in the top panel")
+                .clicked();
         });
         ui.separator();
-        match (load, delete, place) {
-            (true, _, _) => action = AppAction::LoadPreset(name),
-            (_, true, _) => action = AppAction::DeletePreset(name),
-            (_, _, true) => action = AppAction::HoldPreset(name),
+        match (load, delete, place, pin) {
+            (true, _, _, _) => action = AppAction::LoadPreset(name),
+            (_, true, _, _) => action = AppAction::DeletePreset(name),
+            (_, _, true, _) => action = AppAction::HoldPreset(name),
+            (_, _, _, true) => action = AppAction::ToggleFavorite(name),
             _ => {}
         }
     }
@@ -151,7 +361,13 @@ pub fn show_library_menu(
     action
 }
 
-pub fn show_pack_menu(ui: &mut Ui, menu: &mut PackMenu, library: &Library) -> AppAction {
+pub fn show_pack_menu(
+    ui: &mut Ui,
+    menu: &mut PackMenu,
+    settings: &mut Settings,
+    library: &Library,
+    board: &Board,
+) -> AppAction {
     let mut action = AppAction::default();
     ui.heading("Pack chip");
     ui.separator();
@@ -160,20 +376,136 @@ pub fn show_pack_menu(ui: &mut Ui, menu: &mut PackMenu, library: &Library) -> Ap
     ui.text_edit_singleline(&mut menu.name);
 
     ui.label("Category");
+    let prev_cat = menu.cat.clone();
     ui.menu_button(menu.cat.clone(), |ui| {
         show_cat_menu(ui, &mut menu.cat, library);
     });
+    // chips start with the color of their category
+    if menu.cat != prev_cat {
+        if let Some(color) = settings.cat_colors.get(&menu.cat) {
+            menu.color = *color;
+        }
+    }
 
     ui.label("Color");
-    ui.color_edit_button_srgba(&mut menu.color);
+    ui.horizontal(|ui| {
+        ui.color_edit_button_srgba(&mut menu.color);
+        let cat_color = settings.cat_colors.get(&menu.cat);
+        let rs = ui
+            .add_enabled(
+                cat_color != Some(&menu.color),
+                Button::new("use for category"),
+            )
+            .on_hover_text("new chips in this category start with this color");
+        if rs.clicked() {
+            settings.cat_colors.insert(menu.cat.clone(), menu.color);
+            settings.modified = true;
+        }
+    });
+    if let Some((cat, color)) = &settings.last_pack {
+        let same = *cat == menu.cat && *color == menu.color;
+        let rs = ui
+            .add_enabled(!same, Button::new("same as last"))
+            .on_hover_text(format!(
+                "the category and color of the last packed chip ({cat})"
+            ));
+        if rs.clicked() {
+            menu.use_last_pack(settings);
+        }
+    }
+
+    ui.add_space(10.0);
+    if ui.button("check library for same logic").clicked() {
+        action = AppAction::FindEquivalent;
+    }
+    if let Some(equivalent) = &menu.equivalent {
+        if equivalent.is_empty() {
+            ui.weak("no preset has the same logic");
+        }
+        for name in equivalent {
+            ui.horizontal(|ui| {
+                ui.label(format!("same as {name}"));
+                if ui.button("place").clicked() {
+                    action = AppAction::HoldPreset(name.clone());
+                }
+            });
+        }
+    }
+    let rs = ui.button("check for loops").on_hover_text(
+        "if it settles with every input combination, the devices of a loop get selected",
+    );
+    if rs.clicked() {
+        action = AppAction::CheckSettles;
+    }
+    if menu.settles == Some(true) {
+        ui.weak("settles with every input combination");
+    }
+    if let Some(err) = &menu.err {
+        ui.colored_label(Color32::RED, err);
+    }
 
-    ui.add_space(50.0);
+    if board.has_link_delays() {
+        ui.colored_label(Color32::YELLOW, "link delays aren't kept in packed chips");
+    }
+
+    ui.add_space(40.0);
+    ui.checkbox(&mut menu.combinational, "combinational")
+        .on_hover_text("pack it as a truth table, which is faster but can't keep any state");
+    ui.checkbox(&mut menu.clear_board, "clear board after packing");
+    ui.horizontal(|ui| {
+        ui.label("then");
+        ui.radio_value(&mut menu.after_pack, AfterPack::Nothing, "nothing");
+        ui.radio_value(&mut menu.after_pack, AfterPack::Place, "place it");
+        ui.radio_value(&mut menu.after_pack, AfterPack::Hold, "hold it");
+    });
     if ui.button("Done").clicked() {
         action = AppAction::PackBoard;
     }
     action
 }
-pub fn show_sim_menu(ui: &mut Ui, menu: &mut SimMenu) -> AppAction {
+/// Edits the input and output names of a preset, without packing it again
+fn show_pin_names(
+    ui: &mut Ui,
+    editing: &mut Option<(String, Vec<String>, Vec<String>)>,
+    name: &str,
+    preset: &DevicePreset,
+) -> AppAction {
+    let mut action = AppAction::None;
+    let (inputs, outputs) = (preset.data.input_names(), preset.data.output_names());
+    if !matches!(editing, Some((editing, _, _)) if editing == name) {
+        *editing = Some((name.to_owned(), inputs.to_vec(), outputs.to_vec()));
+    }
+    let Some((_, new_inputs, new_outputs)) = editing else { return action };
+
+    ui.collapsing("pin names", |ui| {
+        Grid::new("pin_names").num_columns(2).show(ui, |ui| {
+            for (label, names) in [("input", &mut *new_inputs), ("output", &mut *new_outputs)] {
+                for (idx, name) in names.iter_mut().enumerate() {
+                    ui.label(format!("{label} {idx}"));
+                    ui.text_edit_singleline(name);
+                    ui.end_row();
+                }
+            }
+        });
+        let changed = new_inputs.as_slice() != inputs || new_outputs.as_slice() != outputs;
+        ui.horizontal(|ui| {
+            if ui.add_enabled(changed, Button::new("save")).clicked() {
+                action = AppAction::SetPinNames(
+                    name.to_owned(),
+                    new_inputs.clone(),
+                    new_outputs.clone(),
+                );
+            }
+            if ui.add_enabled(changed, Button::new("revert")).clicked() {
+                *new_inputs = inputs.to_vec();
+                *new_outputs = outputs.to_vec();
+            }
+        });
+    });
+    action
+}
+
+pub fn show_sim_menu(ui: &mut Ui, menu: &mut SimMenu, board: &mut Board) -> AppAction {
     let mut action = AppAction::default();
     ui.heading("Sim");
     ui.separator();
@@ -182,13 +514,25 @@ pub fn show_sim_menu(ui: &mut Ui, menu: &mut SimMenu) -> AppAction {
         true => "Unpause",
         false => "Pause",
     };
-    if ui.button(pause_label).clicked() {
+    let pause_button = ui
+        .add_enabled(!board.meta.manual_only, Button::new(pause_label))
+        .on_disabled_hover_text("this board is manual only, see the board info");
+    if pause_button.clicked() {
         menu.paused = !menu.paused;
     }
 
     if ui.add_enabled(menu.paused, Button::new("Step")).clicked() {
         action = AppAction::StepSim;
     }
+    if ui.button("Reset").clicked() {
+        action = AppAction::ResetSim;
+    }
+    let repair = ui.button("Repair").on_hover_text(
+        "recompute outputs that got stuck after editing the board, keeping the input states",
+    );
+    if repair.clicked() {
+        action = AppAction::RepairSim;
+    }
     ui.group(|ui| {
         ui.label("speed");
 
@@ -202,9 +546,225 @@ pub fn show_sim_menu(ui: &mut Ui, menu: &mut SimMenu) -> AppAction {
             ui.label(format!("{}", menu.speed));
         });
     });
+    if ui.button("input vectors").clicked() {
+        action = AppAction::ToggleVectorRunner;
+    }
+    let fit = ui.button("zoom to fit").on_hover_text(format!(
+        "{}, frames everything on the board",
+        Input::display_command(Key::F)
+    ));
+    if fit.clicked() {
+        action = AppAction::ZoomToFit;
+    }
+    ui.checkbox(&mut menu.show_writes, "show pending writes");
+    ui.checkbox(&mut menu.show_profiler, "show profiler");
+    ui.checkbox(&mut menu.show_legend, "show link color legend");
+    ui.checkbox(&mut menu.outputs_as_number, "show outputs as a number")
+        .on_hover_text("read the outputs as one unsigned number, the top output is the lowest bit");
+    ui.checkbox(&mut menu.print_preview, "print preview")
+        .on_hover_text("black on white, like exports in the print style");
+    show_seed(ui, board);
+    action.set(show_period(ui, menu));
+    action.set(show_input_counter(ui, menu, board));
+    show_assertions(ui, board);
+    show_unconnected(ui, menu, board);
+    action.set(show_bookmarks(ui, board));
+    action
+}
+
+/// Lists the camera bookmarks of the board, for renaming and jumping to them
+pub fn show_bookmarks(ui: &mut Ui, board: &mut Board) -> AppAction {
+    let mut action = AppAction::None;
+    let mut remove = None;
+    ui.collapsing("bookmarks", |ui| {
+        for bookmark in &mut board.meta.bookmarks {
+            ui.horizontal(|ui| {
+                let keybind = Input::display_command(Key::Num1);
+                let keybind = keybind.as_str().replace("Num1", "");
+                ui.label(format!("{}{}", keybind, bookmark.slot));
+                if ui.text_edit_singleline(&mut bookmark.name).changed() {
+                    board.modified = true;
+                }
+                if ui.small_button("go").clicked() {
+                    action = AppAction::JumpToBookmark(bookmark.slot);
+                }
+                if ui.small_button("x").clicked() {
+                    remove = Some(bookmark.slot);
+                }
+            });
+        }
+        if let Some(slot) = board.free_bookmark_slot() {
+            let add = ui
+                .button("add bookmark")
+                .on_hover_text("save the current view, Shift + the keybind saves to a slot");
+            if add.clicked() {
+                action = AppAction::SetBookmark(slot);
+            }
+        }
+    });
+    if let Some(slot) = remove {
+        board
+            .meta
+            .bookmarks
+            .retain(|bookmark| bookmark.slot != slot);
+        board.modified = true;
+    }
+    action
+}
+
+/// Lists the device inputs and outputs that no link goes to
+pub fn show_unconnected(ui: &mut Ui, menu: &mut SimMenu, board: &Board) {
+    const MAX_LISTED: usize = 20;
+
+    ui.group(|ui| {
+        let targets = board.unconnected_targets();
+        ui.label(format!("unconnected ({})", targets.len()));
+        ui.checkbox(&mut menu.show_unconnected, "outline them");
+
+        for target in targets.iter().take(MAX_LISTED) {
+            let text = match target {
+                LinkTarget::DeviceInput(device, input) => {
+                    let preset = &board.devices.get(device).unwrap().preset;
+                    format!("{preset} input {input}")
+                }
+                LinkTarget::Output(output) => {
+                    match board.outputs.get(output).unwrap().io.name.trim() {
+                        "" => String::from("output (no-name)"),
+                        name => format!("output {name}"),
+                    }
+                }
+            };
+            ui.weak(text);
+        }
+        if targets.len() > MAX_LISTED {
+            ui.weak(format!("and {} more", targets.len() - MAX_LISTED));
+        }
+    });
+}
+
+/// Steps through every combination of the board inputs, counting them as one number
+pub fn show_input_counter(ui: &mut Ui, menu: &mut SimMenu, board: &Board) -> AppAction {
+    let mut action = AppAction::None;
+    ui.group(|ui| {
+        ui.checkbox(&mut menu.input_counter, "count inputs")
+            .on_hover_text("PageUp and PageDown step through every combination of the inputs, the top input is the lowest bit");
+        if !menu.input_counter {
+            return;
+        }
+        ui.horizontal(|ui| {
+            if ui.small_button("-").clicked() {
+                action = AppAction::StepInputs(-1);
+            }
+            if ui.small_button("+").clicked() {
+                action = AppAction::StepInputs(1);
+            }
+            let combinations = match board.inputs.len() {
+                len if len >= 64 => String::from("2^64"),
+                len => format!("{}", 1u64 << len),
+            };
+            ui.label(format!(
+                "combination {} of {combinations}",
+                board.input_combination()
+            ));
+        });
+    });
     action
 }
 
+/// Fixes the seed of the random write delays, so glitches can be reproduced
+pub fn show_seed(ui: &mut Ui, board: &mut Board) {
+    ui.horizontal(|ui| {
+        let mut fixed = board.seed.is_some();
+        let rs = ui
+            .checkbox(&mut fixed, "fixed seed")
+            .on_hover_text("the random write delays are the same every time the sim is reset");
+        if rs.changed() {
+            match fixed {
+                true => board.reseed(0),
+                false => board.seed = None,
+            }
+            board.modified = true;
+        }
+        if let Some(mut seed) = board.seed {
+            if ui.add(DragValue::new(&mut seed)).changed() {
+                board.reseed(seed);
+                board.modified = true;
+            }
+        }
+    });
+}
+
+/// Measures how often the outputs of an oscillating board repeat
+pub fn show_period(ui: &mut Ui, menu: &mut SimMenu) -> AppAction {
+    let mut action = AppAction::None;
+    ui.group(|ui| {
+        ui.label("oscillation period");
+        ui.horizontal(|ui| {
+            ui.add(
+                DragValue::new(&mut menu.period_ticks)
+                    .clamp_range(10..=10000)
+                    .suffix(" ticks"),
+            );
+            let rs = ui.button("measure").on_hover_text(
+                "simulate a copy of the board and find how often its outputs repeat",
+            );
+            if rs.clicked() {
+                action = AppAction::MeasurePeriod;
+            }
+        });
+        match menu.period {
+            Some(Period::Settled(ticks)) => ui.label(format!("settled after {ticks} ticks")),
+            Some(Period::Oscillates(ticks)) => ui.label(format!("repeats every {ticks} ticks")),
+            Some(Period::Jitters(ticks)) => ui
+                .label(format!("repeats every ~{ticks:.1} ticks"))
+                .on_hover_text("the random write delays make the period change between cycles"),
+            Some(Period::Unknown) => ui.label("did not settle or oscillate"),
+            None => ui.small("not measured"),
+        };
+    });
+    action
+}
+
+pub fn show_assertions(ui: &mut Ui, board: &mut Board) {
+    ui.group(|ui| {
+        ui.label("expected outputs");
+
+        for id in board.outputs_sorted() {
+            let output = board.outputs.get_mut(&id).unwrap();
+            let name = match output.io.name.trim() {
+                "" => String::from("no-name"),
+                name => name.to_owned(),
+            };
+            ui.horizontal(|ui| {
+                let color = match output.failed() {
+                    true => Color32::RED,
+                    false => ui.visuals().text_color(),
+                };
+                ui.colored_label(color, format!("{name}: {}", output.io.state as u8));
+
+                let expected = match output.expected {
+                    None => "-",
+                    Some(false) => "0",
+                    Some(true) => "1",
+                };
+                // cycles through none -> 0 -> 1 -> none
+                if ui.button(expected).clicked() {
+                    output.expected = match output.expected {
+                        None => Some(false),
+                        Some(false) => Some(true),
+                        Some(true) => None,
+                    };
+                }
+            });
+        }
+        match board.assertions_pass() {
+            Some(true) => ui.colored_label(Color32::GREEN, "PASS"),
+            Some(false) => ui.colored_label(Color32::RED, "FAIL"),
+            None => ui.label("no expected outputs"),
+        };
+    });
+}
+
 pub fn show_cat_menu(ui: &mut Ui, cat: &mut String, library: &Library) {
     const LEFT_SP: f32 = 15.0;
 
@@ -232,11 +792,47 @@ pub fn show_cat_menu(ui: &mut Ui, cat: &mut String, library: &Library) {
     }
 }
 
-pub fn show_top_panel(ui: &mut Ui) -> AppAction {
+pub fn show_top_panel(
+    ui: &mut Ui,
+    native: bool,
+    title: &str,
+    favorites: &[String],
+    save_status: &str,
+    boards: &mut BoardsMenu,
+) -> AppAction {
     let mut action = AppAction::None;
     if ui.button("Settings").clicked() {
         action = AppAction::OpenSettings;
     }
+    if ui.button("Save").on_hover_text(save_status).clicked() {
+        action = AppAction::SaveAll;
+    }
+    ui.weak(save_status);
+    if native {
+        ui.menu_button("File", |ui| {
+            let mut button = |label: &str, new_action: AppAction| {
+                if ui.button(label).clicked() {
+                    action = new_action;
+                    ui.close_menu();
+                }
+            };
+            button("Import board", AppAction::ImportBoard);
+            button("Export board", AppAction::ExportBoard);
+            button("Import netlist", AppAction::ImportNetlist);
+            button("Export netlist", AppAction::ExportNetlist);
+            button("Import presets bundle", AppAction::ImportBundle);
+            button("Export presets bundle", AppAction::ExportLibrary);
+            ui.separator();
+            if ui.button("Quit").clicked() {
+                action = AppAction::Quit;
+                ui.close_menu();
+            }
+        });
+        let rs = ui.menu_button("Boards", |ui| action.set(show_boards_menu(ui, boards)));
+        if rs.response.clicked() {
+            action = AppAction::ListBoards;
+        }
+    }
     if ui.button("Library").clicked() {
         action = AppAction::ToggleLibraryMenu;
     }
@@ -246,9 +842,501 @@ pub fn show_top_panel(ui: &mut Ui) -> AppAction {
     if ui.button("Sim").clicked() {
         action = AppAction::ToggleSimMenu;
     }
+    if ui.button("Board info").clicked() {
+        action = AppAction::ToggleBoardInfo;
+    }
+    if !favorites.is_empty() {
+        ui.separator();
+        for name in favorites {
+            if ui.button(name).on_hover_text("hold for placing").clicked() {
+                action = AppAction::HoldPreset(name.clone());
+            }
+        }
+    }
+    if !title.is_empty() {
+        ui.separator();
+        ui.strong(title);
+    }
+    action
+}
+
+fn color_swatch(ui: &mut Ui, color: Color32, selected: bool) -> Response {
+    let size = Vec2::splat(ui.spacing().interact_size.y);
+    let (rect, rs) = ui.allocate_exact_size(size, Sense::click());
+    ui.painter().rect_filled(rect, 2.0, color);
+    if selected {
+        ui.painter()
+            .rect_stroke(rect, 2.0, Stroke::new(2.0, Color32::WHITE));
+    }
+    rs
+}
+
+pub fn show_link_color(ui: &mut Ui, links: &mut CreateLinks, palette: Palette, meta: &BoardMeta) {
+    ui.label("link color");
+    for (idx, colors) in link_palette(palette).iter().enumerate() {
+        let selected = links.rgb.is_none() && links.color == idx;
+        let mut rs = color_swatch(ui, colors[1], selected);
+        if !meta.color_label(idx).is_empty() {
+            rs = rs.on_hover_text(meta.color_label(idx));
+        }
+        if rs.clicked() {
+            links.color = idx;
+            links.rgb = None;
+        }
+    }
+    if links.rgb.is_none() && !meta.color_label(links.color).is_empty() {
+        ui.label(meta.color_label(links.color));
+    }
+    ui.separator();
+
+    let mut custom = links.rgb.is_some();
+    if ui.checkbox(&mut custom, "custom").changed() {
+        links.rgb = custom.then(|| link_palette(palette)[links.color]);
+    }
+    if let Some([off, on]) = &mut links.rgb {
+        ui.label("off");
+        ui.color_edit_button_srgba(off);
+        ui.label("on");
+        ui.color_edit_button_srgba(on);
+    }
+}
+
+pub struct TruthTableWindow {
+    pub title: String,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub table: Result<TruthTable, &'static str>,
+}
+
+/// Returns false if the window was closed
+pub fn show_truth_table(ctx: &Context, window: &TruthTableWindow) -> bool {
+    let mut open = true;
+    Window::new(&window.title).open(&mut open).show(ctx, |ui| {
+        let table = match &window.table {
+            Ok(table) => table,
+            Err(err) => {
+                ui.colored_label(Color32::RED, *err);
+                return;
+            }
+        };
+        let name = |names: &[String], idx: usize, prefix: &str| match names.get(idx) {
+            Some(name) if !name.trim().is_empty() => name.clone(),
+            _ => format!("{prefix}{idx}"),
+        };
+        ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            Grid::new("truth_table").striped(true).show(ui, |ui| {
+                for idx in 0..table.num_inputs {
+                    ui.strong(name(&window.inputs, idx, "in"));
+                }
+                ui.separator();
+                for idx in 0..table.num_outputs {
+                    ui.strong(name(&window.outputs, idx, "out"));
+                }
+                ui.end_row();
+
+                for (input, output) in table.rows().enumerate() {
+                    for idx in 0..table.num_inputs {
+                        ui.label(format!("{}", (input >> idx) & 1));
+                    }
+                    ui.separator();
+                    for idx in 0..table.num_outputs {
+                        ui.label(format!("{}", output.get(idx) as u8));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    });
+    open
+}
+
+/// The input vector, and the outputs after it settled
+pub type VectorResult = (Vec<bool>, Result<Vec<bool>, &'static str>);
+
+#[derive(Clone)]
+pub struct VectorRunner {
+    pub open: bool,
+    /// One input vector per line, either like "0101" or "0,1,0,1"
+    pub vectors: String,
+    pub max_updates: usize,
+    /// The input and output names of the board when it was last run
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub results: Vec<VectorResult>,
+    pub err: Option<String>,
+}
+impl VectorRunner {
+    /// Empty lines, comments (#) and a header line (like in a CSV) are skipped
+    pub fn parse_vectors(&self) -> Result<Vec<Vec<bool>>, String> {
+        let mut vectors = Vec::new();
+        let mut first = true;
+        for (idx, line) in self.vectors.lines().map(str::trim).enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bits: String = line
+                .chars()
+                .filter(|c| *c != ',' && !c.is_whitespace())
+                .collect();
+            if bits.chars().all(|c| c == '0' || c == '1') {
+                vectors.push(bits.chars().map(|c| c == '1').collect());
+            } else if !first {
+                return Err(format!("Invalid vector on line {}: {line:?}", idx + 1));
+            }
+            first = false;
+        }
+        Ok(vectors)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let name = |names: &[String], idx: usize, prefix: &str| match names[idx].trim() {
+            "" => format!("{prefix}{idx}"),
+            name => name.replace(',', " "),
+        };
+        let mut header: Vec<String> = (0..self.inputs.len())
+            .map(|idx| name(&self.inputs, idx, "in"))
+            .collect();
+        header.extend((0..self.outputs.len()).map(|idx| name(&self.outputs, idx, "out")));
+
+        let mut csv = header.join(",");
+        csv.push('\n');
+        for (inputs, outputs) in &self.results {
+            let mut row: Vec<String> = (0..self.inputs.len())
+                .map(|idx| match inputs.get(idx) {
+                    Some(state) => format!("{}", *state as u8),
+                    None => String::new(),
+                })
+                .collect();
+            match outputs {
+                Ok(outputs) => row.extend(outputs.iter().map(|state| format!("{}", *state as u8))),
+                Err(err) => row.push(String::from(*err)),
+            }
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+impl Default for VectorRunner {
+    fn default() -> Self {
+        Self {
+            open: false,
+            vectors: String::new(),
+            max_updates: 1000,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            results: Vec::new(),
+            err: None,
+        }
+    }
+}
+
+/// Loading and exporting CSV files is only available on native
+pub fn show_vector_runner(ui: &mut Ui, runner: &mut VectorRunner, native: bool) -> AppAction {
+    let mut action = AppAction::None;
+    ui.label("One vector per line, inputs ordered top to bottom");
+    ScrollArea::vertical()
+        .id_source("vectors")
+        .max_height(150.0)
+        .show(ui, |ui| {
+            ui.add(
+                TextEdit::multiline(&mut runner.vectors)
+                    .code_editor()
+                    .hint_text("0101"),
+            );
+        });
+    ui.horizontal(|ui| {
+        ui.label("max updates");
+        ui.add(DragValue::new(&mut runner.max_updates).clamp_range(1..=100_000));
+    });
+    ui.horizontal(|ui| {
+        if ui.button("Run").clicked() {
+            action = AppAction::RunVectors;
+        }
+        if !native {
+            return;
+        }
+        if ui.button("Load CSV").clicked() {
+            action = AppAction::ImportVectors;
+        }
+        let can_export = !runner.results.is_empty();
+        if ui
+            .add_enabled(can_export, Button::new("Export CSV"))
+            .clicked()
+        {
+            action = AppAction::ExportVectors;
+        }
+    });
+    if let Some(err) = &runner.err {
+        ui.colored_label(Color32::RED, err);
+    }
+    if runner.results.is_empty() {
+        return action;
+    }
+    ui.separator();
+
+    let name = |names: &[String], idx: usize, prefix: &str| match names[idx].trim() {
+        "" => format!("{prefix}{idx}"),
+        name => name.to_owned(),
+    };
+    ScrollArea::vertical()
+        .id_source("vector_results")
+        .max_height(300.0)
+        .show(ui, |ui| {
+            Grid::new("vector_results").striped(true).show(ui, |ui| {
+                for idx in 0..runner.inputs.len() {
+                    ui.strong(name(&runner.inputs, idx, "in"));
+                }
+                ui.separator();
+                for idx in 0..runner.outputs.len() {
+                    ui.strong(name(&runner.outputs, idx, "out"));
+                }
+                ui.end_row();
+
+                for (inputs, outputs) in &runner.results {
+                    for idx in 0..runner.inputs.len() {
+                        match inputs.get(idx) {
+                            Some(state) => ui.label(format!("{}", *state as u8)),
+                            None => ui.weak("-"),
+                        };
+                    }
+                    ui.separator();
+                    match outputs {
+                        Ok(outputs) => {
+                            for state in outputs {
+                                ui.label(format!("{}", *state as u8));
+                            }
+                        }
+                        Err(err) => {
+                            ui.colored_label(Color32::RED, *err);
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    action
+}
+
+#[derive(Clone, Default)]
+pub struct RefreshPrompt {
+    pub open: bool,
+    /// The presets whose pin counts changed in the last refresh
+    pub changed: Vec<String>,
+}
+
+pub fn show_refresh_prompt(ui: &mut Ui, prompt: &mut RefreshPrompt) -> AppAction {
+    let mut action = AppAction::None;
+    if prompt.changed.is_empty() {
+        ui.label("The library was reloaded, update the placed devices to match it?");
+        ui.horizontal(|ui| {
+            if ui.button("Update").clicked() {
+                action = AppAction::RefreshDevices;
+            }
+            if ui.button("Skip").clicked() {
+                prompt.open = false;
+            }
+        });
+        return action;
+    }
+    ui.colored_label(
+        Color32::YELLOW,
+        "The pins of these presets changed, links to removed pins were dropped:",
+    );
+    for name in &prompt.changed {
+        ui.label(name);
+    }
+    if ui.button("Ok").clicked() {
+        prompt.open = false;
+    }
     action
 }
 
+#[derive(Clone, Default)]
+pub struct QuitPrompt {
+    pub open: bool,
+    /// If saving failed on the last try, so quitting was canceled
+    pub save_failed: bool,
+}
+
+pub fn show_quit_prompt(ui: &mut Ui, prompt: &mut QuitPrompt) -> AppAction {
+    let mut action = AppAction::None;
+    ui.label("There are unsaved changes. Save before quitting?");
+    if prompt.save_failed {
+        ui.colored_label(Color32::RED, "Saving failed, see the log for details");
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Save").clicked() {
+            action = AppAction::SaveAndQuit;
+        }
+        if ui.button("Discard").clicked() {
+            action = AppAction::DiscardAndQuit;
+        }
+        if ui.button("Cancel").clicked() {
+            *prompt = QuitPrompt::default();
+        }
+    });
+    action
+}
+
+#[derive(Clone, Default)]
+pub struct LoadPrompt {
+    pub open: bool,
+    /// The preset whose board would replace the current one
+    pub preset: String,
+}
+
+pub fn show_load_prompt(ui: &mut Ui, prompt: &mut LoadPrompt) -> AppAction {
+    let mut action = AppAction::None;
+    ui.label(format!(
+        "Loading the board of {} replaces the current board, which has unsaved changes.",
+        prompt.preset
+    ));
+    ui.horizontal(|ui| {
+        if ui.button("Load anyway").clicked() {
+            action = AppAction::ForceLoadPreset(prompt.preset.clone());
+        }
+        if ui.button("Cancel").clicked() {
+            *prompt = LoadPrompt::default();
+        }
+    });
+    action
+}
+
+/// The boards saved by name in the config directory, native only
+#[derive(Clone, Default)]
+pub struct BoardsMenu {
+    /// The name to save the board as
+    pub name: String,
+    /// The names of the saved boards, filled in by the integration
+    pub saved: Vec<String>,
+    /// The saved board that's open, which saving writes to.
+    /// `None` is the default board file.
+    pub current: Option<String>,
+}
+
+pub fn show_boards_menu(ui: &mut Ui, menu: &mut BoardsMenu) -> AppAction {
+    let mut action = AppAction::None;
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut menu.name);
+        let name = menu.name.trim();
+        // the name is used as the file name
+        let valid = !name.is_empty() && !name.contains(['/', '\\', '.']);
+        if ui.add_enabled(valid, Button::new("Save as")).clicked() {
+            action = AppAction::SaveBoardAs(name.to_owned());
+            ui.close_menu();
+        }
+    });
+    ui.separator();
+    if menu.saved.is_empty() {
+        ui.weak("no saved boards");
+    }
+    for name in &menu.saved {
+        ui.horizontal(|ui| {
+            match menu.current.as_ref() == Some(name) {
+                true => ui.strong(name),
+                false => ui.label(name),
+            };
+            if ui.small_button("open").clicked() {
+                action = AppAction::OpenBoard(name.clone());
+                ui.close_menu();
+            }
+            if ui.small_button("x").clicked() {
+                action = AppAction::DeleteBoard(name.clone());
+            }
+        });
+    }
+    action
+}
+
+#[derive(Clone, Default)]
+pub struct BoardInfoMenu {
+    pub open: bool,
+    /// The comma separated tags being edited
+    pub tags_field: String,
+}
+
+/// Lists the labeled link colors, for explaining the colors of a board
+pub fn show_color_legend(ui: &mut Ui, palette: Palette, meta: &BoardMeta) {
+    Frame::popup(ui.style()).show(ui, |ui| {
+        ui.vertical(|ui| {
+            let mut empty = true;
+            for (idx, colors) in link_palette(palette).iter().enumerate() {
+                let label = meta.color_label(idx);
+                if label.is_empty() {
+                    continue;
+                }
+                empty = false;
+                ui.horizontal(|ui| {
+                    color_swatch(ui, colors[1], false);
+                    ui.label(label);
+                });
+            }
+            if empty {
+                ui.weak("no link colors are labeled, see board info");
+            }
+        });
+    });
+}
+
+/// Returns true if the info was edited
+pub fn show_board_info(
+    ui: &mut Ui,
+    menu: &mut BoardInfoMenu,
+    meta: &mut BoardMeta,
+    palette: Palette,
+) -> bool {
+    let mut changed = false;
+    Grid::new("board_info").num_columns(2).show(ui, |ui| {
+        ui.label("Title");
+        changed |= ui.text_edit_singleline(&mut meta.title).changed();
+        ui.end_row();
+
+        ui.label("Author");
+        changed |= ui.text_edit_singleline(&mut meta.author).changed();
+        ui.end_row();
+
+        ui.label("Tags");
+        if ui.text_edit_singleline(&mut menu.tags_field).changed() {
+            changed = true;
+            meta.tags = menu
+                .tags_field
+                .split(',')
+                .map(|tag| tag.trim().to_owned())
+                .filter(|tag| !tag.is_empty())
+                .collect();
+        }
+        ui.end_row();
+    });
+    ui.label("Description");
+    changed |= ui.text_edit_multiline(&mut meta.description).changed();
+
+    ui.label("Link colors");
+    Grid::new("color_labels").num_columns(2).show(ui, |ui| {
+        for (idx, colors) in link_palette(palette).iter().enumerate() {
+            color_swatch(ui, colors[1], false);
+            let mut label = meta.color_label(idx).to_owned();
+            if ui.text_edit_singleline(&mut label).changed() {
+                meta.set_color_label(idx, label);
+                changed = true;
+            }
+            ui.end_row();
+        }
+    });
+
+    changed |= ui
+        .checkbox(&mut meta.exact_sim, "save the exact sim state")
+        .on_hover_text(
+            "also save writes that weren't flushed and the random delays, so a paused simulation resumes exactly",
+        )
+        .changed();
+    changed |= ui
+        .checkbox(&mut meta.manual_only, "manual only")
+        .on_hover_text("always open paused, so the board only advances on Step")
+        .changed();
+    changed
+}
+
 #[derive(Clone)]
 pub struct ChipPlacer {
     // A search query into self.library
@@ -426,7 +1514,11 @@ impl NamePopup {
         col_w: f32,
         t: Transform,
     ) -> Option<Self> {
-        let size = vec2(100.0, 30.0);
+        let group_id = board.get_io(self.ty, self.id).unwrap().group_member;
+        let size = match group_id {
+            Some(_) => vec2(280.0, 30.0),
+            None => vec2(100.0, 30.0),
+        };
         let pos = self.calc_pos(size, board, col_w, t);
 
         let mut ui = ui.child_ui(Rect::from_min_size(pos, size), ui.layout().clone());
@@ -437,26 +1529,54 @@ impl NamePopup {
                 let io = board.mut_io(self.ty, self.id).unwrap();
 
                 let rs = ui.text_edit_singleline(&mut io.name);
-                let result = rs.lost_focus();
+                board.modified |= rs.changed();
+                let done = rs.lost_focus();
+                let enter = ui.input().key_pressed(Key::Enter);
+
+                // the display options of the group the pin is in
+                let group = group_id.and_then(|id| board.mut_io_group(self.ty, id));
+                if let Some(group) = group {
+                    let mut changed = false;
+                    changed |= ui.checkbox(&mut group.lsb_top, "lsb top").changed();
+                    changed |= ui.checkbox(&mut group.signed, "signed").changed();
+                    changed |= ui.checkbox(&mut group.hex, "hex").changed();
+                    board.modified |= changed;
+                }
                 rs.request_focus();
-                result
+                (done, enter)
             })
             .inner
         });
-        self.hovered = rs.response.hovered();
-        if rs.inner {
+        self.hovered = ui.rect_contains_pointer(rs.response.rect);
+        // Enter or clicking outside of the popup finishes editing,
+        // clicking a checkbox only takes the focus for a moment
+        let (done, enter) = rs.inner;
+        if done && (enter || !self.hovered) {
             return None;
         }
         // let rs = rs.response.interact(Sense::click());
         Some(self)
     }
-    fn show_name(mut self, ui: &mut Ui, board: &Board, col_w: f32, t: Transform) -> Option<Self> {
+    fn show_name(
+        mut self,
+        ui: &mut Ui,
+        board: &mut Board,
+        col_w: f32,
+        t: Transform,
+    ) -> Option<Self> {
         if self.timer == 0 {
             return None;
         }
         self.timer -= 1;
 
-        let size = vec2(100.0, 30.0);
+        let force = match self.ty {
+            IoSel::Input => Some(board.inputs.get(&self.id).unwrap().force),
+            IoSel::Output => None,
+        };
+        let size = match force {
+            Some(_) => vec2(150.0, 30.0),
+            None => vec2(100.0, 30.0),
+        };
         let pos = self.calc_pos(size, board, col_w, t);
         let name = {
             let mut temp = match self.ty {
@@ -483,14 +1603,34 @@ impl NamePopup {
 
             ui.horizontal_centered(|ui| {
                 ui.label(&name);
-            });
+
+                // mute/solo buttons for inputs
+                let force = force?;
+                let mute = ui.selectable_label(force == Some(false), "M");
+                let solo = ui.selectable_label(force == Some(true), "S");
+                let toggle = |value| match force == Some(value) {
+                    true => None,
+                    false => Some(value),
+                };
+                if mute.clicked() {
+                    return Some(toggle(false));
+                }
+                if solo.clicked() {
+                    return Some(toggle(true));
+                }
+                None
+            })
+            .inner
         });
+        let new_force = rs.inner;
         let rs = rs.response.interact(Sense::click());
         self.hovered = rs.hovered();
         if self.hovered {
             self.persist();
         }
-        if rs.clicked() {
+        if let Some(force) = new_force {
+            board.force_input(self.id, force);
+        } else if rs.clicked() {
             self.edit = true;
         }
         Some(self)
@@ -505,6 +1645,73 @@ impl NamePopup {
     }
 }
 
+/// The small card shown after hovering a device for a moment
+pub fn show_device_card(ui: &mut Ui, device: &Device) {
+    ui.strong(&device.preset);
+    let (input, output) = (device.data.input(), device.data.output());
+    ui.label(format!("inputs: {}, outputs: {}", input.len, output.len));
+    match &device.data {
+        DeviceData::CombGate(comb_gate) => {
+            ui.label(format!("truth table: {} rows", comb_gate.table.num_rows()));
+        }
+        DeviceData::Chip(chip) => {
+            ui.label(format!("gates: {}", chip.devices.len()));
+            if chip.is_oscillating() {
+                ui.colored_label(Color32::RED, "oscillating");
+            }
+        }
+    }
+}
+
+/// What a link from a device output carries, and where to
+pub fn show_link_card(
+    ui: &mut Ui,
+    board: &Board,
+    library: &Library,
+    (id, output, link_idx): (u64, usize, usize),
+) {
+    let Some(device) = board.devices.get(&id) else { return };
+    let Some(link) = device.links.get(output).and_then(|links| links.get(link_idx)) else {
+        return;
+    };
+    // the name of a device pin, if the preset is still in the library and named it
+    let pin_name = |device: &Device, idx: usize, output: bool| {
+        let preset = library.get_preset(&device.preset)?;
+        let names = match output {
+            true => preset.data.output_names(),
+            false => preset.data.input_names(),
+        };
+        names.get(idx).filter(|name| !name.is_empty()).cloned()
+    };
+    let pin = |device: &Device, idx: usize, output: bool| {
+        let kind = if output { "output" } else { "input" };
+        match pin_name(device, idx, output) {
+            Some(name) => format!("{} {kind} {idx} ({name})", device.preset),
+            None => format!("{} {kind} {idx}", device.preset),
+        }
+    };
+
+    let state = device.data.output().get(output);
+    ui.strong(if state { "on" } else { "off" });
+    ui.label(format!("from: {}", pin(device, output, true)));
+    let target = match link.target {
+        LinkTarget::DeviceInput(target, input) => match board.devices.get(&target) {
+            Some(target) => pin(target, input, false),
+            None => String::from("?"),
+        },
+        LinkTarget::Output(target) => match board.outputs.get(&target) {
+            Some(output) if !output.io.name.is_empty() => {
+                format!("board output ({})", output.io.name)
+            }
+            _ => String::from("board output"),
+        },
+    };
+    ui.label(format!("to: {target}"));
+    if link.delay > 0 {
+        ui.label(format!("delay: {} updates", link.delay));
+    }
+}
+
 pub fn debug_ui(ui: &mut Ui, app: &mut App) {
     ui.style_mut().wrap = Some(false);
     ui.separator();
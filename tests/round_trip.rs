@@ -0,0 +1,231 @@
+//! Saving and loading (in both encodings the native app saves with) has to give back exactly what was saved
+use egui::{pos2, Color32};
+use logsim::board::{Board, Bookmark, Device};
+use logsim::graphics::View;
+use logsim::presets::{
+    ChipPreset, CombGatePreset, DevicePreset, Library, PresetData, PresetSource,
+};
+use logsim::settings::Settings;
+use logsim::{Link, LinkStart, LinkTarget};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+fn ron_round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+    let text = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::new()).unwrap();
+    ron::de::from_str(&text).unwrap()
+}
+fn bincode_round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+    bincode::deserialize(&bincode::serialize(value).unwrap()).unwrap()
+}
+
+/// An And gate linked between an input and an output, to pack as a chip
+fn and_board(library: &Library) -> Board {
+    let mut board = Board::new();
+    board.add_input(50.0);
+    board.add_input(100.0);
+    board.add_output(75.0);
+    let and = library.get_preset("And").unwrap();
+    board.add_device(1, Device::from_preset(and, pos2(100.0, 60.0)));
+
+    let inputs = board.inputs_sorted();
+    let output = board.outputs_sorted()[0];
+    for (idx, input) in inputs.into_iter().enumerate() {
+        let link = Link::new(LinkTarget::DeviceInput(1, idx), 0, Vec::new());
+        board.add_link(LinkStart::Input(input), link);
+    }
+    let link = Link::new(LinkTarget::Output(output), 0, Vec::new());
+    board.add_link(LinkStart::DeviceOutput(1, 0), link);
+    board
+}
+
+fn chip_preset(library: &Library) -> DevicePreset {
+    let board = and_board(library);
+    DevicePreset {
+        name: String::from("Chip And"),
+        cat: String::from("Chips"),
+        color: [0, 100, 200, 255],
+        data: PresetData::Chip(ChipPreset::from_board(&board)),
+        src: PresetSource::Board(board),
+        glyph: Some(String::from("&")),
+    }
+}
+
+/// A combinational gate with more outputs than fit in a word: every other output is the input inverted
+fn wide_gate_preset(library: &Library) -> DevicePreset {
+    let mut board = Board::new();
+    board.add_input(50.0);
+    let input = board.inputs_sorted()[0];
+    let not = library.get_preset("Not").unwrap();
+    board.add_device(1, Device::from_preset(not, pos2(100.0, 50.0)));
+    let link = Link::new(LinkTarget::DeviceInput(1, 0), 0, Vec::new());
+    board.add_link(LinkStart::Input(input), link);
+
+    for idx in 0..70 {
+        board.add_output(10.0 + idx as f32 * 5.0);
+    }
+    for (idx, output) in board.outputs_sorted().into_iter().enumerate() {
+        let start = match idx % 2 {
+            0 => LinkStart::Input(input),
+            _ => LinkStart::DeviceOutput(1, 0),
+        };
+        board.add_link(start, Link::new(LinkTarget::Output(output), 0, Vec::new()));
+    }
+    // cloning the board drops writes that were never flushed, like the ones `add_link` queues
+    while !board.is_settled() {
+        board.update();
+    }
+    let gate = CombGatePreset::from_board(&mut board.clone(), 8).unwrap();
+    DevicePreset {
+        name: String::from("Wide"),
+        cat: String::from("Chips"),
+        color: [0, 100, 200, 255],
+        data: PresetData::CombGate(gate),
+        src: PresetSource::Board(board),
+        glyph: None,
+    }
+}
+
+/// A board with a chip, stacked IO groups, and colored links with anchors and delays
+fn full_board() -> Board {
+    let library = Library::new();
+    let settings = Settings::default();
+    let chip = chip_preset(&library);
+
+    let mut board = Board::new();
+    board.meta.title = String::from("Round trip");
+    board.meta.tags = vec![String::from("test")];
+    board.meta.set_color_label(2, String::from("clock"));
+    board.meta.bookmarks.push(Bookmark {
+        name: String::from("chip"),
+        slot: 1,
+        view: View::default(),
+    });
+
+    board.add_input(50.0);
+    let first_input = board.inputs_sorted()[0];
+    board.stack_input(first_input, &settings);
+    board.add_output(50.0);
+    let first_output = board.outputs_sorted()[0];
+    board.stack_output(first_output, &settings);
+
+    board.add_device(10, Device::from_preset(&chip, pos2(150.0, 40.0)));
+    let not = library.get_preset("Not").unwrap();
+    board.add_device(11, Device::from_preset(not, pos2(250.0, 60.0)));
+
+    let inputs = board.inputs_sorted();
+    let outputs = board.outputs_sorted();
+    for (idx, input) in inputs.into_iter().enumerate() {
+        let anchors = vec![pos2(100.0, 50.0 + idx as f32 * 10.0), pos2(120.0, 45.0)];
+        let mut link = Link::new(LinkTarget::DeviceInput(10, idx), 2, anchors);
+        link.rgb = Some([Color32::DARK_BLUE, Color32::LIGHT_BLUE]);
+        board.add_link(LinkStart::Input(input), link);
+    }
+    let mut link = Link::new(LinkTarget::DeviceInput(11, 0), 1, vec![pos2(200.0, 70.0)]);
+    link.delay = 3;
+    board.add_link(LinkStart::DeviceOutput(10, 0), link);
+    for output in outputs {
+        board.add_link(
+            LinkStart::DeviceOutput(11, 0),
+            Link::new(LinkTarget::Output(output), 0, Vec::new()),
+        );
+    }
+
+    // leave some writes pending, so the queues aren't empty
+    board.set_input(first_input, true);
+    board.update();
+    board
+}
+
+#[test]
+fn board_round_trip() {
+    let board = full_board();
+    assert_eq!(ron_round_trip(&board), board);
+    assert_eq!(bincode_round_trip(&board), board);
+}
+
+#[test]
+fn exact_board_round_trip() {
+    let mut board = full_board();
+    board.meta.exact_sim = true;
+    board.capture_sim_state();
+    assert!(board.sim_state.is_some());
+    assert_eq!(ron_round_trip(&board), board);
+    assert_eq!(bincode_round_trip(&board), board);
+}
+
+#[test]
+fn preset_round_trip() {
+    let library = Library::new();
+    let mut presets = library.presets().to_vec();
+    presets.push(chip_preset(&library));
+    for preset in &presets {
+        assert_eq!(&ron_round_trip(preset), preset);
+        assert_eq!(&bincode_round_trip(preset), preset);
+    }
+}
+
+#[test]
+fn wide_gate_round_trip() {
+    let library = Library::new();
+    let preset = wide_gate_preset(&library);
+    let PresetData::CombGate(gate) = &preset.data else {
+        panic!("not packed as a combinational gate");
+    };
+    assert_eq!(gate.table.num_outputs, 70);
+    for (input, row) in gate.table.rows().enumerate() {
+        assert_eq!(row.get(68), input == 1);
+        assert_eq!(row.get(69), input == 0);
+    }
+    assert_eq!(ron_round_trip(&preset), preset);
+    assert_eq!(bincode_round_trip(&preset), preset);
+
+    // a placed device keeps all 70 output bits in its state
+    let mut board = Board::new();
+    let mut device = Device::from_preset(&preset, pos2(100.0, 50.0));
+    device.data.set_input(0, true);
+    assert!(device.data.output().get(68));
+    board.add_device(1, device);
+    assert_eq!(ron_round_trip(&board), board);
+    assert_eq!(bincode_round_trip(&board), board);
+}
+
+#[test]
+fn settings_round_trip() {
+    let mut settings = Settings {
+        last_pack: Some((String::from("Chips"), Color32::GOLD)),
+        ..Default::default()
+    };
+    settings
+        .cat_colors
+        .insert(String::from("Chips"), Color32::GOLD);
+    assert!(ron_round_trip(&settings) == settings);
+    assert!(bincode_round_trip(&settings) == settings);
+}
+
+#[test]
+fn old_settings_load() {
+    // settings saved before any of the newer fields existed
+    let text = "(
+        theme: Light,
+        colorful_wires: true,
+        auto_link: false,
+        debug: false,
+        board_color: ((20, 20, 20, 255)),
+        board_io_pin_size: 8.0,
+        board_io_col_color: ((180, 180, 180, 255)),
+        board_io_col_w: 40.0,
+        pin_colors: (((100, 100, 100, 255)), ((255, 0, 0, 255))),
+        link_width: 4.0,
+        link_colors: (((80, 80, 80, 255)), ((200, 0, 0, 255))),
+        device_name_size: 16.0,
+        device_pin_size: 6.0,
+        device_min_pin_spacing: 13.0,
+    )";
+    let settings: Settings = ron::de::from_str(text).unwrap();
+    let default = Settings::default();
+    assert!(settings.theme == logsim::settings::Theme::Light);
+    assert!(settings.colorful_wires);
+    assert!(settings.continuous_redraw == default.continuous_redraw);
+    assert!(settings.write_delay == default.write_delay);
+    assert!(settings.pin_colors == default.pin_colors);
+}
@@ -2,6 +2,7 @@ use eframe::egui::Context;
 use eframe::wasm_bindgen::{self, prelude::*};
 use logsim::app::App;
 use logsim::board::Board;
+use logsim::old_data::OldBoardPreset;
 use logsim::presets::{DevicePreset, Library};
 use logsim::settings::Settings;
 use rfd::AsyncFileDialog;
@@ -48,6 +49,19 @@ pub fn get_os() -> Option<&'static str> {
     Some(os_name)
 }
 
+/// Downloads `text` as a file, the browser decides where it's saved
+fn download_text(file_name: &str, text: &str) -> Option<()> {
+    let parts = js_sys::Array::of1(&JsValue::from_str(text));
+    let blob = web_sys::Blob::new_with_str_sequence(&parts).ok()?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).ok()?;
+    let document = web_sys::window()?.document()?;
+    let anchor: web_sys::HtmlAnchorElement = document.create_element("a").ok()?.dyn_into().ok()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).ok()
+}
+
 macro_rules! console_log {
     ($($t:tt)*) => {{
     	let string = format_args!($($t)*).to_string();
@@ -99,7 +113,16 @@ impl eframe::App for WebApp {
                     let entries = AsyncFileDialog::new().pick_files().await;
                     for entry in entries.unwrap_or(Vec::new()) {
                         let bytes = entry.read().await;
-                        let Ok(preset) = bincode::deserialize::<DevicePreset>(&bytes) else {
+                        let preset = match entry.file_name().ends_with(".json") {
+                            true => serde_json::from_slice::<DevicePreset>(&bytes).ok(),
+                            false => bincode::deserialize::<DevicePreset>(&bytes)
+                                .or_else(|_| {
+                                    bincode::deserialize::<OldBoardPreset>(&bytes)
+                                        .map(OldBoardPreset::update)
+                                })
+                                .ok(),
+                        };
+                        let Some(preset) = preset else {
                         console_log!("failed to parse preset {:?}", entry.file_name());
                         continue;
                     };
@@ -109,6 +132,15 @@ impl eframe::App for WebApp {
                 wasm_bindgen_futures::spawn_local(future);
             }
             logsim::OutEvent::RevealConfigDir => {}
+            logsim::OutEvent::ExportCsv(name) => {
+                let csv = self.app.preset_csv(&name);
+                if csv
+                    .and_then(|csv| download_text(&format!("{name}.csv"), &csv))
+                    .is_none()
+                {
+                    console_log!("failed to export {name:?} as CSV");
+                }
+            }
 
             logsim::OutEvent::LoadBoard => {}
             logsim::OutEvent::LoadLibrary => {}
@@ -118,10 +150,16 @@ impl eframe::App for WebApp {
             logsim::OutEvent::SaveLibrary => {}
             logsim::OutEvent::SaveSettings => {}
 
-            logsim::OutEvent::SaveAll => {}
+            logsim::OutEvent::SaveAll => {
+                // nothing is kept in local storage yet, so saving always fails
+                console_log!("saving isn't supported on the web yet");
+                self.app.saved(ctx, false);
+            }
             _ => {}
         }
 
-        ctx.request_repaint_after(Duration::from_millis(1000 / 60));
+        if self.app.needs_repaint() {
+            ctx.request_repaint_after(Duration::from_millis(1000 / 60));
+        }
     }
 }